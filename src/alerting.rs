@@ -0,0 +1,295 @@
+use crate::database::Database;
+use crate::{Result, Timestamp};
+use anyhow::Error;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout, Duration};
+
+/// Source name of the alerts about the database.
+pub const DATABASE_SOURCE: &'static str = "Database";
+/// Seconds the database may take to respond to a ping of the watcher.
+const PING_TIMEOUT: u64 = 10;
+
+/// A channel for operational alerts about the monitor itself, kept separate
+/// from the report publishers which might be broken as well.
+#[async_trait]
+pub trait SendAlert {
+    async fn send_alert(&self, message: &str) -> Result<()>;
+}
+
+pub struct WebhookAlert {
+    client: Client,
+    url: String,
+}
+
+impl WebhookAlert {
+    pub fn new(url: &str) -> Self {
+        WebhookAlert {
+            client: Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookBody<'a> {
+    text: &'a str,
+}
+
+#[async_trait]
+impl SendAlert for WebhookAlert {
+    async fn send_alert(&self, message: &str) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&WebhookBody { text: message })
+            .send()
+            .await?
+            .error_for_status()
+            .map(|_| ())
+            .map_err(|err| err.into())
+    }
+}
+
+#[derive(Default)]
+struct AlertState {
+    failures: HashMap<String, usize>,
+    last_alerts: HashMap<String, Timestamp>,
+}
+
+impl AlertState {
+    /// Whether an alert for the source may be sent at `now`, at most once
+    /// per `interval` seconds. Marks the alert as sent if so.
+    fn mark_alert(&mut self, source: &str, now: Timestamp, interval: u64) -> bool {
+        if let Some(last) = self.last_alerts.get(source) {
+            if (now - *last).as_secs() < interval {
+                return false;
+            }
+        }
+
+        self.last_alerts.insert(source.to_string(), now);
+        true
+    }
+}
+
+/// Tracks consecutive failures per source and sends a (throttled) alert once
+/// a source keeps failing.
+pub struct OpsAlerter {
+    sender: Arc<dyn SendAlert + Send + Sync>,
+    threshold: usize,
+    interval: u64,
+    state: Mutex<AlertState>,
+}
+
+impl OpsAlerter {
+    pub fn new(sender: Arc<dyn SendAlert + Send + Sync>, threshold: usize, interval: u64) -> Self {
+        OpsAlerter {
+            sender: sender,
+            threshold: threshold,
+            interval: interval,
+            state: Default::default(),
+        }
+    }
+    /// Records a failure of the given source. An alert is sent once the
+    /// source failed `threshold` consecutive times, but at most once per
+    /// `interval` seconds for each source.
+    pub async fn report_failure(&self, source: &str, err: &Error) {
+        let message = {
+            let mut state = self.state.lock().await;
+            let failures = state.failures.entry(source.to_string()).or_insert(0);
+            *failures += 1;
+
+            if *failures < self.threshold {
+                return;
+            }
+
+            let message = format!(
+                "{} failed {} consecutive times, last error: {:?}",
+                source, failures, err
+            );
+
+            if !state.mark_alert(source, Timestamp::now(), self.interval) {
+                return;
+            }
+
+            message
+        };
+
+        self.send(&message).await;
+    }
+    /// Sends an alert about a failure the monitor can't recover from, e.g.
+    /// on startup, without waiting for the threshold.
+    pub async fn report_fatal(&self, source: &str, err: &Error) {
+        let sent = self
+            .state
+            .lock()
+            .await
+            .mark_alert(source, Timestamp::now(), self.interval);
+
+        if sent {
+            self.send(&format!("{} failed: {:?}", source, err)).await;
+        }
+    }
+    async fn send(&self, message: &str) {
+        if let Err(err) = self.sender.send_alert(message).await {
+            error!("Failed to send ops alert: {:?}", err);
+        }
+    }
+    /// Resets the failure counter of the given source.
+    pub async fn report_success(&self, source: &str) {
+        self.state.lock().await.failures.remove(source);
+    }
+}
+
+/// Pings the database every `interval` and reports to the alerter whether it
+/// responds, so a connection lost after startup is noticed.
+pub async fn watch_database(db: Database, alerter: Arc<OpsAlerter>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+
+        match timeout(Duration::from_secs(PING_TIMEOUT), db.ping()).await {
+            Ok(Ok(())) => alerter.report_success(DATABASE_SOURCE).await,
+            Ok(Err(err)) => {
+                warn!("Database did not respond to ping: {:?}", err);
+                alerter.report_failure(DATABASE_SOURCE, &err).await;
+            }
+            Err(_) => {
+                let err = anyhow!("ping timed out after {}s", PING_TIMEOUT);
+                warn!("Database did not respond to ping: {:?}", err);
+                alerter.report_failure(DATABASE_SOURCE, &err).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountAlerts(AtomicUsize);
+
+    #[async_trait]
+    impl SendAlert for CountAlerts {
+        async fn send_alert(&self, _message: &str) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn threshold_and_throttle() {
+        let sender = Arc::new(CountAlerts::default());
+        let alerter = OpsAlerter::new(sender.clone(), 3, 3600);
+
+        // Below threshold.
+        for _ in 0..2 {
+            alerter
+                .report_failure("TransferFetcher", &anyhow!("subscan down"))
+                .await;
+        }
+        assert_eq!(sender.0.load(Ordering::SeqCst), 0);
+
+        // Sustained failures, throttled to a single alert.
+        for _ in 0..10 {
+            alerter
+                .report_failure("TransferFetcher", &anyhow!("subscan down"))
+                .await;
+        }
+        assert_eq!(sender.0.load(Ordering::SeqCst), 1);
+
+        // A success resets the counter, further failures are still throttled.
+        alerter.report_success("TransferFetcher").await;
+        for _ in 0..5 {
+            alerter
+                .report_failure("TransferFetcher", &anyhow!("subscan down"))
+                .await;
+        }
+        assert_eq!(sender.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn throttle_per_source() {
+        let sender = Arc::new(CountAlerts::default());
+        let alerter = OpsAlerter::new(sender.clone(), 1, 3600);
+
+        alerter
+            .report_failure("TransferFetcher", &anyhow!("subscan down"))
+            .await;
+        assert_eq!(sender.0.load(Ordering::SeqCst), 1);
+
+        // The fetcher alert does not suppress the one of the database.
+        alerter
+            .report_failure(DATABASE_SOURCE, &anyhow!("connection lost"))
+            .await;
+        assert_eq!(sender.0.load(Ordering::SeqCst), 2);
+
+        alerter
+            .report_fatal(DATABASE_SOURCE, &anyhow!("connection lost"))
+            .await;
+        assert_eq!(sender.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn fatal_ignores_threshold() {
+        let sender = Arc::new(CountAlerts::default());
+        let alerter = OpsAlerter::new(sender.clone(), 3, 3600);
+
+        alerter
+            .report_fatal(DATABASE_SOURCE, &anyhow!("connection refused"))
+            .await;
+        assert_eq!(sender.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn lost_database_connection() {
+        let sender = Arc::new(CountAlerts::default());
+        let alerter = Arc::new(OpsAlerter::new(sender.clone(), 3, 3600));
+
+        // Nothing listens on the port, so every ping fails.
+        let db = Database::connect_lazy(
+            "mongodb://localhost:1/?serverSelectionTimeoutMS=50",
+            "monitoring_test",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let watcher = tokio::spawn(watch_database(
+            db,
+            Arc::clone(&alerter),
+            Duration::from_millis(10),
+        ));
+
+        // Well above the threshold, still throttled to a single alert.
+        for _ in 0..100 {
+            if alerter.state.lock().await.failures.get(DATABASE_SOURCE) >= Some(&6) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        watcher.abort();
+
+        assert!(alerter.state.lock().await.failures[DATABASE_SOURCE] >= 6);
+        assert_eq!(sender.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn last_alert_in_the_future() {
+        let sender = Arc::new(CountAlerts::default());
+        let alerter = OpsAlerter::new(sender.clone(), 1, 3600);
+
+        // The system clock was set back after the last alert.
+        alerter.state.lock().await.last_alerts.insert(
+            "TransferFetcher".to_string(),
+            Timestamp::now() + Timestamp::from(60),
+        );
+
+        alerter
+            .report_failure("TransferFetcher", &anyhow!("subscan down"))
+            .await;
+        assert_eq!(sender.0.load(Ordering::SeqCst), 0);
+    }
+}
@@ -1,6 +1,195 @@
-use system::{run, Result};
+use anyhow::anyhow;
+use clap::{App, Arg, SubCommand};
+use system::{explain, export, report, run, validate, Result, RunOptions};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    run().await
+    let matches = App::new("monitor")
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .default_value("config/config.yml")
+                .help("Path to the config file"),
+        )
+        .arg(
+            Arg::with_name("once")
+                .long("once")
+                .help("Runs a single scraping pass and exits, e.g. for cron-style scheduling"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Fetches the entries of all accounts without storing them"),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .takes_value(true)
+                .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+                .help("Overrides the log level of the config"),
+        )
+        .arg(
+            Arg::with_name("validate-only")
+                .long("validate-only")
+                .help("Checks the config and accounts offline, without connecting to anything"),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Checks the config, the accounts and the database connection, then exits"),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Prints the Subscan request for an account without sending it")
+                .arg(
+                    Arg::with_name("stash")
+                        .long("stash")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("network")
+                        .long("network")
+                        .takes_value(true)
+                        .default_value("polkadot"),
+                )
+                .arg(
+                    Arg::with_name("module")
+                        .long("module")
+                        .takes_value(true)
+                        .default_value("transfer"),
+                )
+                .arg(
+                    Arg::with_name("row")
+                        .long("row")
+                        .takes_value(true)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("page")
+                        .long("page")
+                        .takes_value(true)
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Generates a report over a date range and prints it to stdout")
+                .arg(
+                    Arg::with_name("module")
+                        .long("module")
+                        .takes_value(true)
+                        .default_value("transfer"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["json", "csv", "text"])
+                        .default_value("text"),
+                )
+                .arg(Arg::with_name("tag").long("tag").takes_value(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Exports the stored entries as CSV or NDJSON")
+                .arg(
+                    Arg::with_name("module")
+                        .long("module")
+                        .takes_value(true)
+                        .default_value("transfer"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(Arg::with_name("from").long("from").takes_value(true))
+                .arg(Arg::with_name("to").long("to").takes_value(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["csv", "ndjson"])
+                        .default_value("csv"),
+                )
+                .arg(Arg::with_name("cursor").long("cursor").takes_value(true)),
+        )
+        .get_matches();
+
+    let config = matches.value_of("config").unwrap();
+
+    if let Some(matches) = matches.subcommand_matches("explain") {
+        return explain(
+            config,
+            matches.value_of("stash").unwrap(),
+            matches.value_of("network").unwrap(),
+            matches.value_of("module").unwrap(),
+            matches.value_of("row").unwrap().parse()?,
+            matches.value_of("page").unwrap().parse()?,
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("report") {
+        return report(
+            config,
+            matches.value_of("module").unwrap(),
+            matches.value_of("from").unwrap(),
+            matches.value_of("to").unwrap(),
+            matches.value_of("format").unwrap(),
+            matches.value_of("tag"),
+        )
+        .await;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export") {
+        return export(
+            config,
+            matches.value_of("module").unwrap(),
+            matches.value_of("from"),
+            matches.value_of("to"),
+            matches.value_of("out").unwrap(),
+            matches.value_of("format").unwrap(),
+            matches.value_of("cursor"),
+        )
+        .await;
+    }
+
+    if matches.subcommand_matches("validate").is_some() {
+        return validate(config, true).await;
+    }
+
+    if matches.is_present("validate-only") {
+        return validate(config, false).await;
+    }
+
+    let log_level = matches
+        .value_of("log-level")
+        .map(|level| {
+            level
+                .parse()
+                .map_err(|_| anyhow!("invalid log level '{}'", level))
+        })
+        .transpose()?;
+
+    run(RunOptions {
+        config: config.to_string(),
+        once: matches.is_present("once"),
+        dry_run: matches.is_present("dry-run"),
+        log_level: log_level,
+    })
+    .await
 }
@@ -1,118 +1,971 @@
-use crate::{BlockNumber, Context, Result, Timestamp};
-use reqwest::header::{CONTENT_TYPE, USER_AGENT};
-use reqwest::Client;
-use serde::{de::DeserializeOwned, Serialize};
+use crate::{Balance, BlockNumber, Context, Network, Result, Timestamp, TransferDirection};
+use metrics::{histogram, increment_counter};
+use rand::{thread_rng, Rng};
+use reqwest::header::{HeaderMap, HeaderName, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Client, StatusCode};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 
+/// Seconds between two requests by default.
 const REQUEST_TIMEOUT: u64 = 10;
+pub const API_KEY_HEADER: &'static str = "X-API-Key";
+/// Overrides the configured API key of every network.
+pub const API_KEY_ENV: &'static str = "SUBSCAN_API_KEY";
+/// The response code Subscan uses for unknown accounts/records.
+const CODE_NOT_FOUND: u64 = 10004;
+/// The response code Subscan uses when the API rate limit is exceeded.
+const CODE_RATE_LIMIT: u64 = 20008;
+/// Maximum rows per request accepted by Subscan. Larger pages are assembled
+/// from multiple requests, so they must be a multiple of it. See
+/// `split_page`.
+pub const MAX_ROW: usize = 100;
+
+/// The result of the Subscan requests, see `ChainApiError`.
+pub type ApiResult<T> = std::result::Result<T, ChainApiError>;
+
+/// Splits a page of `row` entries into Subscan pages of `MAX_ROW` entries,
+/// covering exactly the same entries. Returns the row amount per request and
+/// the pages to request.
+fn split_page(row: usize, page: usize) -> ApiResult<(usize, Vec<usize>)> {
+    if row <= MAX_ROW {
+        return Ok((row, vec![page]));
+    }
+
+    // The requests must line up with the page boundaries.
+    if row % MAX_ROW != 0 {
+        return Err(ChainApiError::InvalidPage(format!(
+            "row amount {} above {} must be a multiple of it",
+            row, MAX_ROW
+        )));
+    }
+
+    let count = row / MAX_ROW;
+    Ok((MAX_ROW, (page * count..(page + 1) * count).collect()))
+}
+
+/// A page of entries, which can be assembled from multiple requests.
+trait PageEntries {
+    fn entries(&self) -> usize;
+    fn append(&mut self, other: Self);
+}
+
+/// Per-network settings of the Subscan API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscanConfig {
+    #[serde(default)]
+    pub polkadot: SubscanNetworkConfig,
+    #[serde(default)]
+    pub kusama: SubscanNetworkConfig,
+    #[serde(default)]
+    pub westend: SubscanNetworkConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+impl SubscanConfig {
+    fn network(&self, network: Network) -> &SubscanNetworkConfig {
+        match network {
+            Network::Polkadot => &self.polkadot,
+            Network::Kusama => &self.kusama,
+            Network::Westend => &self.westend,
+        }
+    }
+    /// Uses the given API key for all networks.
+    pub fn override_api_key(&mut self, key: String) {
+        self.polkadot.api_key = Some(key.clone());
+        self.kusama.api_key = Some(key.clone());
+        self.westend.api_key = Some(key);
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscanNetworkConfig {
+    /// E.g. a self-hosted deployment. Defaults to the public Subscan host of
+    /// the network.
+    pub base_url: Option<String>,
+    /// Sent as `X-API-Key` header, requests are anonymous otherwise.
+    pub api_key: Option<String>,
+}
+
+/// The request budget shared by all fetchers, as a token bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Rate at which the bucket is refilled.
+    pub requests_per_second: f64,
+    /// Requests which can be sent at once after being idle.
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            requests_per_second: 1.0 / REQUEST_TIMEOUT as f64,
+            burst: 1,
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket rate limiter. Starts full.
+struct RateLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl RateLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            bucket: Mutex::new(TokenBucket {
+                tokens: config.burst.max(1) as f64,
+                last_refill: Instant::now(),
+            }),
+            config: config,
+        }
+    }
+    /// Waits until a token is available and takes it.
+    async fn acquire(&self) {
+        let capacity = self.config.burst.max(1) as f64;
+        // A rate of zero would never refill.
+        let rate = self.config.requests_per_second.max(0.001);
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                (1.0 - bucket.tokens) / rate
+            };
+
+            sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+/// Remembers the last response per request (account, endpoint and page), so
+/// unchanged pages are not stored again. Subscan only accepts POST requests,
+/// which are not cached by HTTP, so a hash of the response is compared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Maximum number of remembered requests, the least recently used one is
+    /// evicted. Zero disables the cache.
+    pub capacity: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig { capacity: 1_000 }
+    }
+}
+
+struct CacheEntry {
+    address: String,
+    hash: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    entries: HashMap<String, CacheEntry>,
+    /// Incremented on every access, orders the entries by their last use.
+    clock: u64,
+}
+
+/// Hashes of the last response per request, with LRU eviction.
+struct ResponseCache {
+    capacity: usize,
+    entries: std::sync::Mutex<CacheEntries>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ResponseCache {
+    fn new(config: &CacheConfig) -> Self {
+        ResponseCache {
+            capacity: config.capacity,
+            entries: Default::default(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+    /// Remembers the payload of the response to the request (see
+    /// `response_data`). Returns whether it is the same as the previous one.
+    fn unchanged(&self, req: &RequestDescription, address: &str, data: &str) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+
+        let key = format!("{} {}", req.url, req.body);
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut cache = self.entries.lock().unwrap();
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        let unchanged = match cache.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_used = clock;
+                let unchanged = entry.hash == hash;
+                entry.hash = hash;
+                unchanged
+            }
+            None => {
+                if cache.entries.len() >= self.capacity {
+                    let oldest = cache
+                        .entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.last_used)
+                        .map(|(key, _)| key.clone());
+                    if let Some(oldest) = oldest {
+                        cache.entries.remove(&oldest);
+                    }
+                }
+
+                cache.entries.insert(
+                    key,
+                    CacheEntry {
+                        address: address.to_string(),
+                        hash: hash,
+                        last_used: clock,
+                    },
+                );
+                false
+            }
+        };
+
+        let path = req.endpoint.path().to_string();
+        if unchanged {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            increment_counter!("monitor_chain_api_cache_hits_total", "endpoint" => path);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            increment_counter!("monitor_chain_api_cache_misses_total", "endpoint" => path);
+        }
+
+        unchanged
+    }
+    /// Forgets the responses of the account.
+    fn forget(&self, address: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|_, entry| entry.address != address);
+    }
+}
+
+/// Retries of transient request failures, such as 5xx responses or reset
+/// connections, with exponential backoff and jitter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub max_retries: usize,
+    /// Delay before the first retry (milliseconds), doubled on each retry.
+    pub base_ms: u64,
+    /// Upper bound of the delay (milliseconds).
+    pub cap_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_retries: 3,
+            base_ms: 1_000,
+            cap_ms: 30_000,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// The delay before the given retry (starting at 0). Between half and
+    /// the full exponential delay, so concurrent clients don't retry in sync.
+    fn delay(&self, retry: usize) -> Duration {
+        let exp = self
+            .base_ms
+            .saturating_mul(1 << retry.min(32))
+            .min(self.cap_ms);
+
+        Duration::from_millis(exp / 2 + thread_rng().gen_range(0..=exp / 2))
+    }
+}
+
+// Never print the API key.
+impl fmt::Debug for SubscanNetworkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscanNetworkConfig")
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
 
 pub struct ChainApi {
     client: Client,
-    guard_lock: Arc<Mutex<()>>,
+    limiter: Arc<RateLimiter>,
+    cache: ResponseCache,
+    config: SubscanConfig,
 }
 
 impl ChainApi {
     pub fn new() -> Self {
+        Self::with_config(Default::default())
+    }
+    pub fn with_config(config: SubscanConfig) -> Self {
         ChainApi {
             client: Client::new(),
-            guard_lock: Arc::new(Mutex::new(())),
+            limiter: Arc::new(RateLimiter::new(config.rate_limit.clone())),
+            cache: ResponseCache::new(&config.cache),
+            config: config,
+        }
+    }
+    fn base_url(&self, network: Network) -> String {
+        match &self.config.network(network).base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("https://{}", network.meta().subscan_host),
         }
     }
-    async fn time_guard(&self) {
-        let mutex = Arc::clone(&self.guard_lock);
-        let guard = mutex.lock_owned().await;
+    /// Describes the exact request that would be sent to Subscan for the
+    /// given endpoint, without actually sending it.
+    pub fn describe_request(
+        &self,
+        endpoint: Endpoint,
+        context: &Context,
+        row: usize,
+        page: usize,
+    ) -> ApiResult<RequestDescription> {
+        let body = match endpoint {
+            Endpoint::Transfers => serde_json::to_value(PageBody {
+                address: &context.stash,
+                row: row,
+                page: page,
+                direction: match context.transfer_direction {
+                    TransferDirection::All => None,
+                    TransferDirection::Sent => Some("sent"),
+                    TransferDirection::Received => Some("received"),
+                },
+                from_block: context.blocks.since,
+                to_block: context.blocks.until,
+            }),
+            Endpoint::RewardsSlashes => serde_json::to_value(PageBody {
+                address: &context.stash,
+                row: row,
+                page: page,
+                direction: None,
+                from_block: context.blocks.since,
+                to_block: context.blocks.until,
+            }),
+            Endpoint::Nominations => serde_json::to_value(Address {
+                address: &context.stash,
+            }),
+            Endpoint::GovernanceVotes => serde_json::to_value(PageBody {
+                address: &context.stash,
+                row: row,
+                page: page,
+                direction: None,
+                from_block: context.blocks.since,
+                to_block: context.blocks.until,
+            }),
+            Endpoint::Bonds => serde_json::to_value(PageBody {
+                address: &context.stash,
+                row: row,
+                page: page,
+                direction: None,
+                from_block: context.blocks.since,
+                to_block: context.blocks.until,
+            }),
+            // The call filter is added by `request_extrinsics`.
+            Endpoint::Extrinsics => serde_json::to_value(PageBody {
+                address: &context.stash,
+                row: row,
+                page: page,
+                direction: None,
+                from_block: context.blocks.since,
+                to_block: context.blocks.until,
+            }),
+            Endpoint::Account => serde_json::to_value(SearchKey {
+                key: &context.stash,
+            }),
+        }
+        .map_err(|err| ChainApiError::Encode(err.to_string()))?;
 
-        tokio::spawn(async move {
-            // Capture guard, drops after sleeping period;
-            let _ = guard;
-            sleep(Duration::from_secs(REQUEST_TIMEOUT)).await;
-        });
+        let mut headers = vec![
+            (
+                CONTENT_TYPE.as_str().to_string(),
+                "application/json".to_string(),
+            ),
+            (USER_AGENT.as_str().to_string(), "curl/7.68.0".to_string()),
+        ];
+        if let Some(key) = &self.config.network(context.network).api_key {
+            headers.push((API_KEY_HEADER.to_string(), key.to_string()));
+        }
+
+        Ok(RequestDescription {
+            endpoint: endpoint,
+            url: format!("{}{}", self.base_url(context.network), endpoint.path()),
+            headers: headers,
+            body: body,
+        })
+    }
+    /// Returns how many responses were unchanged (hits) and changed or
+    /// requested for the first time (misses), see `CacheConfig`.
+    #[cfg(test)]
+    pub fn cache_stats(&self) -> (usize, usize) {
+        (
+            self.cache.hits.load(Ordering::Relaxed),
+            self.cache.misses.load(Ordering::Relaxed),
+        )
+    }
+    /// Sends the request, retrying transient failures according to the
+    /// `RetryConfig`.
+    async fn post<R>(&self, req: RequestDescription) -> ApiResult<R>
+    where
+        R: DeserializeOwned,
+    {
+        self.send(req, None).await
     }
-    async fn post<T, R>(&self, url: &str, param: &T) -> Result<R>
+    /// Like `post`, but fails with `ChainApiError::Unchanged` if the response
+    /// is the same as the previous one to this request.
+    async fn post_cached<R>(&self, req: RequestDescription, context: &Context) -> ApiResult<R>
     where
-        T: Serialize,
         R: DeserializeOwned,
     {
-        let headers = [
-            ("X-API-Key".parse()?, "YOUR_KEY".parse()?),
-            (CONTENT_TYPE, "application/json".parse()?),
-            (USER_AGENT, "curl/7.68.0".parse()?),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-
-        self.time_guard().await;
-
-        self.client
-            .post(url)
+        self.send(req, Some(&context.stash)).await
+    }
+    async fn send<R>(&self, req: RequestDescription, cache: Option<&str>) -> ApiResult<R>
+    where
+        R: DeserializeOwned,
+    {
+        let retry = &self.config.retry;
+        let mut attempt = 0;
+        loop {
+            match self.post_once(&req, cache).await {
+                Ok(resp) => return Ok(resp),
+                Err(err) if attempt < retry.max_retries && err.is_transient() => {
+                    let delay = retry.delay(attempt);
+                    attempt += 1;
+
+                    debug!(
+                        "Request to {} failed, retry {}/{} in {:?}: {:?}",
+                        req.endpoint.path(),
+                        attempt,
+                        retry.max_retries,
+                        delay,
+                        err
+                    );
+                    sleep(delay).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    /// Requests a page of the endpoint, with as many requests as required by
+    /// Subscan's row limit. Stops early at the last entry. New entries show up
+    /// at the top, so the page is unchanged if its first request is.
+    async fn post_paged<T>(
+        &self,
+        endpoint: Endpoint,
+        context: &Context,
+        row: usize,
+        page: usize,
+    ) -> ApiResult<Response<T>>
+    where
+        T: DeserializeOwned + PageEntries,
+    {
+        self.post_paged_filtered(endpoint, context, None, row, page)
+            .await
+    }
+    /// Like `post_paged`, with the extrinsics of the request limited to the
+    /// given call.
+    async fn post_paged_filtered<T>(
+        &self,
+        endpoint: Endpoint,
+        context: &Context,
+        filter: Option<&ExtrinsicFilter>,
+        row: usize,
+        page: usize,
+    ) -> ApiResult<Response<T>>
+    where
+        T: DeserializeOwned + PageEntries,
+    {
+        let (sub_row, pages) = split_page(row, page)?;
+
+        let mut resps: Vec<Response<T>> = vec![];
+        for page in pages {
+            let mut req = self.describe_request(endpoint, context, sub_row, page)?;
+            if let Some(filter) = filter {
+                filter.apply(&mut req.body);
+            }
+            let resp: Response<T> = if resps.is_empty() {
+                self.post_cached(req, context).await?
+            } else {
+                self.post(req).await?
+            };
+
+            let last = resp.data.entries() < sub_row;
+            resps.push(resp);
+            if last {
+                break;
+            }
+        }
+
+        let mut resps = resps.into_iter();
+        let mut resp = resps
+            .next()
+            .ok_or_else(|| ChainApiError::InvalidPage("no page was requested".to_string()))?;
+        for next in resps {
+            resp.data.append(next.data);
+        }
+
+        Ok(resp)
+    }
+    async fn post_once<R>(&self, req: &RequestDescription, cache: Option<&str>) -> ApiResult<R>
+    where
+        R: DeserializeOwned,
+    {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &req.headers {
+            // The value is never part of the error, it might be the API key.
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| ChainApiError::InvalidHeader(format!("{}: {}", name, err)))?;
+            let value = value.parse().map_err(|err| {
+                ChainApiError::InvalidHeader(format!("value of {}: {}", name, err))
+            })?;
+            headers.insert(header, value);
+        }
+
+        self.limiter.acquire().await;
+
+        // Measured after waiting for the rate limiter, so only the actual
+        // request counts.
+        let start = Instant::now();
+        let resp = self
+            .client
+            .post(&req.url)
             .headers(headers)
-            .json(param)
+            .json(&req.body)
             .send()
-            .await?
-            .json()
             .await
-            .map_err(|err| err.into())
+            .map_err(ChainApiError::from)?;
+
+        let status = resp.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ChainApiError::RateLimited);
+        }
+        if status.is_server_error() {
+            return Err(ChainApiError::Http(status.as_u16()));
+        }
+
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_string());
+
+        let body = resp.text().await.map_err(ChainApiError::from)?;
+        histogram!(
+            "monitor_chain_api_request_duration_seconds",
+            start.elapsed(),
+            "endpoint" => req.endpoint.path().to_string()
+        );
+
+        let resp = parse_body(content_type.as_deref(), &body)?;
+        if let Some(address) = cache {
+            if self.cache.unchanged(req, address, &response_data(&body)) {
+                return Err(ChainApiError::Unchanged);
+            }
+        }
+
+        Ok(resp)
+    }
+}
+
+/// The source of chain data used by the fetchers. `ChainApi` queries
+/// Subscan, other implementations can be plugged into the `ScrapingService`.
+/// Apart from the transfers, the requests are not supported by default, so
+/// a backend only implements the ones of the modules it serves.
+#[async_trait]
+pub trait ChainBackend {
+    async fn request_transfer(
+        &self,
+        context: &Context,
+        row: usize,
+        page: usize,
+    ) -> Result<Response<TransfersPage>>;
+    async fn request_reward_slash(
+        &self,
+        _context: &Context,
+        _row: usize,
+        _page: usize,
+    ) -> Result<Response<RewardsSlashesPage>> {
+        Err(anyhow!(
+            "rewards and slashes are not supported by this backend"
+        ))
+    }
+    async fn request_nominations(&self, _context: &Context) -> Result<Response<NominationsPage>> {
+        Err(anyhow!("nominations are not supported by this backend"))
+    }
+    async fn request_governance_votes(
+        &self,
+        _context: &Context,
+        _row: usize,
+        _page: usize,
+    ) -> Result<Response<GovernanceVotesPage>> {
+        Err(anyhow!(
+            "governance votes are not supported by this backend"
+        ))
+    }
+    async fn request_bonds(
+        &self,
+        _context: &Context,
+        _row: usize,
+        _page: usize,
+    ) -> Result<Response<BondsPage>> {
+        Err(anyhow!("bonds are not supported by this backend"))
     }
-    pub async fn request_transfer(
+    async fn request_account(&self, _context: &Context) -> Result<Response<AccountPage>> {
+        Err(anyhow!("accounts are not supported by this backend"))
+    }
+    /// The extrinsics signed by the account which match the filter.
+    async fn request_extrinsics(
+        &self,
+        _context: &Context,
+        _filter: &ExtrinsicFilter,
+        _row: usize,
+        _page: usize,
+    ) -> Result<Response<ExtrinsicsPage>> {
+        Err(anyhow!("extrinsics are not supported by this backend"))
+    }
+    /// Subscan reports the balances as part of the account, so this is the
+    /// same request as `request_account` by default.
+    async fn request_balance(&self, context: &Context) -> Result<Response<AccountPage>> {
+        self.request_account(context).await
+    }
+    /// Same as `request_balance`, the identity is part of the account.
+    async fn request_identity(&self, context: &Context) -> Result<Response<AccountPage>> {
+        self.request_account(context).await
+    }
+    /// Forgets the cached responses of the account, so the next requests
+    /// are never reported as unchanged, e.g. after its entries could not be
+    /// stored.
+    fn forget_responses(&self, _context: &Context) {}
+}
+
+#[async_trait]
+impl ChainBackend for ChainApi {
+    async fn request_transfer(
         &self,
         context: &Context,
         row: usize,
         page: usize,
     ) -> Result<Response<TransfersPage>> {
         Ok(self
-            .post(
-                &format!(
-                    "https://{}.api.subscan.io/api/scan/transfers",
-                    context.network.as_str()
-                ),
-                &PageBody {
-                    address: &context.stash,
-                    row: row,
-                    page: page,
-                },
-            )
+            .post_paged(Endpoint::Transfers, context, row, page)
             .await?)
     }
-    pub async fn request_reward_slash(
+    async fn request_reward_slash(
         &self,
         context: &Context,
         row: usize,
         page: usize,
     ) -> Result<Response<RewardsSlashesPage>> {
         Ok(self
-            .post(
-                &format!(
-                    "https://{}.api.subscan.io/api/scan/account/reward_slash",
-                    context.network.as_str()
-                ),
-                &PageBody {
-                    address: &context.stash,
-                    row: row,
-                    page: page,
-                },
+            .post_paged(Endpoint::RewardsSlashes, context, row, page)
+            .await?)
+    }
+    async fn request_nominations(&self, context: &Context) -> Result<Response<NominationsPage>> {
+        Ok(self
+            .post_cached(
+                self.describe_request(Endpoint::Nominations, context, 0, 0)?,
+                context,
             )
             .await?)
     }
-    pub async fn request_nominations(
+    async fn request_governance_votes(
         &self,
         context: &Context,
-    ) -> Result<Response<NominationsPage>> {
+        row: usize,
+        page: usize,
+    ) -> Result<Response<GovernanceVotesPage>> {
         Ok(self
-            .post(
-                &format!(
-                    "https://{}.api.subscan.io/api/scan/staking/voted",
-                    context.network.as_str()
-                ),
-                &Address {
-                    address: &context.stash,
-                },
-            )
+            .post_paged(Endpoint::GovernanceVotes, context, row, page)
+            .await?)
+    }
+    async fn request_bonds(
+        &self,
+        context: &Context,
+        row: usize,
+        page: usize,
+    ) -> Result<Response<BondsPage>> {
+        Ok(self.post_paged(Endpoint::Bonds, context, row, page).await?)
+    }
+    async fn request_account(&self, context: &Context) -> Result<Response<AccountPage>> {
+        Ok(self
+            .post(self.describe_request(Endpoint::Account, context, 0, 0)?)
+            .await?)
+    }
+    async fn request_extrinsics(
+        &self,
+        context: &Context,
+        filter: &ExtrinsicFilter,
+        row: usize,
+        page: usize,
+    ) -> Result<Response<ExtrinsicsPage>> {
+        Ok(self
+            .post_paged_filtered(Endpoint::Extrinsics, context, Some(filter), row, page)
             .await?)
     }
+    fn forget_responses(&self, context: &Context) {
+        self.cache.forget(&context.stash);
+    }
+}
+
+/// The errors of the Subscan requests. The `ChainBackend` methods return them
+/// as `anyhow::Error`, so callers can downcast to decide how to proceed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainApiError {
+    /// Subscan responded with a non-JSON (usually HTML) body, which happens
+    /// while it's under maintenance.
+    Unavailable,
+    /// Subscan explicitly reported that the account does not exist, as
+    /// opposed to an account without any entries.
+    NotFound,
+    /// The API rate limit was exceeded, either reported by the HTTP status or
+    /// the response envelope.
+    RateLimited,
+    /// A server error.
+    Http(u16),
+    /// The response body is not the expected JSON.
+    Decode(String),
+    /// The request failed before a response was received, e.g. a timeout.
+    Transport(String),
+    /// Subscan responded with a non-zero code in the response envelope.
+    Subscan { code: u64, message: String },
+    /// The response is the same as the previous one to the request, see
+    /// `CacheConfig`.
+    Unchanged,
+    /// A header of the request is not a valid HTTP header.
+    InvalidHeader(String),
+    /// The requested page can't be assembled from Subscan pages, see
+    /// `split_page`.
+    InvalidPage(String),
+    /// The request body could not be encoded.
+    Encode(String),
+}
+
+impl ChainApiError {
+    /// Whether the request should be retried later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ChainApiError::Unavailable => true,
+            ChainApiError::NotFound => false,
+            ChainApiError::RateLimited => true,
+            ChainApiError::Http(_) => true,
+            ChainApiError::Decode(_) => false,
+            ChainApiError::Transport(_) => true,
+            // Other codes (e.g. invalid parameters) fail the same way on
+            // every attempt.
+            ChainApiError::Subscan { .. } => false,
+            ChainApiError::Unchanged => false,
+            // The request is the same on every attempt.
+            ChainApiError::InvalidHeader(_) => false,
+            ChainApiError::InvalidPage(_) => false,
+            ChainApiError::Encode(_) => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ChainApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ChainApiError::Transport(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ChainApiError {
+    fn from(err: serde_json::Error) -> Self {
+        ChainApiError::Decode(err.to_string())
+    }
+}
+
+impl fmt::Display for ChainApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainApiError::Unavailable => {
+                write!(f, "Subscan is under maintenance or unavailable")
+            }
+            ChainApiError::NotFound => write!(f, "account not found on Subscan"),
+            ChainApiError::RateLimited => write!(f, "Subscan API rate limit exceeded"),
+            ChainApiError::Http(status) => {
+                write!(f, "Subscan responded with HTTP status {}", status)
+            }
+            ChainApiError::Decode(err) => write!(f, "invalid Subscan response: {}", err),
+            ChainApiError::Transport(err) => write!(f, "Subscan request failed: {}", err),
+            ChainApiError::Subscan { code, message } => {
+                write!(f, "Subscan responded with code {}: {}", code, message)
+            }
+            ChainApiError::Unchanged => write!(f, "response unchanged since the last request"),
+            ChainApiError::InvalidHeader(err) => write!(f, "invalid request header {}", err),
+            ChainApiError::InvalidPage(err) => write!(f, "invalid page: {}", err),
+            ChainApiError::Encode(err) => write!(f, "invalid request body: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ChainApiError {}
+
+fn parse_body<R>(content_type: Option<&str>, body: &str) -> ApiResult<R>
+where
+    R: DeserializeOwned,
+{
+    let is_html = content_type
+        .map(|val| val.contains("text/html"))
+        .unwrap_or(false);
+
+    if is_html || body.trim_start().starts_with('<') {
+        return Err(ChainApiError::Unavailable);
+    }
+
+    let value: serde_json::Value = serde_json::from_str(body).map_err(ChainApiError::from)?;
+    match value.get("code").and_then(|code| code.as_u64()) {
+        Some(CODE_NOT_FOUND) => return Err(ChainApiError::NotFound),
+        Some(CODE_RATE_LIMIT) => return Err(ChainApiError::RateLimited),
+        // The payload of a failed request is usually `null`, so it must not
+        // be parsed as a (possibly empty) page.
+        Some(code) if code != 0 => {
+            let message = value
+                .get("message")
+                .and_then(|message| message.as_str())
+                .unwrap_or_default()
+                .to_string();
+
+            if message.to_lowercase().contains("rate limit") {
+                return Err(ChainApiError::RateLimited);
+            }
+
+            return Err(ChainApiError::Subscan {
+                code: code,
+                message: message,
+            });
+        }
+        _ => {}
+    }
+
+    serde_json::from_value(value).map_err(ChainApiError::from)
+}
+
+/// The `data` payload of a response body. Subscan stamps every response
+/// with the time it was generated at (`generated_at`), so the body itself
+/// changes on every request.
+fn response_data(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("data").map(|data| data.to_string()))
+        .unwrap_or_else(|| body.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    Transfers,
+    RewardsSlashes,
+    Nominations,
+    GovernanceVotes,
+    Bonds,
+    Extrinsics,
+    Account,
+}
+
+impl Endpoint {
+    pub fn path(&self) -> &str {
+        match self {
+            Endpoint::Transfers => "/api/scan/transfers",
+            Endpoint::RewardsSlashes => "/api/scan/account/reward_slash",
+            Endpoint::Nominations => "/api/scan/staking/voted",
+            Endpoint::GovernanceVotes => "/api/scan/account/democracy/votes",
+            Endpoint::Bonds => "/api/scan/staking/history",
+            Endpoint::Extrinsics => "/api/v2/scan/extrinsics",
+            Endpoint::Account => "/api/v2/scan/search",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct RequestDescription {
+    pub endpoint: Endpoint,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: serde_json::Value,
+}
+
+// Never print the API key.
+impl fmt::Debug for RequestDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                if name.eq_ignore_ascii_case(API_KEY_HEADER) {
+                    (name.as_str(), "<redacted>")
+                } else {
+                    (name.as_str(), value.as_str())
+                }
+            })
+            .collect();
+
+        f.debug_struct("RequestDescription")
+            .field("endpoint", &self.endpoint)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+impl fmt::Display for RequestDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "POST {}", self.url)?;
+        for (name, value) in &self.headers {
+            // Never print the API key.
+            if name.eq_ignore_ascii_case(API_KEY_HEADER) {
+                writeln!(f, "{}: <redacted>", name)?;
+            } else {
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "{}",
+            serde_json::to_string_pretty(&self.body).map_err(|_| fmt::Error)?
+        )
+    }
 }
 
 #[derive(Serialize)]
@@ -120,6 +973,13 @@ struct PageBody<'a> {
     address: &'a str,
     row: usize,
     page: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<&'a str>,
+    // Only set if the account is restricted to a range of blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_block: Option<BlockNumber>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_block: Option<BlockNumber>,
 }
 
 #[derive(Serialize)]
@@ -127,6 +987,11 @@ struct Address<'a> {
     address: &'a str,
 }
 
+#[derive(Serialize)]
+struct SearchKey<'a> {
+    key: &'a str,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Response<T> {
     pub code: Option<usize>,
@@ -143,11 +1008,14 @@ pub struct TransfersPage {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Transfer {
+    #[serde(deserialize_with = "string_or_number")]
     pub amount: String,
     pub block_num: BlockNumber,
     pub block_timestamp: Timestamp,
     pub extrinsic_index: ExtrinsicIndex,
-    pub fee: String,
+    // Subscan omits the fee for some (incoming) transfers, stored as null.
+    #[serde(default, deserialize_with = "opt_balance")]
+    pub fee: Option<Balance>,
     pub from: String,
     pub from_account_display: FromAccountDisplay,
     pub hash: String,
@@ -158,6 +1026,27 @@ pub struct Transfer {
     pub to_account_display: ToAccountDisplay,
 }
 
+impl Transfer {
+    /// The `module.call` name used for filtering. Subscan only reports the
+    /// module (e.g. `balances`), the call is always a transfer.
+    pub fn call(&self) -> String {
+        format!("{}.transfer", self.module)
+    }
+}
+
+impl PageEntries for TransfersPage {
+    fn entries(&self) -> usize {
+        self.transfers.as_ref().map(|t| t.len()).unwrap_or(0)
+    }
+    fn append(&mut self, other: Self) {
+        if let Some(mut other) = other.transfers {
+            self.transfers
+                .get_or_insert_with(Vec::new)
+                .append(&mut other);
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FromAccountDisplay {
     pub address: String,
@@ -220,6 +1109,41 @@ pub struct StashAccountDisplay {
     pub parent: Option<Parent>,
 }
 
+/// Subscan is not consistent about whether amounts are encoded as strings or
+/// as numbers, so both are accepted and stored as a string.
+fn string_or_number<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    opt_string_or_number(deserializer)?
+        .ok_or_else(|| de::Error::custom("expected a string or a number, found null"))
+}
+
+fn opt_string_or_number<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(val) => Ok(Some(val)),
+        serde_json::Value::Number(val) => Ok(Some(val.to_string())),
+        other => Err(de::Error::custom(format!(
+            "expected a string or a number, found {}",
+            other
+        ))),
+    }
+}
+
+/// Plancks, encoded as a string or as a number.
+fn opt_balance<'de, D>(deserializer: D) -> std::result::Result<Option<Balance>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    opt_string_or_number(deserializer)?
+        .map(|val| val.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
 #[derive(Default, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct ExtrinsicIndex(String);
 
@@ -229,19 +1153,72 @@ impl fmt::Display for ExtrinsicIndex {
     }
 }
 
+/// A `0x`-prefixed, hex encoded 32-byte extrinsic hash. Deserialization does
+/// not validate, so a single malformed entry doesn't fail a whole page;
+/// entries are checked with `is_valid` before being stored.
 #[derive(Default, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct ExtrinsicHash(String);
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub struct RewardsSlashesPage {
-    count: i64,
-    pub list: Option<Vec<RewardSlash>>,
+impl ExtrinsicHash {
+    pub fn new(val: String) -> Result<Self> {
+        Self::validate(&val)?;
+        Ok(ExtrinsicHash(val))
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    pub fn is_valid(&self) -> bool {
+        Self::validate(&self.0).is_ok()
+    }
+    fn validate(val: &str) -> Result<()> {
+        let hex = val
+            .strip_prefix("0x")
+            .ok_or_else(|| anyhow!("extrinsic hash is not 0x-prefixed: '{}'", val))?;
+
+        if hex.len() != 64 {
+            return Err(anyhow!(
+                "extrinsic hash must be 32 bytes, found {} hex characters: '{}'",
+                hex.len(),
+                val
+            ));
+        }
+
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("extrinsic hash is not valid hex: '{}'", val));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExtrinsicHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RewardsSlashesPage {
+    count: i64,
+    pub list: Option<Vec<RewardSlash>>,
+}
+
+impl PageEntries for RewardsSlashesPage {
+    fn entries(&self) -> usize {
+        self.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+    fn append(&mut self, other: Self) {
+        if let Some(mut other) = other.list {
+            self.list.get_or_insert_with(Vec::new).append(&mut other);
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct RewardSlash {
+    #[serde(deserialize_with = "string_or_number")]
     pub amount: String,
     pub event_index: String,
     pub block_num: BlockNumber,
@@ -251,11 +1228,802 @@ pub struct RewardSlash {
     pub params: String,
     pub extrinsic_hash: ExtrinsicHash,
     pub event_idx: i64,
+    // Not returned by older Subscan versions.
+    #[serde(default)]
+    pub block_timestamp: Timestamp,
+    #[serde(default)]
+    pub era: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GovernanceVotesPage {
+    pub count: i64,
+    pub list: Option<Vec<GovernanceVote>>,
+}
+
+impl PageEntries for GovernanceVotesPage {
+    fn entries(&self) -> usize {
+        self.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+    fn append(&mut self, other: Self) {
+        if let Some(mut other) = other.list {
+            self.list.get_or_insert_with(Vec::new).append(&mut other);
+        }
+    }
+}
+
+/// A democracy/referendum vote cast by the monitored account.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GovernanceVote {
+    pub referendum_index: u64,
+    pub extrinsic_index: ExtrinsicIndex,
+    // "Aye" or "Nay".
+    pub status: String,
+    #[serde(deserialize_with = "string_or_number")]
+    pub amount: String,
+    #[serde(default)]
+    pub conviction: String,
+    pub voting_time: Timestamp,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BondsPage {
+    pub count: i64,
+    pub list: Option<Vec<BondEvent>>,
+}
+
+impl PageEntries for BondsPage {
+    fn entries(&self) -> usize {
+        self.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+    fn append(&mut self, other: Self) {
+        if let Some(mut other) = other.list {
+            self.list.get_or_insert_with(Vec::new).append(&mut other);
+        }
+    }
+}
+
+/// Limits the extrinsics to a single call, e.g. `staking.bond`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExtrinsicFilter {
+    /// E.g. `staking`.
+    pub call_module: String,
+    /// E.g. `bond`.
+    pub call_name: String,
+}
+
+impl ExtrinsicFilter {
+    /// Both the module and the call must be set, Subscan returns all
+    /// extrinsics otherwise.
+    pub fn validate(&self) -> Result<()> {
+        if self.call_module.trim().is_empty() || self.call_name.trim().is_empty() {
+            return Err(anyhow!(
+                "extrinsic filter requires both the call module and name, got '{}.{}'",
+                self.call_module,
+                self.call_name
+            ));
+        }
+
+        Ok(())
+    }
+    /// Adds the filter to the request body.
+    fn apply(&self, body: &mut serde_json::Value) {
+        if let Some(body) = body.as_object_mut() {
+            body.insert("module".to_string(), self.call_module.to_lowercase().into());
+            body.insert("call".to_string(), self.call_name.to_lowercase().into());
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtrinsicsPage {
+    pub count: i64,
+    pub extrinsics: Option<Vec<Extrinsic>>,
+}
+
+impl PageEntries for ExtrinsicsPage {
+    fn entries(&self) -> usize {
+        self.extrinsics.as_ref().map(|e| e.len()).unwrap_or(0)
+    }
+    fn append(&mut self, other: Self) {
+        if let Some(mut other) = other.extrinsics {
+            self.extrinsics
+                .get_or_insert_with(Vec::new)
+                .append(&mut other);
+        }
+    }
+}
+
+/// An extrinsic signed by the monitored account.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extrinsic {
+    pub extrinsic_index: ExtrinsicIndex,
+    pub block_num: BlockNumber,
+    pub block_timestamp: Timestamp,
+    pub call_module: String,
+    pub call_module_function: String,
+    pub extrinsic_hash: ExtrinsicHash,
+    pub success: bool,
+    #[serde(default, deserialize_with = "opt_balance")]
+    pub fee: Option<Balance>,
+}
+
+impl Extrinsic {
+    /// The `module.call` name used for filtering, e.g. `staking.bond`.
+    pub fn call(&self) -> String {
+        format!("{}.{}", self.call_module, self.call_module_function)
+    }
+}
+
+/// A change of the bonded (staked) balance of the monitored account.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BondEvent {
+    pub extrinsic_index: ExtrinsicIndex,
+    pub block_num: BlockNumber,
+    pub block_timestamp: Timestamp,
+    // Subscan reports the called extrinsic, e.g. `unbond`.
+    #[serde(alias = "call")]
+    pub kind: BondKind,
+    #[serde(deserialize_with = "string_or_number")]
+    pub amount: String,
+    pub era: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BondKind {
+    /// Both the initial bond and `bond_extra`.
+    #[serde(alias = "bond_extra")]
+    Bond,
+    Unbond,
+    Rebond,
+}
+
+impl Default for BondKind {
+    fn default() -> Self {
+        BondKind::Bond
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountPage {
+    pub account: Option<Account>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Account {
+    pub address: String,
+    #[serde(default)]
+    pub judgements: Option<Vec<Judgement>>,
+    /// The total balance (free and reserved), in tokens.
+    #[serde(default, deserialize_with = "opt_string_or_number")]
+    pub balance: Option<String>,
+    /// In tokens.
+    #[serde(default, deserialize_with = "opt_string_or_number")]
+    pub reserved: Option<String>,
+    /// The locked part of the free balance, in tokens.
+    #[serde(default, deserialize_with = "opt_string_or_number")]
+    pub lock: Option<String>,
+    #[serde(default)]
+    pub display: Option<String>,
+    #[serde(default)]
+    pub legal: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+impl Account {
+    pub fn identity(&self) -> Identity {
+        // Subscan reports unset fields as empty strings.
+        let field = |val: &Option<String>| val.clone().filter(|val| !val.is_empty());
+
+        Identity {
+            display: field(&self.display),
+            legal: field(&self.legal),
+            email: field(&self.email),
+            judgements: self.judgements.clone().unwrap_or_default(),
+        }
+    }
+    /// Converts the reported token amounts to plancks of the given network.
+    /// Missing amounts are treated as zero.
+    pub fn balance(&self, network: Network) -> Result<AccountBalance> {
+        let parse = |val: &Option<String>| match val {
+            Some(val) => Balance::parse_tokens(val, network.decimals()),
+            None => Ok(Balance::default()),
+        };
+
+        let total = parse(&self.balance)?;
+        let reserved = parse(&self.reserved)?;
+
+        Ok(AccountBalance {
+            free: Balance::from(total.as_plancks().saturating_sub(reserved.as_plancks())),
+            reserved: reserved,
+            locked: parse(&self.lock)?,
+        })
+    }
+}
+
+/// The on-chain identity of an account. Empty if none is set.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identity {
+    pub display: Option<String>,
+    pub legal: Option<String>,
+    pub email: Option<String>,
+    pub judgements: Vec<Judgement>,
+}
+
+/// A snapshot of the balances of an account, in plancks.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub free: Balance,
+    pub reserved: Balance,
+    pub locked: Balance,
+}
+
+/// A judgement of the account's identity by a registrar.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Judgement {
+    /// The index of the registrar.
+    pub index: u32,
+    /// E.g. "KnownGood", "Reasonable" or "LowQuality".
+    pub judgement: String,
+}
+
+impl Judgement {
+    /// Orders judgements by trust, so downgrades can be detected. Unknown
+    /// values rank like a pending judgement.
+    pub fn rank(&self) -> u8 {
+        match self.judgement.as_str() {
+            "KnownGood" => 6,
+            "Reasonable" => 5,
+            "Unknown" | "FeePaid" => 4,
+            "OutOfDate" => 3,
+            "LowQuality" => 2,
+            "Erroneous" => 1,
+            _ => 4,
+        }
+    }
+}
+
+impl RewardSlash {
+    /// The `module.event` name used for filtering, e.g. `staking.Reward`.
+    pub fn call(&self) -> String {
+        format!("{}.{}", self.module_id, self.event_id)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn describe_transfer_request() {
+        let api = ChainApi::new();
+        let alice = Context::alice();
+
+        let desc = api
+            .describe_request(Endpoint::Transfers, &alice, 10, 2)
+            .unwrap();
+
+        assert_eq!(
+            desc.url,
+            "https://polkadot.api.subscan.io/api/scan/transfers"
+        );
+        assert_eq!(
+            desc.body,
+            serde_json::json!({
+                "address": alice.stash,
+                "row": 10,
+                "page": 2,
+            })
+        );
+        assert!(desc
+            .headers
+            .iter()
+            .any(|(name, value)| name == "content-type" && value == "application/json"));
+
+        // Anonymous without a configured key.
+        assert!(!desc.headers.iter().any(|(name, _)| name == "X-API-Key"));
+    }
+
+    #[test]
+    fn api_key_header() {
+        let mut config = SubscanConfig::default();
+        config.kusama.api_key = Some("secret-kusama".to_string());
+        let api = ChainApi::with_config(config.clone());
+
+        let mut bob = Context::bob();
+        bob.network = Network::Kusama;
+        let desc = api
+            .describe_request(Endpoint::Transfers, &bob, 10, 1)
+            .unwrap();
+        assert!(desc
+            .headers
+            .iter()
+            .any(|(name, value)| name == "X-API-Key" && value == "secret-kusama"));
+
+        // Only for the configured network.
+        let desc = api
+            .describe_request(Endpoint::Transfers, &Context::alice(), 10, 1)
+            .unwrap();
+        assert!(!desc.headers.iter().any(|(name, _)| name == "X-API-Key"));
+
+        // The key is redacted when printed.
+        let desc = api
+            .describe_request(Endpoint::Transfers, &bob, 10, 1)
+            .unwrap();
+        let printed = desc.to_string();
+        assert!(printed.contains("X-API-Key: <redacted>"));
+        assert!(!printed.contains("secret-kusama"));
+        let printed = format!("{:?}", desc);
+        assert!(printed.contains("<redacted>"));
+        assert!(!printed.contains("secret-kusama"));
+        assert!(!format!("{:?}", config).contains("secret-kusama"));
+
+        // The override applies to all networks.
+        config.override_api_key("secret".to_string());
+        assert_eq!(config.polkadot.api_key.as_deref(), Some("secret"));
+        assert_eq!(config.kusama.api_key.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn extrinsic_hash_validation() {
+        let valid = format!("0x{}", "ab".repeat(32));
+        assert_eq!(ExtrinsicHash::new(valid.clone()).unwrap().as_str(), valid);
+
+        // Wrong length.
+        assert!(ExtrinsicHash::new("0xabcd".to_string()).is_err());
+        assert!(ExtrinsicHash::new(format!("0x{}", "ab".repeat(33))).is_err());
+        // Missing prefix.
+        assert!(ExtrinsicHash::new("ab".repeat(32)).is_err());
+        // Non-hex.
+        assert!(ExtrinsicHash::new(format!("0x{}", "zz".repeat(32))).is_err());
+        // Empty.
+        assert!(ExtrinsicHash::new(String::new()).is_err());
+        assert!(!ExtrinsicHash::default().is_valid());
+    }
+
+    const EMPTY_TRANSFERS: &'static str =
+        r#"{"code":0,"message":"Success","data":{"count":0,"transfers":null}}"#;
+
+    /// Minimal HTTP server answering one request per connection with the
+    /// given status codes and JSON bodies, in order.
+    async fn serve(responses: Vec<(u16, &'static str)>) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+
+                let resp = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn request_latency_metric() {
+        use metrics_util::DebugValue;
+
+        crate::tests::metrics();
+
+        let addr = serve(vec![(200, EMPTY_TRANSFERS)]).await;
+
+        let api = ChainApi::new();
+        let mut req = api
+            .describe_request(Endpoint::Transfers, &Context::alice(), 10, 1)
+            .unwrap();
+        req.url = format!("http://{}{}", addr, Endpoint::Transfers.path());
+
+        let resp: Response<TransfersPage> = api.post(req).await.unwrap();
+        assert_eq!(resp.data.count, 0);
+
+        let recorded = crate::tests::metrics().into_iter().any(|(key, value)| {
+            key == "monitor_chain_api_request_duration_seconds"
+                && matches!(value, DebugValue::Histogram(values) if values.len() == 1)
+        });
+        assert!(recorded);
+    }
+
+    #[tokio::test]
+    async fn custom_base_url() {
+        let addr = serve(vec![(200, EMPTY_TRANSFERS)]).await;
+
+        let api = ChainApi::with_config(SubscanConfig {
+            polkadot: SubscanNetworkConfig {
+                base_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let alice = Context::alice();
+        let desc = api
+            .describe_request(Endpoint::Transfers, &alice, 10, 1)
+            .unwrap();
+        assert_eq!(desc.url, format!("http://{}/api/scan/transfers", addr));
+
+        // Other networks fall back to the public host.
+        let mut bob = Context::bob();
+        bob.network = Network::Kusama;
+        let desc = api
+            .describe_request(Endpoint::Transfers, &bob, 10, 1)
+            .unwrap();
+        assert_eq!(desc.url, "https://kusama.api.subscan.io/api/scan/transfers");
+
+        bob.network = Network::Westend;
+        let desc = api
+            .describe_request(Endpoint::Transfers, &bob, 10, 1)
+            .unwrap();
+        assert_eq!(
+            desc.url,
+            "https://westend.api.subscan.io/api/scan/transfers"
+        );
+
+        let resp = api.request_transfer(&alice, 10, 1).await.unwrap();
+        assert_eq!(resp.data.count, 0);
+    }
+
+    #[test]
+    fn split_page_row_limit() {
+        assert_eq!(split_page(10, 3).unwrap(), (10, vec![3]));
+        assert_eq!(split_page(MAX_ROW, 0).unwrap(), (MAX_ROW, vec![0]));
+
+        // Entries 300..600.
+        assert_eq!(split_page(300, 1).unwrap(), (100, vec![3, 4, 5]));
+        // Would need more than one request per `MAX_ROW` entries.
+        assert!(matches!(
+            split_page(150, 1),
+            Err(ChainApiError::InvalidPage(_))
+        ));
+        assert!(matches!(
+            split_page(101, 1),
+            Err(ChainApiError::InvalidPage(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn paged_request_row_limit() {
+        let page = |rows: usize| -> &'static str {
+            let mut resp: Response<TransfersPage> = Default::default();
+            resp.data.transfers = Some(vec![Default::default(); rows]);
+            Box::leak(serde_json::to_string(&resp).unwrap().into_boxed_str())
+        };
+
+        // Stops at the partial page, the last response is never requested.
+        let addr = serve(vec![(200, page(100)), (200, page(30)), (200, page(100))]).await;
+
+        let api = ChainApi::with_config(SubscanConfig {
+            polkadot: SubscanNetworkConfig {
+                base_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let resp = api
+            .request_transfer(&Context::alice(), 300, 1)
+            .await
+            .unwrap();
+        assert_eq!(resp.data.entries(), 130);
+    }
+
+    #[tokio::test]
+    async fn unchanged_responses() {
+        // Like Subscan, every response carries the time it was generated at.
+        let page = |rows: usize, generated_at: u64| -> &'static str {
+            let mut resp: Response<TransfersPage> = Default::default();
+            resp.data.transfers = Some(vec![Default::default(); rows]);
+            let mut resp = serde_json::to_value(&resp).unwrap();
+            resp["generated_at"] = generated_at.into();
+            Box::leak(resp.to_string().into_boxed_str())
+        };
+
+        let addr = serve(vec![
+            (200, page(10, 1_628_000_000)),
+            (200, page(10, 1_628_000_010)),
+            (200, page(10, 1_628_000_020)),
+            (200, page(5, 1_628_000_030)),
+        ])
+        .await;
+
+        let api = ChainApi::with_config(SubscanConfig {
+            polkadot: SubscanNetworkConfig {
+                base_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let alice = Context::alice();
+
+        assert!(api.request_transfer(&alice, 10, 1).await.is_ok());
+        let err = api.request_transfer(&alice, 10, 1).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ChainApiError>(),
+            Some(&ChainApiError::Unchanged)
+        );
+
+        // Forgotten responses are never unchanged.
+        api.forget_responses(&alice);
+        assert!(api.request_transfer(&alice, 10, 1).await.is_ok());
+
+        let resp = api.request_transfer(&alice, 10, 1).await.unwrap();
+        assert_eq!(resp.data.entries(), 5);
+
+        assert_eq!(api.cache_stats(), (1, 3));
+    }
+
+    #[test]
+    fn response_cache_eviction() {
+        let api = ChainApi::new();
+        let alice = Context::alice();
+        let req = |page| {
+            api.describe_request(Endpoint::Transfers, &alice, 10, page)
+                .unwrap()
+        };
+
+        let cache = ResponseCache::new(&CacheConfig { capacity: 2 });
+        assert!(!cache.unchanged(&req(1), &alice.stash, "a"));
+        assert!(!cache.unchanged(&req(2), &alice.stash, "a"));
+        assert!(cache.unchanged(&req(1), &alice.stash, "a"));
+        assert!(!cache.unchanged(&req(1), &alice.stash, "b"));
+
+        // Evicts the least recently used page 2.
+        assert!(!cache.unchanged(&req(3), &alice.stash, "a"));
+        assert!(!cache.unchanged(&req(2), &alice.stash, "a"));
+        assert!(cache.unchanged(&req(3), &alice.stash, "a"));
+
+        // Disabled.
+        let cache = ResponseCache::new(&CacheConfig { capacity: 0 });
+        assert!(!cache.unchanged(&req(1), &alice.stash, "a"));
+        assert!(!cache.unchanged(&req(1), &alice.stash, "a"));
+    }
+
+    fn fast_retries(max_retries: usize) -> ChainApi {
+        ChainApi::with_config(SubscanConfig {
+            retry: RetryConfig {
+                max_retries: max_retries,
+                base_ms: 10,
+                cap_ms: 50,
+            },
+            rate_limit: RateLimitConfig {
+                requests_per_second: 1_000.0,
+                burst: 10,
+            },
+            ..Default::default()
+        })
+    }
+
+    async fn request_transfers(
+        api: &ChainApi,
+        addr: std::net::SocketAddr,
+    ) -> ApiResult<Response<TransfersPage>> {
+        let mut req = api
+            .describe_request(Endpoint::Transfers, &Context::alice(), 10, 1)
+            .unwrap();
+        req.url = format!("http://{}{}", addr, Endpoint::Transfers.path());
+
+        api.post(req).await
+    }
+
+    #[tokio::test]
+    async fn retry_transient_failures() {
+        let addr = serve(vec![(503, "{}"), (502, "{}"), (200, EMPTY_TRANSFERS)]).await;
+
+        let resp = request_transfers(&fast_retries(3), addr).await.unwrap();
+        assert_eq!(resp.data.count, 0);
+
+        // Gives up after the retries are exhausted.
+        let addr = serve(vec![(503, "{}"), (502, "{}"), (200, EMPTY_TRANSFERS)]).await;
+
+        let err = request_transfers(&fast_retries(1), addr).await.unwrap_err();
+        assert_eq!(err, ChainApiError::Http(502));
+
+        // Rate limiting reported in the response envelope.
+        let addr = serve(vec![
+            (
+                200,
+                r#"{"code":20008,"message":"API rate limit exceeded","data":null}"#,
+            ),
+            (200, EMPTY_TRANSFERS),
+        ])
+        .await;
+
+        let resp = request_transfers(&fast_retries(3), addr).await.unwrap();
+        assert_eq!(resp.data.count, 0);
+
+        // Permanent failures are not retried.
+        let addr = serve(vec![
+            (
+                200,
+                r#"{"code":10004,"message":"Record Not Found","data":null}"#,
+            ),
+            (200, EMPTY_TRANSFERS),
+        ])
+        .await;
+
+        let err = request_transfers(&fast_retries(3), addr).await.unwrap_err();
+        assert_eq!(err, ChainApiError::NotFound);
+
+        // Nothing is listening anymore.
+        let addr = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let err = request_transfers(&fast_retries(0), addr).await.unwrap_err();
+        assert!(matches!(err, ChainApiError::Transport(_)));
+        assert!(err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn invalid_request_headers() {
+        // Never sent, so nothing needs to listen.
+        let api = fast_retries(3);
+        let req = || {
+            api.describe_request(Endpoint::Transfers, &Context::alice(), 10, 1)
+                .unwrap()
+        };
+
+        let mut invalid_name = req();
+        invalid_name
+            .headers
+            .push(("X-API Key".to_string(), "secret".to_string()));
+        let err = api
+            .post::<Response<TransfersPage>>(invalid_name)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChainApiError::InvalidHeader(_)));
+        assert!(!err.is_transient());
+
+        // The value is not part of the error.
+        let mut invalid_value = req();
+        invalid_value
+            .headers
+            .push((API_KEY_HEADER.to_string(), "secret\n".to_string()));
+        let err = api
+            .post::<Response<TransfersPage>>(invalid_value)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChainApiError::InvalidHeader(_)));
+        assert!(!err.to_string().contains("secret"));
+
+        // Still an `anyhow::Error` for the backend callers.
+        let mut config = SubscanConfig::default();
+        config.override_api_key("secret\n".to_string());
+        let err = ChainApi::with_config(config)
+            .request_transfer(&Context::alice(), 10, 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ChainApiError>(),
+            Some(ChainApiError::InvalidHeader(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn shared_rate_limit() {
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            requests_per_second: 20.0,
+            burst: 2,
+        }));
+
+        // Three "fetchers" sharing the budget, two requests each.
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                tokio::spawn(async move {
+                    limiter.acquire().await;
+                    limiter.acquire().await;
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // The burst is free, the other four requests are refilled at 20/s.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(190), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn retry_delay_bounds() {
+        let config = RetryConfig {
+            max_retries: 10,
+            base_ms: 100,
+            cap_ms: 1_000,
+        };
+
+        for _ in 0..100 {
+            let first = config.delay(0).as_millis();
+            assert!(first >= 50 && first <= 100);
+
+            let third = config.delay(2).as_millis();
+            assert!(third >= 200 && third <= 400);
+
+            // Capped.
+            let last = config.delay(9).as_millis();
+            assert!(last >= 500 && last <= 1_000);
+        }
+    }
+
+    #[test]
+    fn describe_transfer_request_direction() {
+        let api = ChainApi::new();
+        let mut alice = Context::alice();
+        alice.transfer_direction = TransferDirection::Sent;
+
+        let desc = api
+            .describe_request(Endpoint::Transfers, &alice, 10, 1)
+            .unwrap();
+
+        assert_eq!(desc.body["direction"], "sent");
+
+        // Not sent for other endpoints.
+        let desc = api
+            .describe_request(Endpoint::RewardsSlashes, &alice, 10, 1)
+            .unwrap();
+
+        assert!(desc.body.get("direction").is_none());
+    }
+
+    #[test]
+    fn describe_request_block_bounds() {
+        let api = ChainApi::new();
+        let mut alice = Context::alice();
+
+        let desc = api
+            .describe_request(Endpoint::Bonds, &alice, 10, 1)
+            .unwrap();
+        assert!(desc.body.get("from_block").is_none());
+        assert!(desc.body.get("to_block").is_none());
+
+        alice.blocks.since = Some(BlockNumber::from(100));
+        let desc = api
+            .describe_request(Endpoint::Bonds, &alice, 10, 1)
+            .unwrap();
+        assert_eq!(desc.body["from_block"], 100);
+        assert!(desc.body.get("to_block").is_none());
+    }
+
+    #[test]
+    fn extrinsic_filter() {
+        let filter = ExtrinsicFilter {
+            call_module: "Staking".to_string(),
+            call_name: "bond".to_string(),
+        };
+        assert!(filter.validate().is_ok());
+
+        let mut body = serde_json::json!({"address": "alice", "row": 10, "page": 0});
+        filter.apply(&mut body);
+        assert_eq!(body["module"], "staking");
+        assert_eq!(body["call"], "bond");
+
+        let filter = ExtrinsicFilter {
+            call_module: "staking".to_string(),
+            call_name: " ".to_string(),
+        };
+        assert!(filter.validate().is_err());
+    }
 
     impl From<String> for ExtrinsicIndex {
         fn from(val: String) -> Self {
@@ -268,4 +2036,307 @@ mod tests {
             ExtrinsicHash(val)
         }
     }
+
+    #[test]
+    fn parse_maintenance_body() {
+        let html = "<!DOCTYPE html><html><body>Under maintenance</body></html>";
+
+        // Detected by body.
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("application/json"), html).unwrap_err();
+        assert_eq!(err, ChainApiError::Unavailable);
+        assert!(err.is_transient());
+
+        // Detected by content type.
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("text/html; charset=utf-8"), "Maintenance")
+                .unwrap_err();
+        assert_eq!(err, ChainApiError::Unavailable);
+
+        // Regular JSON errors are not mistaken for maintenance.
+        let err = parse_body::<Response<TransfersPage>>(None, "{").unwrap_err();
+        assert!(matches!(err, ChainApiError::Decode(_)));
+    }
+
+    #[test]
+    fn parse_not_found_body() {
+        let body = r#"{"code":10004,"message":"Record Not Found","data":null}"#;
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("application/json"), body).unwrap_err();
+        assert_eq!(err, ChainApiError::NotFound);
+        assert!(!err.is_transient());
+
+        // Empty results are not an unknown account.
+        let body = r#"{"code":0,"message":"Success","data":{"count":0,"transfers":null}}"#;
+        let resp = parse_body::<Response<TransfersPage>>(Some("application/json"), body).unwrap();
+        assert!(resp.data.transfers.is_none());
+    }
+
+    #[test]
+    fn parse_error_code_body() {
+        let body = r#"{"code":10001,"message":"Params Error","data":null}"#;
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("application/json"), body).unwrap_err();
+        assert_eq!(
+            err,
+            ChainApiError::Subscan {
+                code: 10001,
+                message: "Params Error".to_string()
+            }
+        );
+        assert!(!err.is_transient());
+        assert_eq!(
+            err.to_string(),
+            "Subscan responded with code 10001: Params Error"
+        );
+
+        let body = r#"{"code":20008,"message":"API rate limit exceeded","data":null}"#;
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("application/json"), body).unwrap_err();
+        assert_eq!(err, ChainApiError::RateLimited);
+        assert!(err.is_transient());
+
+        // Valid JSON, but not a page.
+        let body = r#"{"code":0,"message":"Success","data":{"count":"many"}}"#;
+        let err =
+            parse_body::<Response<TransfersPage>>(Some("application/json"), body).unwrap_err();
+        assert!(matches!(err, ChainApiError::Decode(_)));
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn parse_bond_kinds() {
+        let body = r#"{"code":0,"message":"Success","data":{"count":3,"list":[
+            {"extrinsic_index":"1-1","block_num":1,"block_timestamp":1,"call":"bond_extra",
+             "amount":"10","era":100},
+            {"extrinsic_index":"2-1","block_num":2,"block_timestamp":2,"call":"unbond",
+             "amount":5,"era":101},
+            {"extrinsic_index":"3-1","block_num":3,"block_timestamp":3,"call":"rebond",
+             "amount":"5","era":102}]}}"#;
+        let resp = parse_body::<Response<BondsPage>>(Some("application/json"), body).unwrap();
+
+        let kinds: Vec<BondKind> = resp.data.list.unwrap().iter().map(|b| b.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![BondKind::Bond, BondKind::Unbond, BondKind::Rebond]
+        );
+    }
+
+    #[test]
+    fn parse_account() {
+        let body = r#"{"code":0,"message":"Success","data":{"account":{
+            "address":"15fTH34bbKGMUjF1bLmTqxPYgpg481imThwhWcQfCyktyBzL",
+            "balance":"12.5","reserved":"2","lock":1.25,
+            "display":"Alice","legal":"","judgements":[{"index":0,"judgement":"Reasonable"}]}}}"#;
+        let resp = parse_body::<Response<AccountPage>>(Some("application/json"), body).unwrap();
+        let account = resp.data.account.unwrap();
+
+        assert_eq!(
+            account.identity(),
+            Identity {
+                display: Some("Alice".to_string()),
+                legal: None,
+                email: None,
+                judgements: vec![Judgement {
+                    index: 0,
+                    judgement: "Reasonable".to_string(),
+                }],
+            }
+        );
+
+        let balance = account.balance(Network::Polkadot).unwrap();
+        assert_eq!(
+            balance,
+            AccountBalance {
+                free: Balance::from(105_000_000_000),
+                reserved: Balance::from(20_000_000_000),
+                locked: Balance::from(12_500_000_000),
+            }
+        );
+    }
+
+    fn arb_json() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(|n| json!(n)),
+            any::<f64>().prop_map(|n| json!(n)),
+            ".*".prop_map(Value::String),
+        ];
+
+        leaf.prop_recursive(3, 32, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+                prop::collection::hash_map("[a-z_]{1,12}", inner, 0..8)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn account_display() -> Value {
+        json!({
+            "address": "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP",
+            "display": "Alice",
+            "judgements": null,
+            "account_index": "",
+            "identity": false,
+            "parent": null,
+        })
+    }
+
+    fn transfer() -> Value {
+        json!({
+            "amount": "1.5",
+            "block_num": 5_000_000,
+            "block_timestamp": 1_620_000_000,
+            "extrinsic_index": "5000000-2",
+            "fee": "156000000",
+            "from": "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP",
+            "from_account_display": account_display(),
+            "hash": "0x00",
+            "module": "balances",
+            "nonce": 1,
+            "success": true,
+            "to": "1b3NhsSEqWSQwS6nPGKgCrSjv9Kp13CnhraLV5Coyd8ooXB",
+            "to_account_display": account_display(),
+        })
+    }
+
+    fn reward_slash() -> Value {
+        json!({
+            "amount": "15000000000",
+            "event_index": "5000000-10",
+            "block_num": 5_000_000,
+            "extrinsic_idx": 2,
+            "module_id": "staking",
+            "event_id": "Reward",
+            "params": "[]",
+            "extrinsic_hash": "0x00",
+            "event_idx": 10,
+        })
+    }
+
+    fn envelope(data: Value) -> Value {
+        json!({
+            "code": 0,
+            "data": data,
+            "message": "Success",
+            "ttl": 1,
+        })
+    }
+
+    /// Replaces (or removes, on `Null`) the fields at the given positions and
+    /// adds the extra fields.
+    fn mutate(
+        mut value: Value,
+        changes: Vec<(usize, Value)>,
+        extra: Vec<(String, Value)>,
+    ) -> Value {
+        let map = value.as_object_mut().unwrap();
+        let keys: Vec<String> = map.keys().cloned().collect();
+
+        for (idx, new) in changes {
+            let key = &keys[idx % keys.len()];
+            if new.is_null() {
+                map.remove(key);
+            } else {
+                map.insert(key.clone(), new);
+            }
+        }
+
+        for (key, new) in extra {
+            map.entry(key).or_insert(new);
+        }
+
+        value
+    }
+
+    fn arb_changes() -> impl Strategy<Value = (Vec<(usize, Value)>, Vec<(String, Value)>)> {
+        (
+            prop::collection::vec((any::<usize>(), arb_json()), 0..4),
+            prop::collection::vec(("[a-z_]{1,12}", arb_json()), 0..4),
+        )
+    }
+
+    #[test]
+    fn deserialize_templates() {
+        let page: Response<TransfersPage> = serde_json::from_value(envelope(json!({
+            "count": 1,
+            "transfers": [transfer()],
+        })))
+        .unwrap();
+        assert_eq!(page.data.transfers.unwrap()[0].amount, "1.5");
+
+        let page: Response<RewardsSlashesPage> = serde_json::from_value(envelope(json!({
+            "count": 1,
+            "list": [reward_slash()],
+        })))
+        .unwrap();
+        assert_eq!(page.data.list.unwrap()[0].amount, "15000000000");
+
+        // Null fields where Subscan omits them.
+        let mut value = transfer();
+        value["fee"] = Value::Null;
+        let parsed: Transfer = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.fee, None);
+
+        // Fees are plancks, as a string or as a number.
+        let parsed: Transfer = serde_json::from_value(transfer()).unwrap();
+        assert_eq!(parsed.fee, Some(Balance::from(156_000_000)));
+        let mut value = transfer();
+        value["fee"] = json!(156_000_000);
+        let parsed: Transfer = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.fee, Some(Balance::from(156_000_000)));
+        let mut value = transfer();
+        value["fee"] = json!("0.1");
+        assert!(serde_json::from_value::<Transfer>(value).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn amount_string_or_number(amount in any::<u64>(), as_string in any::<bool>()) {
+            let mut value = reward_slash();
+            value["amount"] = if as_string {
+                json!(amount.to_string())
+            } else {
+                json!(amount)
+            };
+
+            let reward_slash: RewardSlash = serde_json::from_value(value).unwrap();
+            prop_assert_eq!(reward_slash.amount, amount.to_string());
+        }
+
+        #[test]
+        fn transfer_never_panics((changes, extra) in arb_changes()) {
+            let value = envelope(json!({
+                "count": 1,
+                "transfers": [mutate(transfer(), changes, extra)],
+            }));
+
+            if let Ok(page) = serde_json::from_value::<Response<TransfersPage>>(value) {
+                prop_assert_eq!(page.data.transfers.map(|t| t.len()), Some(1));
+            }
+        }
+
+        #[test]
+        fn reward_slash_never_panics((changes, extra) in arb_changes()) {
+            let value = envelope(json!({
+                "count": 1,
+                "list": [mutate(reward_slash(), changes, extra)],
+            }));
+
+            if let Ok(page) = serde_json::from_value::<Response<RewardsSlashesPage>>(value) {
+                prop_assert_eq!(page.data.list.map(|l| l.len()), Some(1));
+            }
+        }
+
+        #[test]
+        fn response_never_panics(data in arb_json(), (changes, extra) in arb_changes()) {
+            let value = mutate(envelope(data), changes, extra);
+
+            let _ = serde_json::from_value::<Response<TransfersPage>>(value.clone());
+            let _ = serde_json::from_value::<Response<RewardsSlashesPage>>(value.clone());
+            let _ = serde_json::from_value::<Response<NominationsPage>>(value);
+        }
+    }
 }
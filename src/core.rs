@@ -1,25 +1,48 @@
-use crate::chain_api::{ChainApi, NominationsPage, Response, RewardsSlashesPage, TransfersPage};
+use crate::alerting::{OpsAlerter, SendAlert};
+use crate::chain_api::{
+    AccountPage, BondsPage, ChainApi, ChainApiError, ChainBackend, ExtrinsicFilter, ExtrinsicsPage,
+    GovernanceVotesPage, NominationsPage, Response, RewardsSlashesPage, Transfer, TransfersPage,
+    MAX_ROW,
+};
 use crate::database::{Database, DatabaseReader};
-use crate::publishing::{GoogleDrive, Publisher};
+use crate::publishing::Publisher;
 use crate::reporting::{
-    GenerateReport, NominationReportGenerator, RewardSlashReportGenerator, TransferReportGenerator,
+    GenerateReport, NominationReport, NominationReportGenerator, RewardSlashReport,
+    RewardSlashReportGenerator, TransferReport, TransferReportGenerator,
 };
-use crate::{Context, Result, Timestamp};
-
-use std::collections::HashSet;
-
+use crate::{Balance, BlockBounds, BlockNumber, Context, ContextId, Network, Result, Timestamp};
+use futures::future::select_all;
+use futures::{stream, StreamExt};
+use metrics::{counter, gauge, increment_counter};
+use rand::{thread_rng, Rng};
+use std::collections::{HashMap, HashSet};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock, Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration};
+use tracing::{info_span, Instrument};
 
 const ROW_AMOUNT: usize = 10;
+const CONCURRENCY: usize = 1;
+/// Upper bound of the random delay (in milliseconds) before the first request
+/// of a context, if multiple contexts are processed at the same time.
+const CONTEXT_JITTER: u64 = 500;
 const FAILED_TASK_SLEEP: u64 = 30;
 const LOOP_INTERVAL: u64 = 300;
 const MAX_ERR_DIFF: u64 = 60;
+const DEFAULT_PUBLISH_CONCURRENCY: usize = 4;
+/// Seconds covered by a single balance snapshot.
+const BALANCE_SNAPSHOT_INTERVAL: u64 = 3_600;
+/// The call (module and name) submitting a governance proposal.
+const PROPOSAL_CALL: (&str, &str) = ("democracy", "propose");
 
 pub struct TransferFetcher {
     db: Database,
-    api: Arc<ChainApi>,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+    notifier: Option<Arc<dyn SendAlert + Send + Sync>>,
+    thresholds: Option<Arc<TransferThresholds>>,
 }
 
 #[async_trait]
@@ -29,20 +52,69 @@ impl FetchChainData for TransferFetcher {
     fn name() -> &'static str {
         "TransferFetcher"
     }
-    fn new(db: Database, api: Arc<ChainApi>) -> Self {
-        TransferFetcher { db: db, api: api }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        TransferFetcher {
+            db: db,
+            api: api,
+            notifier: None,
+            thresholds: None,
+        }
+    }
+    fn set_notifier(&mut self, notifier: Arc<dyn SendAlert + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+    fn notifier(&self) -> Option<&Arc<dyn SendAlert + Send + Sync>> {
+        self.notifier.as_ref()
+    }
+    fn set_transfer_thresholds(&mut self, thresholds: Arc<TransferThresholds>) {
+        self.thresholds = Some(thresholds);
     }
     async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
         self.api.request_transfer(context, row, page).await
     }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
     async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
-        self.db.store_transfer_event(context, data).await
+        let transfers = self.db.store_transfers(context, data).await?;
+
+        if let (Some(_), Some(thresholds)) = (&self.notifier, &self.thresholds) {
+            for transfer in &transfers {
+                let amount = match thresholds.exceeded(context.network, transfer) {
+                    Ok(Some(amount)) => amount,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(
+                            "{}: Failed to check transfer amount: {:?}",
+                            Self::name(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let message = format!(
+                    "Large transfer of {} for {} ({}, {}): {} -> {}, extrinsic {}",
+                    amount.in_tokens(context.network),
+                    context.description,
+                    context.network.as_str(),
+                    context.stash,
+                    transfer.from,
+                    transfer.to,
+                    transfer.hash,
+                );
+
+                self.notify(&message).await;
+            }
+        }
+
+        Ok(transfers.len())
     }
 }
 
 pub struct RewardsSlashesFetcher {
     db: Database,
-    api: Arc<ChainApi>,
+    api: Arc<dyn ChainBackend + Send + Sync>,
 }
 
 #[async_trait]
@@ -52,12 +124,15 @@ impl FetchChainData for RewardsSlashesFetcher {
     fn name() -> &'static str {
         "RewardsSlashesFetcher"
     }
-    fn new(db: Database, api: Arc<ChainApi>) -> Self {
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
         RewardsSlashesFetcher { db: db, api: api }
     }
     async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
         self.api.request_reward_slash(context, row, page).await
     }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
     async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
         self.db.store_reward_slash_event(context, data).await
     }
@@ -65,7 +140,7 @@ impl FetchChainData for RewardsSlashesFetcher {
 
 pub struct NominationsFetcher {
     db: Database,
-    api: Arc<ChainApi>,
+    api: Arc<dyn ChainBackend + Send + Sync>,
 }
 
 #[async_trait]
@@ -75,29 +150,406 @@ impl FetchChainData for NominationsFetcher {
     fn name() -> &'static str {
         "NominationsFetcher"
     }
-    fn new(db: Database, api: Arc<ChainApi>) -> Self {
+    fn paged() -> bool {
+        false
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
         NominationsFetcher { db: db, api: api }
     }
     async fn fetch_data(&self, context: &Context, _row: usize, _page: usize) -> Result<Self::Data> {
         self.api.request_nominations(context).await
     }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
     async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
         self.db.store_nomination_event(context, data).await
     }
 }
 
+pub struct GovernanceFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+    notifier: Option<Arc<dyn SendAlert + Send + Sync>>,
+}
+
+#[async_trait]
+impl FetchChainData for GovernanceFetcher {
+    type Data = Response<GovernanceVotesPage>;
+
+    fn name() -> &'static str {
+        "GovernanceFetcher"
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        GovernanceFetcher {
+            db: db,
+            api: api,
+            notifier: None,
+        }
+    }
+    fn set_notifier(&mut self, notifier: Arc<dyn SendAlert + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+    fn notifier(&self) -> Option<&Arc<dyn SendAlert + Send + Sync>> {
+        self.notifier.as_ref()
+    }
+    async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
+        self.api.request_governance_votes(context, row, page).await
+    }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        let votes = self.db.store_governance_votes(context, data).await?;
+
+        if self.notifier.is_some() {
+            for vote in &votes {
+                let message = format!(
+                    "{} ({}) voted {} on referendum #{} with {} {} (conviction: {})",
+                    context.description,
+                    context.network.as_str(),
+                    vote.status,
+                    vote.referendum_index,
+                    vote.amount,
+                    context.network.token_symbol(),
+                    vote.conviction,
+                );
+
+                self.notify(&message).await;
+            }
+        }
+
+        Ok(votes.len())
+    }
+}
+
+/// Fetches the governance proposals submitted by the account, i.e. its
+/// extrinsics of the `PROPOSAL_CALL`. Runs along with the `GovernanceFetcher`.
+pub struct GovernanceProposalFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+    notifier: Option<Arc<dyn SendAlert + Send + Sync>>,
+}
+
+#[async_trait]
+impl FetchChainData for GovernanceProposalFetcher {
+    type Data = Response<ExtrinsicsPage>;
+
+    fn name() -> &'static str {
+        "GovernanceProposalFetcher"
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        GovernanceProposalFetcher {
+            db: db,
+            api: api,
+            notifier: None,
+        }
+    }
+    fn set_notifier(&mut self, notifier: Arc<dyn SendAlert + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+    fn notifier(&self) -> Option<&Arc<dyn SendAlert + Send + Sync>> {
+        self.notifier.as_ref()
+    }
+    async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
+        let (module, call) = PROPOSAL_CALL;
+        let filter = ExtrinsicFilter {
+            call_module: module.to_string(),
+            call_name: call.to_string(),
+        };
+
+        self.api
+            .request_extrinsics(context, &filter, row, page)
+            .await
+    }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        let proposals = self.db.store_governance_proposals(context, data).await?;
+
+        if self.notifier.is_some() {
+            for proposal in &proposals {
+                let message = format!(
+                    "{} ({}) submitted a governance proposal in extrinsic {} (block {})",
+                    context.description,
+                    context.network.as_str(),
+                    proposal.extrinsic_index,
+                    proposal.block_num,
+                );
+
+                self.notify(&message).await;
+            }
+        }
+
+        Ok(proposals.len())
+    }
+}
+
+pub struct JudgementFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+    notifier: Option<Arc<dyn SendAlert + Send + Sync>>,
+}
+
+#[async_trait]
+impl FetchChainData for JudgementFetcher {
+    type Data = Response<AccountPage>;
+
+    fn name() -> &'static str {
+        "JudgementFetcher"
+    }
+    fn paged() -> bool {
+        false
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        JudgementFetcher {
+            db: db,
+            api: api,
+            notifier: None,
+        }
+    }
+    fn set_notifier(&mut self, notifier: Arc<dyn SendAlert + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+    fn notifier(&self) -> Option<&Arc<dyn SendAlert + Send + Sync>> {
+        self.notifier.as_ref()
+    }
+    async fn fetch_data(&self, context: &Context, _row: usize, _page: usize) -> Result<Self::Data> {
+        self.api.request_account(context).await
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        let judgements = data
+            .data
+            .account
+            .as_ref()
+            .and_then(|account| account.judgements.clone())
+            .unwrap_or_default();
+
+        let changes = self.db.store_judgements(context, &judgements).await?;
+
+        if self.notifier.is_some() {
+            for change in changes.iter().filter(|change| change.is_downgrade()) {
+                let message = format!(
+                    "Judgement of registrar #{} for {} ({}) changed from {} to {}",
+                    change.registrar,
+                    context.description,
+                    context.network.as_str(),
+                    change
+                        .old
+                        .as_ref()
+                        .map(|j| j.judgement.as_str())
+                        .unwrap_or("none"),
+                    change
+                        .new
+                        .as_ref()
+                        .map(|j| j.judgement.as_str())
+                        .unwrap_or("none (revoked)"),
+                );
+
+                self.notify(&message).await;
+            }
+        }
+
+        Ok(changes.len())
+    }
+}
+
+pub struct BondsFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+}
+
+#[async_trait]
+impl FetchChainData for BondsFetcher {
+    type Data = Response<BondsPage>;
+
+    fn name() -> &'static str {
+        "BondsFetcher"
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        BondsFetcher { db: db, api: api }
+    }
+    async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
+        self.api.request_bonds(context, row, page).await
+    }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        self.db.store_bond_events(context, data).await
+    }
+}
+
+pub struct ExtrinsicsFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+    filter: Option<ExtrinsicFilter>,
+}
+
+#[async_trait]
+impl FetchChainData for ExtrinsicsFetcher {
+    type Data = Response<ExtrinsicsPage>;
+
+    fn name() -> &'static str {
+        "ExtrinsicsFetcher"
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        ExtrinsicsFetcher {
+            db: db,
+            api: api,
+            filter: None,
+        }
+    }
+    fn set_extrinsic_filter(&mut self, filter: ExtrinsicFilter) {
+        self.filter = Some(filter);
+    }
+    async fn fetch_data(&self, context: &Context, row: usize, page: usize) -> Result<Self::Data> {
+        let filter = self
+            .filter
+            .as_ref()
+            .ok_or_else(|| anyhow!("no extrinsic filter was set"))?;
+
+        self.api
+            .request_extrinsics(context, filter, row, page)
+            .await
+    }
+    fn forget_responses(&self, context: &Context) {
+        self.api.forget_responses(context);
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        self.db.store_extrinsic_event(context, data).await
+    }
+}
+
+pub struct BalanceFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+}
+
+#[async_trait]
+impl FetchChainData for BalanceFetcher {
+    type Data = Response<AccountPage>;
+
+    fn name() -> &'static str {
+        "BalanceFetcher"
+    }
+    fn paged() -> bool {
+        false
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        BalanceFetcher { db: db, api: api }
+    }
+    // A single snapshot per pass, there are no pages.
+    async fn fetch_data(&self, context: &Context, _row: usize, _page: usize) -> Result<Self::Data> {
+        self.api.request_balance(context).await
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        let balance = data
+            .data
+            .account
+            .as_ref()
+            .ok_or(anyhow!("No account found in response body"))?
+            .balance(context.network)?;
+
+        // One snapshot per interval, repeated passes replace it.
+        let now = Timestamp::now().as_secs();
+        let timestamp = Timestamp::from(now - now % BALANCE_SNAPSHOT_INTERVAL);
+
+        self.db
+            .store_balance_snapshot(context, timestamp, &balance)
+            .await
+    }
+}
+
+pub struct IdentityFetcher {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
+}
+
+#[async_trait]
+impl FetchChainData for IdentityFetcher {
+    type Data = Response<AccountPage>;
+
+    fn name() -> &'static str {
+        "IdentityFetcher"
+    }
+    fn paged() -> bool {
+        false
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        IdentityFetcher { db: db, api: api }
+    }
+    async fn fetch_data(&self, context: &Context, _row: usize, _page: usize) -> Result<Self::Data> {
+        self.api.request_identity(context).await
+    }
+    async fn store_data(&self, context: &Context, data: &Self::Data) -> Result<usize> {
+        let identity = data
+            .data
+            .account
+            .as_ref()
+            .ok_or(anyhow!("No account found in response body"))?
+            .identity();
+
+        self.db.store_identity(context, &identity).await
+    }
+}
+
 #[async_trait]
 pub trait FetchChainData {
     type Data: Send + Sync + std::fmt::Debug + DataInfo;
 
     fn name() -> &'static str;
-    fn new(db: Database, api: Arc<ChainApi>) -> Self;
+    /// Whether the entries are requested in pages. Fetchers of the current
+    /// state of an account (e.g. its nominations) ignore the page, so they
+    /// only request the first one per pass.
+    fn paged() -> bool {
+        true
+    }
+    fn new(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self;
+    /// Sets the channel for notifications about new entries. Fetchers which
+    /// don't notify ignore it.
+    fn set_notifier(&mut self, _notifier: Arc<dyn SendAlert + Send + Sync>) {}
+    /// The notifier set with `set_notifier`, if any.
+    fn notifier(&self) -> Option<&Arc<dyn SendAlert + Send + Sync>> {
+        None
+    }
+    /// Sends the notification, if a notifier is set. A failed notification
+    /// is only logged, so it does not prevent further scraping.
+    async fn notify(&self, message: &str) {
+        if let Some(notifier) = self.notifier() {
+            if let Err(err) = notifier.send_alert(message).await {
+                error!("{}: Failed to send notification: {:?}", Self::name(), err);
+            }
+        }
+    }
+    /// Sets the thresholds for notifications about large transfers. Fetchers
+    /// which don't store transfers ignore it.
+    fn set_transfer_thresholds(&mut self, _thresholds: Arc<TransferThresholds>) {}
+    /// Sets the calls to fetch. Fetchers of other entries ignore it.
+    fn set_extrinsic_filter(&mut self, _filter: ExtrinsicFilter) {}
+    /// Fetches a page of `row` entries. Only the last page may contain fewer
+    /// entries, even if the backend caps the entries per request (see
+    /// `chain_api::MAX_ROW`), since paging stops at the first partial page.
     async fn fetch_data(&self, _: &Context, row: usize, page: usize) -> Result<Self::Data>;
     async fn store_data(&self, _: &Context, data: &Self::Data) -> Result<usize>;
+    /// Called if the fetched entries could not be stored, so the backend
+    /// does not report the same page as unchanged (see
+    /// `ChainApiError::Unchanged`) on the next pass.
+    fn forget_responses(&self, _: &Context) {}
 }
 
 pub trait DataInfo {
     fn is_empty(&self) -> bool;
+    /// Number of entries in the response.
+    fn len(&self) -> usize;
+    /// Block numbers of the entries, newest first. Empty for entries without
+    /// a block, which are never restricted by the block bounds.
+    fn blocks(&self) -> Vec<BlockNumber> {
+        vec![]
+    }
+    /// Drops the entries outside of the bounds, in case Subscan ignores them.
+    fn retain_blocks(&mut self, _bounds: &BlockBounds) {}
 }
 
 #[async_trait]
@@ -105,6 +557,22 @@ impl DataInfo for Response<TransfersPage> {
     fn is_empty(&self) -> bool {
         self.data.transfers.is_none()
     }
+    fn len(&self) -> usize {
+        self.data.transfers.as_ref().map(|t| t.len()).unwrap_or(0)
+    }
+    fn blocks(&self) -> Vec<BlockNumber> {
+        self.data
+            .transfers
+            .iter()
+            .flatten()
+            .map(|t| t.block_num)
+            .collect()
+    }
+    fn retain_blocks(&mut self, bounds: &BlockBounds) {
+        if let Some(transfers) = &mut self.data.transfers {
+            transfers.retain(|t| bounds.contains(t.block_num));
+        }
+    }
 }
 
 #[async_trait]
@@ -112,6 +580,22 @@ impl DataInfo for Response<RewardsSlashesPage> {
     fn is_empty(&self) -> bool {
         self.data.list.is_none()
     }
+    fn len(&self) -> usize {
+        self.data.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+    fn blocks(&self) -> Vec<BlockNumber> {
+        self.data
+            .list
+            .iter()
+            .flatten()
+            .map(|e| e.block_num)
+            .collect()
+    }
+    fn retain_blocks(&mut self, bounds: &BlockBounds) {
+        if let Some(list) = &mut self.data.list {
+            list.retain(|e| bounds.contains(e.block_num));
+        }
+    }
 }
 
 #[async_trait]
@@ -119,301 +603,3262 @@ impl DataInfo for Response<NominationsPage> {
     fn is_empty(&self) -> bool {
         self.data.list.is_none()
     }
+    fn len(&self) -> usize {
+        self.data.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+}
+
+#[async_trait]
+impl DataInfo for Response<BondsPage> {
+    fn is_empty(&self) -> bool {
+        self.data.list.is_none()
+    }
+    fn len(&self) -> usize {
+        self.data.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+    fn blocks(&self) -> Vec<BlockNumber> {
+        self.data
+            .list
+            .iter()
+            .flatten()
+            .map(|e| e.block_num)
+            .collect()
+    }
+    fn retain_blocks(&mut self, bounds: &BlockBounds) {
+        if let Some(list) = &mut self.data.list {
+            list.retain(|e| bounds.contains(e.block_num));
+        }
+    }
+}
+
+#[async_trait]
+impl DataInfo for Response<GovernanceVotesPage> {
+    fn is_empty(&self) -> bool {
+        self.data.list.is_none()
+    }
+    fn len(&self) -> usize {
+        self.data.list.as_ref().map(|l| l.len()).unwrap_or(0)
+    }
+}
+
+#[async_trait]
+impl DataInfo for Response<ExtrinsicsPage> {
+    fn is_empty(&self) -> bool {
+        self.data.extrinsics.is_none()
+    }
+    fn len(&self) -> usize {
+        self.data.extrinsics.as_ref().map(|e| e.len()).unwrap_or(0)
+    }
+    fn blocks(&self) -> Vec<BlockNumber> {
+        self.data
+            .extrinsics
+            .iter()
+            .flatten()
+            .map(|e| e.block_num)
+            .collect()
+    }
+    fn retain_blocks(&mut self, bounds: &BlockBounds) {
+        if let Some(extrinsics) = &mut self.data.extrinsics {
+            extrinsics.retain(|e| bounds.contains(e.block_num));
+        }
+    }
+}
+
+#[async_trait]
+impl DataInfo for Response<AccountPage> {
+    fn is_empty(&self) -> bool {
+        self.data.account.is_none()
+    }
+    fn len(&self) -> usize {
+        self.data.account.iter().count()
+    }
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ScrapingModule {
+    // Same spelling as the report module, e.g. for `--module` on the command
+    // line.
+    #[serde(alias = "transfers")]
     Transfer,
     RewardsSlashes,
     Nominations,
+    Governance,
+    Judgements,
+    Balance,
+    Identity,
+    Bonds,
+    /// The extrinsics of a single call signed by the account, e.g.
+    /// `staking.bond`. Only one such module can be enabled.
+    Extrinsics {
+        call_module: String,
+        call_name: String,
+    },
 }
 
-// TODO: lifetime annotation required?
-pub struct ScrapingService<'a> {
-    db: Database,
-    api: Arc<ChainApi>,
-    contexts: Arc<RwLock<Vec<Context>>>,
-    running: HashSet<&'a ScrapingModule>,
+/// Polling settings of a single scraping module. Unspecified fields fall back
+/// to the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModuleSettings {
+    /// Seconds to pause after all accounts have been processed.
+    pub loop_interval: u64,
+    /// Seconds to pause after a failed cycle.
+    pub failed_task_sleep: u64,
+    /// Entries requested per page. Above `chain_api::MAX_ROW`, it must be a
+    /// multiple of it, since each page is then requested in parts.
+    pub row_amount: usize,
+    /// Number of contexts processed at the same time. All of them share the
+    /// rate limit of the chain API.
+    pub concurrency: usize,
 }
 
-impl<'a> ScrapingService<'a> {
-    pub fn new(db: Database) -> Self {
-        ScrapingService {
-            db: db,
-            api: Arc::new(ChainApi::new()),
-            contexts: Arc::new(RwLock::new(vec![])),
-            running: HashSet::new(),
+impl ModuleSettings {
+    pub fn validate(&self, module: &ScrapingModule) -> Result<()> {
+        if self.row_amount == 0 {
+            return Err(anyhow!(
+                "row amount of module {:?} must not be zero",
+                module
+            ));
         }
-    }
-    pub async fn add_contexts(&mut self, mut contexts: Vec<Context>) {
-        self.contexts.write().await.append(&mut contexts);
-    }
-    // TODO: Get rid fo this, use `run_fetcher` directly.
-    pub async fn run(&mut self, module: &'a ScrapingModule) -> Result<()> {
-        if self.running.contains(module) {
+        if self.row_amount > MAX_ROW && self.row_amount % MAX_ROW != 0 {
             return Err(anyhow!(
-                "configuration contains the same module multiple times"
+                "row amount of module {:?} above {} must be a multiple of it",
+                module,
+                MAX_ROW
             ));
         }
-
-        self.running.insert(module);
-
-        match module {
-            ScrapingModule::Transfer => self.run_fetcher::<TransferFetcher>().await,
-            ScrapingModule::RewardsSlashes => self.run_fetcher::<RewardsSlashesFetcher>().await,
-            ScrapingModule::Nominations => self.run_fetcher::<NominationsFetcher>().await,
+        if self.concurrency == 0 {
+            return Err(anyhow!(
+                "concurrency of module {:?} must not be zero",
+                module
+            ));
         }
 
         Ok(())
     }
-    async fn run_fetcher<T>(&self)
-    where
-        T: 'static + Send + Sync + FetchChainData,
-    {
-        async fn local<T>(fetcher: &T, contexts: &Arc<RwLock<Vec<Context>>>) -> Result<()>
-        where
-            T: 'static + Send + Sync + FetchChainData,
-        {
-            let mut page: usize = 1;
-
-            loop {
-                // This `read()` can result in a quite long-running lock.
-                // However, it is not expected that `Self::add_contexts` will be
-                // called after a fetcher is running, since those are loaded on
-                // application startup.
-                for context in contexts.read().await.iter() {
-                    loop {
-                        let resp = fetcher.fetch_data(context, ROW_AMOUNT, page).await?;
-
-                        // No entires were found, continue with next account.
-                        if resp.is_empty() {
-                            debug!(
-                                "{}: No new entries were found for {:?}, moving on...",
-                                T::name(),
-                                context
-                            );
-                            break;
-                        }
-
-                        // The cache tries to filter all unprocessed extrinsics,
-                        // but the cache is not persisted and is wiped on
-                        // application shutdown. The database method will return
-                        // how many extrinsics have been *newly* inserted into
-                        // the database. If it's 0, then no new extrinsics were
-                        // detected. Continue with the next account.
-                        let newly_inserted = fetcher.store_data(context, &resp).await?;
-                        if newly_inserted == 0 {
-                            debug!(
-                                "{}: No new entries were found for {:?}, moving on...",
-                                T::name(),
-                                context
-                            );
-                            break;
-                        }
+}
 
-                        info!(
-                            "{}: {} new entries found for {:?}",
-                            T::name(),
-                            newly_inserted,
-                            context
-                        );
+impl Default for ModuleSettings {
+    fn default() -> Self {
+        ModuleSettings {
+            loop_interval: LOOP_INTERVAL,
+            failed_task_sleep: FAILED_TASK_SLEEP,
+            row_amount: ROW_AMOUNT,
+            concurrency: CONCURRENCY,
+        }
+    }
+}
 
-                        // If new extrinsics were all on one page, continue with
-                        // the next account. Otherwise, fetch the next page.
-                        if newly_inserted < ROW_AMOUNT {
-                            debug!(
-                                "{}: All new entries have been fetched for {:?}, \
-                            continuing with the next accounts.",
-                                T::name(),
-                                context
-                            );
-                            break;
-                        }
+/// Reduces the polling frequency of contexts which had no new entries for
+/// `after` consecutive cycles. The interval doubles with each further idle
+/// cycle, up to `max_factor` times the loop interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CooldownConfig {
+    pub after: usize,
+    pub max_factor: u64,
+}
 
-                        page += 1;
-                    }
+#[derive(Debug, Default)]
+struct ContextCooldown {
+    idle_cycles: usize,
+    skip: u64,
+}
 
-                    // Reset to page 1.
-                    page = 1;
-                }
+#[derive(Debug, Default)]
+struct Cooldown {
+    config: Option<CooldownConfig>,
+    state: HashMap<Context, ContextCooldown>,
+}
 
-                // Once all accounts have been processed, pause so other active
-                // fetchers are not blocked (by the time guard) from executing
-                // requests.
-                sleep(Duration::from_secs(LOOP_INTERVAL)).await;
+impl Cooldown {
+    fn new(config: Option<CooldownConfig>) -> Self {
+        Cooldown {
+            config: config,
+            state: HashMap::new(),
+        }
+    }
+    /// Whether the context should be polled in the current cycle.
+    fn should_poll(&mut self, context: &Context) -> bool {
+        match self.state.get_mut(context) {
+            Some(state) if state.skip > 0 => {
+                state.skip -= 1;
+                false
             }
+            _ => true,
+        }
+    }
+    /// Records the outcome of a poll. New entries reset the cooldown
+    /// immediately.
+    fn record(&mut self, context: &Context, found_new: bool) {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return,
+        };
+
+        if found_new {
+            self.state.remove(context);
+            return;
         }
 
-        let fetcher = T::new(self.db.clone(), Arc::clone(&self.api));
-        let contexts = Arc::clone(&self.contexts);
-        let mut last_err = Timestamp::now();
+        let state = self.state.entry(context.clone()).or_default();
+        state.idle_cycles += 1;
+        if state.idle_cycles >= config.after {
+            state.skip = Self::factor(config, state.idle_cycles) - 1;
+        }
+    }
+    fn factor(config: &CooldownConfig, idle_cycles: usize) -> u64 {
+        let exp = (idle_cycles - config.after + 1).min(63) as u32;
+        2u64.saturating_pow(exp).min(config.max_factor.max(1))
+    }
+}
 
-        tokio::spawn(async move {
-            info!("{}: Running event loop...", T::name());
-            loop {
-                if let Err(err) = local(&fetcher, &contexts).await {
-                    // Only print errors when two or more occur within one
-                    // minute. Sometimes the Subscan API just returns an empty
-                    // value.
-                    if Timestamp::now().as_secs() - last_err.as_secs() < MAX_ERR_DIFF {
-                        error!(
-                            "Failed task while running fetcher '{}': {:?}",
-                            T::name(),
-                            err
-                        );
-                    } else {
-                        debug!(
-                            "(Acceptable) Failed task while running fetcher '{}'",
-                            T::name(),
-                        );
-                    }
+/// What to do when Subscan reports that a monitored account does not exist,
+/// e.g. because of a typo in the address.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotFoundPolicy {
+    /// Log a warning once and keep polling.
+    Warn,
+    /// Stop polling the account after `after` consecutive not-found
+    /// responses.
+    Disable { after: usize },
+}
 
-                    last_err = Timestamp::now();
-                }
+impl Default for NotFoundPolicy {
+    fn default() -> Self {
+        NotFoundPolicy::Warn
+    }
+}
 
-                sleep(Duration::from_secs(FAILED_TASK_SLEEP)).await;
+#[derive(Debug, Default)]
+struct NotFoundTracker {
+    policy: NotFoundPolicy,
+    counts: HashMap<Context, usize>,
+    disabled: HashSet<Context>,
+}
+
+impl NotFoundTracker {
+    fn new(policy: NotFoundPolicy) -> Self {
+        NotFoundTracker {
+            policy: policy,
+            counts: HashMap::new(),
+            disabled: HashSet::new(),
+        }
+    }
+    fn is_disabled(&self, context: &Context) -> bool {
+        self.disabled.contains(context)
+    }
+    /// Records a not-found response and applies the policy.
+    fn record(&mut self, fetcher: &str, context: &Context, stats: &ScrapingStats) {
+        stats.not_found.fetch_add(1, Ordering::Relaxed);
+        increment_counter!("monitor_accounts_not_found_total", "fetcher" => fetcher.to_string());
+
+        let count = self.counts.entry(context.clone()).or_insert(0);
+        *count += 1;
+
+        if *count == 1 {
+            warn!(
+                "{}: Account {:?} was not found on Subscan, please check the address",
+                fetcher, context
+            );
+        }
+
+        if let NotFoundPolicy::Disable { after } = self.policy {
+            if *count >= after && self.disabled.insert(context.clone()) {
+                warn!(
+                    "{}: Disabling {:?} after {} not-found responses",
+                    fetcher, context, count
+                );
+                stats.disabled.fetch_add(1, Ordering::Relaxed);
+                gauge!(
+                    "monitor_disabled_contexts",
+                    self.disabled.len() as f64,
+                    "fetcher" => fetcher.to_string()
+                );
             }
-        });
+        }
+    }
+    fn reset(&mut self, context: &Context) {
+        self.counts.remove(context);
     }
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ReportModule {
-    Transfers,
-    RewardsSlashes,
-    Nominations,
+/// Skips contexts which failed in `after` consecutive cycles for the next
+/// `skip_cycles` cycles, e.g. because Subscan rejects the address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkipFailingConfig {
+    pub after: usize,
+    pub skip_cycles: u64,
 }
 
-pub struct ReportGenerator {
-    db: DatabaseReader,
+/// Per-network thresholds, in plancks, above which new transfers are
+/// notified about.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferThresholds {
+    thresholds: HashMap<Network, Balance>,
+}
+
+impl TransferThresholds {
+    pub fn new(thresholds: HashMap<Network, Balance>) -> Self {
+        TransferThresholds {
+            thresholds: thresholds,
+        }
+    }
+    /// Returns the amount of the transfer if it exceeds the threshold of the
+    /// network. Networks without a threshold are never exceeded.
+    pub fn exceeded(&self, network: Network, transfer: &Transfer) -> Result<Option<Balance>> {
+        let threshold = match self.thresholds.get(&network) {
+            Some(threshold) => *threshold,
+            None => return Ok(None),
+        };
+
+        // Subscan reports transfer amounts in tokens.
+        let amount = Balance::parse_tokens(&transfer.amount, network.decimals())?;
+        if amount > threshold {
+            Ok(Some(amount))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ContextFailures {
+    consecutive: usize,
+    skip: u64,
+}
+
+/// Tracks the consecutive failures of each context.
+#[derive(Debug, Default)]
+struct FailureTracker {
+    config: Option<SkipFailingConfig>,
+    state: HashMap<Context, ContextFailures>,
+}
+
+impl FailureTracker {
+    fn new(config: Option<SkipFailingConfig>) -> Self {
+        FailureTracker {
+            config: config,
+            state: HashMap::new(),
+        }
+    }
+    /// Whether the context should be polled in the current cycle.
+    fn should_poll(&mut self, context: &Context) -> bool {
+        match self.state.get_mut(context) {
+            Some(state) if state.skip > 0 => {
+                state.skip -= 1;
+                false
+            }
+            _ => true,
+        }
+    }
+    /// Records a failed cycle of the context. Returns the number of
+    /// consecutive failures.
+    fn record(&mut self, fetcher: &str, context: &Context) -> usize {
+        let state = self.state.entry(context.clone()).or_default();
+        state.consecutive += 1;
+
+        if let Some(config) = &self.config {
+            if state.consecutive >= config.after && config.skip_cycles > 0 {
+                warn!(
+                    "{}: Skipping {:?} for {} cycles after {} consecutive failures",
+                    fetcher, context, config.skip_cycles, state.consecutive
+                );
+                state.skip = config.skip_cycles;
+            }
+        }
+
+        state.consecutive
+    }
+    fn reset(&mut self, context: &Context) {
+        self.state.remove(context);
+    }
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ChainApiError>() == Some(&ChainApiError::NotFound)
+}
+
+fn is_unchanged(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ChainApiError>() == Some(&ChainApiError::Unchanged)
+}
+
+/// Counters of the scraping progress, shared by all fetchers of a service.
+/// The same values are reported through the `metrics` facade, so any
+/// exporter can be installed.
+#[derive(Debug, Default)]
+pub struct ScrapingStats {
+    requests: AtomicUsize,
+    events_stored: AtomicUsize,
+    errors: AtomicUsize,
+    not_found: AtomicUsize,
+    disabled: AtomicUsize,
+    /// The last completed pass over all contexts, per running fetcher.
+    passes: std::sync::Mutex<HashMap<&'static str, Option<Timestamp>>>,
+}
+
+impl ScrapingStats {
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+    pub fn events_stored(&self) -> usize {
+        self.events_stored.load(Ordering::Relaxed)
+    }
+    /// Failed passes and contexts which still failed after the immediate
+    /// retries.
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+    /// Responses reporting an unknown account.
+    pub fn not_found(&self) -> usize {
+        self.not_found.load(Ordering::Relaxed)
+    }
+    /// Contexts disabled by the not-found policy.
+    pub fn disabled(&self) -> usize {
+        self.disabled.load(Ordering::Relaxed)
+    }
+    /// The time of the last completed pass of each running fetcher, `None`
+    /// if the fetcher has not completed a pass yet.
+    pub fn last_passes(&self) -> HashMap<&'static str, Option<Timestamp>> {
+        self.passes.lock().unwrap().clone()
+    }
+    pub fn register_fetcher(&self, name: &'static str) {
+        self.passes.lock().unwrap().entry(name).or_insert(None);
+    }
+    pub fn record_pass(&self, name: &'static str) {
+        self.passes
+            .lock()
+            .unwrap()
+            .insert(name, Some(Timestamp::now()));
+    }
+}
+
+// TODO: lifetime annotation required?
+pub struct ScrapingService<'a> {
+    db: Database,
+    api: Arc<dyn ChainBackend + Send + Sync>,
     contexts: Arc<RwLock<Vec<Context>>>,
+    running: HashSet<&'a ScrapingModule>,
+    start_delay: u64,
+    alerter: Option<Arc<OpsAlerter>>,
+    stats: Arc<ScrapingStats>,
+    cooldown: Option<CooldownConfig>,
+    skip_failing: Option<SkipFailingConfig>,
+    notifier: Option<Arc<dyn SendAlert + Send + Sync>>,
+    thresholds: Option<Arc<TransferThresholds>>,
+    immediate_retry: usize,
+    not_found: NotFoundPolicy,
+    settings: HashMap<ScrapingModule, ModuleSettings>,
+    /// Limits the modules running a pass at the same time, if set.
+    module_permits: Option<Arc<Semaphore>>,
+    /// The calls of the extrinsics module.
+    extrinsic_filter: Option<ExtrinsicFilter>,
+    once: bool,
+    dry_run: bool,
+    shutdown_tx: watch::Sender<bool>,
+    shutdown_rx: watch::Receiver<bool>,
+    /// The spawned fetcher tasks, by module name.
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
 }
 
-impl ReportGenerator {
-    pub fn new(db: DatabaseReader) -> Self {
-        ReportGenerator {
+impl<'a> ScrapingService<'a> {
+    pub fn new(db: Database) -> Self {
+        Self::with_api(db, Arc::new(ChainApi::new()))
+    }
+    /// Creates the service with a custom chain backend instead of Subscan.
+    pub fn with_api(db: Database, api: Arc<dyn ChainBackend + Send + Sync>) -> Self {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        ScrapingService {
             db: db,
-            contexts: Default::default(),
+            api: api,
+            contexts: Arc::new(RwLock::new(vec![])),
+            running: HashSet::new(),
+            start_delay: 0,
+            alerter: None,
+            stats: Default::default(),
+            cooldown: None,
+            skip_failing: None,
+            notifier: None,
+            thresholds: None,
+            immediate_retry: 0,
+            not_found: Default::default(),
+            settings: HashMap::new(),
+            module_permits: None,
+            extrinsic_filter: None,
+            once: false,
+            dry_run: false,
+            shutdown_tx: shutdown_tx,
+            shutdown_rx: shutdown_rx,
+            tasks: vec![],
+        }
+    }
+    /// Stops all fetchers once their current step (e.g. storing a page) is
+    /// completed and waits for them to finish.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_tx.send(true);
+
+        for (name, task) in self.tasks {
+            if let Err(err) = task.await {
+                error!("{}: Task failed during shutdown: {:?}", name, err);
+            }
+        }
+    }
+    /// Waits for all fetchers to finish on their own, which only happens in
+    /// the one-shot mode (see `set_run_once`). Returns an error if a fetcher
+    /// panicked.
+    pub async fn wait_finished(self) -> Result<()> {
+        let mut result = Ok(());
+        for (name, task) in self.tasks {
+            if let Err(err) = task.await {
+                error!("{}: Task failed: {:?}", name, err);
+                result = Err(anyhow!("fetcher '{}' failed: {}", name, err));
+            }
+        }
+
+        result
+    }
+    /// Returns the modules whose fetcher task is still running.
+    pub fn running_modules(&self) -> Vec<&'static str> {
+        self.tasks
+            .iter()
+            .filter(|(_, task)| !task.is_finished())
+            .map(|(name, _)| *name)
+            .collect()
+    }
+    /// Waits until one of the fetcher tasks stops, which only happens on a
+    /// panic or on shutdown, and returns the cause as an error. Never returns
+    /// if no fetchers are running.
+    pub async fn watch_tasks(&mut self) -> Result<()> {
+        if self.tasks.is_empty() {
+            futures::future::pending::<()>().await;
+        }
+
+        let (res, index, _) = select_all(self.tasks.iter_mut().map(|(_, task)| task)).await;
+        let (name, _) = self.tasks.remove(index);
+
+        match res {
+            Err(err) if err.is_panic() => {
+                increment_counter!("monitor_fetcher_panics_total", "fetcher" => name);
+                Err(anyhow!("fetcher '{}' panicked: {}", name, err))
+            }
+            Err(err) => Err(anyhow!("fetcher '{}' failed: {}", name, err)),
+            Ok(()) => Err(anyhow!("fetcher '{}' stopped unexpectedly", name)),
         }
     }
-    // TODO: make this part of `new()` and wrap it in an `Arc`.
     pub async fn add_contexts(&mut self, mut contexts: Vec<Context>) {
-        self.contexts.write().await.append(&mut contexts);
+        let mut current = self.contexts.write().await;
+        current.append(&mut contexts);
+        gauge!("monitor_contexts", current.len() as f64);
     }
-    pub async fn run(
-        &mut self,
-        module: ReportModule,
-        publisher: Arc<GoogleDrive>,
-        info: <GoogleDrive as Publisher>::Info,
-    ) {
+    /// Stops monitoring the contexts with a matching stash and network.
+    /// Returns how many contexts were removed.
+    ///
+    /// Fetchers process a snapshot of the contexts per cycle, so a cycle in
+    /// progress still finishes with the removed contexts. The removal takes
+    /// effect with the next cycle.
+    pub async fn remove_contexts(&mut self, ids: &[ContextId<'_>]) -> usize {
+        let mut contexts = self.contexts.write().await;
+        let before = contexts.len();
+        contexts.retain(|context| {
+            !ids.iter()
+                .any(|id| *id.stash == context.stash && id.network == context.network)
+        });
+        gauge!("monitor_contexts", contexts.len() as f64);
+
+        before - contexts.len()
+    }
+    /// Returns a copy of the currently monitored contexts.
+    pub async fn contexts_snapshot(&self) -> Vec<Context> {
+        self.contexts.read().await.clone()
+    }
+    /// Returns a handle to the counters updated by all running fetchers.
+    pub fn stats(&self) -> Arc<ScrapingStats> {
+        Arc::clone(&self.stats)
+    }
+    /// Sets the start delay (in seconds) between modules, so that their
+    /// polling cycles are staggered instead of hitting Subscan at once.
+    pub fn set_start_delay(&mut self, secs: u64) {
+        self.start_delay = secs;
+    }
+    /// Enables the per-context cooldown for accounts without new entries.
+    pub fn set_cooldown(&mut self, config: CooldownConfig) {
+        self.cooldown = Some(config);
+    }
+    /// Temporarily skips accounts which keep failing.
+    pub fn set_skip_failing(&mut self, config: SkipFailingConfig) {
+        self.skip_failing = Some(config);
+    }
+    /// Sets the channel for operational alerts on sustained fetcher failures.
+    pub fn set_ops_alerter(&mut self, alerter: Arc<OpsAlerter>) {
+        self.alerter = Some(alerter);
+    }
+    /// Sets how many times the contexts which failed in a cycle are retried
+    /// right away, before waiting for the next cycle.
+    pub fn set_immediate_retry(&mut self, retries: usize) {
+        self.immediate_retry = retries;
+    }
+    /// Sets what to do with accounts unknown to Subscan.
+    pub fn set_not_found_policy(&mut self, policy: NotFoundPolicy) {
+        self.not_found = policy;
+    }
+    /// Sets the channel for notifications about new account activity.
+    pub fn set_notifier(&mut self, notifier: Arc<dyn SendAlert + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+    /// Notifies about new transfers above the thresholds, using the channel
+    /// set with `set_notifier`.
+    pub fn set_transfer_thresholds(&mut self, thresholds: TransferThresholds) {
+        self.thresholds = Some(Arc::new(thresholds));
+    }
+    /// Processes all contexts a single time, after which the fetchers stop
+    /// instead of polling in a loop.
+    pub fn set_run_once(&mut self, once: bool) {
+        self.once = once;
+    }
+    /// Fetches the entries without storing them, e.g. to check that Subscan
+    /// returns data for new accounts. All fetched entries count as new, so
+    /// the fetchers page through the whole history.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+    /// Overrides the polling settings of the given module.
+    pub fn set_module_settings(&mut self, module: ScrapingModule, settings: ModuleSettings) {
+        self.settings.insert(module, settings);
+    }
+    /// Limits how many modules (at least one) process their contexts at the
+    /// same time. The others wait for a module to complete its pass before
+    /// starting their own. Unlike the rate limit of the `ChainApi`, which
+    /// spaces out the individual requests, this limits the modules competing
+    /// for it, so the requests of a pass are not interleaved with the ones of
+    /// all other modules. Must be set before running the modules.
+    pub fn set_max_active_modules(&mut self, limit: usize) {
+        self.module_permits = Some(Arc::new(Semaphore::new(limit.max(1))));
+    }
+    // TODO: Get rid fo this, use `run_fetcher` directly.
+    pub async fn run(&mut self, module: &'a ScrapingModule) -> Result<()> {
+        if self.running.contains(module) {
+            return Err(anyhow!(
+                "configuration contains the same module multiple times"
+            ));
+        }
+        // The fetcher progress is tracked per fetcher, so multiple filters
+        // would interfere.
+        if let ScrapingModule::Extrinsics {
+            call_module,
+            call_name,
+        } = module
+        {
+            if self.extrinsic_filter.is_some() {
+                return Err(anyhow!("only one extrinsics module can be enabled"));
+            }
+
+            let filter = ExtrinsicFilter {
+                call_module: call_module.clone(),
+                call_name: call_name.clone(),
+            };
+            filter.validate()?;
+            self.extrinsic_filter = Some(filter);
+        }
+
+        let settings = self.settings.get(module).cloned().unwrap_or_default();
+        settings.validate(module)?;
+
+        let delay = start_delay(self.running.len(), self.start_delay);
+        self.running.insert(module);
+
         match module {
-            ReportModule::Transfers => {
-                let generator =
-                    TransferReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts));
-                self.do_run(generator, publisher, info).await;
+            ScrapingModule::Transfer => self.run_fetcher::<TransferFetcher>(delay, settings).await,
+            ScrapingModule::RewardsSlashes => {
+                self.run_fetcher::<RewardsSlashesFetcher>(delay, settings)
+                    .await
             }
-            ReportModule::RewardsSlashes => {
-                let generator =
-                    RewardSlashReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts));
-                self.do_run(generator, publisher, info).await;
+            ScrapingModule::Nominations => {
+                self.run_fetcher::<NominationsFetcher>(delay, settings)
+                    .await
             }
-            ReportModule::Nominations => {
-                let generator =
-                    NominationReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts));
-                self.do_run(generator, publisher, info).await;
+            ScrapingModule::Governance => {
+                self.run_fetcher::<GovernanceFetcher>(delay, settings.clone())
+                    .await;
+                self.run_fetcher::<GovernanceProposalFetcher>(delay, settings)
+                    .await
+            }
+            ScrapingModule::Judgements => {
+                self.run_fetcher::<JudgementFetcher>(delay, settings).await
+            }
+            ScrapingModule::Balance => self.run_fetcher::<BalanceFetcher>(delay, settings).await,
+            ScrapingModule::Identity => self.run_fetcher::<IdentityFetcher>(delay, settings).await,
+            ScrapingModule::Bonds => self.run_fetcher::<BondsFetcher>(delay, settings).await,
+            ScrapingModule::Extrinsics { .. } => {
+                self.run_fetcher::<ExtrinsicsFetcher>(delay, settings).await
             }
         }
+
+        Ok(())
     }
-    async fn do_run<T, P>(&self, generator: T, publisher: Arc<P>, info: <P as Publisher>::Info)
+    async fn run_fetcher<T>(&mut self, start_delay: Duration, settings: ModuleSettings)
     where
-        T: 'static + Send + Sync + GenerateReport<P>,
-        P: 'static + Send + Sync + Publisher,
-        <T as GenerateReport<P>>::Data: Send + Sync,
-        <T as GenerateReport<P>>::Report: Send + Sync,
-        <P as Publisher>::Info: Send + Sync + Clone,
+        T: 'static + Send + Sync + FetchChainData,
     {
-        async fn local<T, P>(
-            generator: &T,
-            publisher: Arc<P>,
-            info: <P as Publisher>::Info,
-        ) -> Result<()>
+        /// Fetches and stores all new entries of a single context. Returns
+        /// whether new entries were found. In a dry run, nothing is written
+        /// and all fetched entries count as new.
+        async fn fetch_context<T>(
+            fetcher: &T,
+            db: &Database,
+            context: &Context,
+            stats: &ScrapingStats,
+            row_amount: usize,
+            dry_run: bool,
+        ) -> Result<bool>
         where
-            P: 'static + Send + Sync + Publisher,
-            T: 'static + Send + Sync + GenerateReport<P>,
-            <P as Publisher>::Info: Send + Sync + Clone,
+            T: 'static + Send + Sync + FetchChainData,
         {
-            let mut first_run = true;
+            // Continue where a previous run was interrupted (e.g. by a
+            // restart) while paging through older entries.
+            let cursor = if dry_run {
+                None
+            } else {
+                db.load_cursor(context, T::name()).await?
+            };
+            let resuming = cursor.is_some();
+            let mut page: usize = cursor.unwrap_or(1);
+            let mut found_new = false;
+            let mut saved_cursor = false;
+
+            // The requests of a backfill share the rate limit of the chain API
+            // with all other fetchers, so it can't starve them.
+            let backfill =
+                context.backfill && !dry_run && !db.is_backfilled(context, T::name()).await?;
+            if backfill {
+                // An unchanged first page would end the pass right away.
+                fetcher.forget_responses(context);
+                info!(
+                    "{}: Backfilling {:?} from page {}",
+                    T::name(),
+                    context,
+                    page
+                );
+            }
+
             loop {
-                if let Some(data) = generator.fetch_data().await? {
-                    for report in generator.generate(&data).await? {
-                        debug!("New report generated, uploading...");
-                        generator
-                            .publish(Arc::clone(&publisher), info.clone(), report)
-                            .await?;
-                    }
-                } else {
-                    if first_run {
-                        warn!("No data found to generate report");
-                        first_run = false;
+                let span = info_span!("page", page = page);
+
+                let page_result = async {
+                    stats.requests.fetch_add(1, Ordering::Relaxed);
+                    increment_counter!("monitor_fetch_requests_total", "fetcher" => T::name());
+                    let mut resp = match fetcher.fetch_data(context, row_amount, page).await {
+                        Ok(resp) => resp,
+                        // Same as on the previous pass, so nothing is new.
+                        Err(err) if is_unchanged(&err) => return Ok(None),
+                        Err(err) => return Err(err),
+                    };
+
+                    // No entires were found, continue with next account.
+                    if resp.is_empty() {
+                        return Ok(None);
                     }
+
+                    // Subscan returns the newest entries first, so the pages
+                    // after an entry below the lower bound are out of bounds
+                    // too. Pages above the upper bound are skipped.
+                    let bounds = &context.blocks;
+                    let blocks = resp.blocks();
+                    let reached_since = bounds
+                        .since
+                        .map(|since| blocks.iter().any(|block| *block < since))
+                        .unwrap_or(false);
+                    let before_until = bounds
+                        .until
+                        .map(|until| !blocks.is_empty() && blocks.iter().all(|b| *b > until))
+                        .unwrap_or(false);
+                    let rows = resp.len();
+                    resp.retain_blocks(bounds);
+
+                    counter!(
+                        "monitor_events_fetched_total",
+                        resp.len() as u64,
+                        "fetcher" => T::name(),
+                        "network" => context.network.as_str().to_string()
+                    );
+
+                    // The database method skips the already stored entries
+                    // and returns how many extrinsics have been *newly*
+                    // inserted. If it's 0, then no new extrinsics were
+                    // detected. Continue with the next account. Since this
+                    // state is persisted, a restart costs no extra requests.
+                    let newly_inserted = if dry_run {
+                        info!(
+                            "{}: Dry run, would store {} entries for {:?}",
+                            T::name(),
+                            resp.len(),
+                            context
+                        );
+                        resp.len()
+                    } else {
+                        fetcher
+                            .store_data(context, &resp)
+                            .instrument(info_span!("store"))
+                            .await
+                            .map_err(|err| {
+                                fetcher.forget_responses(context);
+                                err
+                            })?
+                    };
+                    tracing::debug!(stored = newly_inserted, "stored entries");
+
+                    Result::<_>::Ok(Some((newly_inserted, rows, reached_since, before_until)))
                 }
+                .instrument(span)
+                .await?;
 
-                sleep(Duration::from_secs(LOOP_INTERVAL)).await;
-            }
-        }
+                if let (true, Some((newly_inserted, rows, _, _))) = (backfill, page_result) {
+                    info!(
+                        "{}: Backfilling {:?}, page {} contained {} entries ({} new)",
+                        T::name(),
+                        context,
+                        page,
+                        rows,
+                        newly_inserted
+                    );
+                }
 
-        tokio::spawn(async move {
-            info!("{}: Running event loop...", T::name());
+                let (newly_inserted, rows, reached_since) = match page_result {
+                    // Only newer entries than wanted, continue with the older
+                    // ones.
+                    Some((_, rows, _, true)) if rows >= row_amount => {
+                        page += 1;
+                        continue;
+                    }
+                    Some((newly_inserted, rows, reached_since, _)) if newly_inserted > 0 => {
+                        (newly_inserted, rows, reached_since)
+                    }
+                    // New entries shift the pages, so the resumed pages might
+                    // only contain known entries. Only the last page ends it,
+                    // which also applies to backfills. Fetchers without pages
+                    // only have a single one.
+                    Some((_, rows, false, _))
+                        if T::paged() && (resuming || backfill) && rows >= row_amount =>
+                    {
+                        page += 1;
+                        db.save_cursor(context, T::name(), page).await?;
+                        saved_cursor = true;
+                        continue;
+                    }
+                    _ => {
+                        debug!(
+                            "{}: No new entries were found for {:?}, moving on...",
+                            T::name(),
+                            context
+                        );
+                        break;
+                    }
+                };
+
+                if !dry_run {
+                    stats
+                        .events_stored
+                        .fetch_add(newly_inserted, Ordering::Relaxed);
+                    counter!(
+                        "monitor_events_stored_total",
+                        newly_inserted as u64,
+                        "fetcher" => T::name(),
+                        "network" => context.network.as_str().to_string()
+                    );
+                }
 
-            loop {
-                if let Err(err) =
-                    local::<T, P>(&generator, Arc::clone(&publisher), info.clone()).await
-                {
-                    error!(
-                        "Failed task while running report generator '{}': {:?}",
+                found_new = true;
+                info!(
+                    "{}: {} new entries found for {:?}",
+                    T::name(),
+                    newly_inserted,
+                    context
+                );
+
+                // A page with fewer rows than requested is the last one, as is
+                // one reaching below the lower block bound or the only page of
+                // a fetcher without pages. A full page might still be followed
+                // by new entries, even if some of its own entries were already
+                // known.
+                if !T::paged() || rows < row_amount || reached_since {
+                    debug!(
+                        "{}: All new entries have been fetched for {:?}, \
+                    continuing with the next accounts.",
                         T::name(),
-                        err
+                        context
                     );
+                    break;
                 }
 
-                sleep(Duration::from_secs(FAILED_TASK_SLEEP)).await;
+                page += 1;
+                if !dry_run {
+                    db.save_cursor(context, T::name(), page).await?;
+                    saved_cursor = true;
+                }
             }
-        });
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::database::DatabaseReader;
-    use crate::publishing::GoogleDrive;
-    use crate::reporting::TransferReport;
-    use crate::tests::{db, init};
-    use crate::wait_blocking;
-    use std::sync::Arc;
-    use std::vec;
 
-    struct StdOut;
+            if resuming || saved_cursor {
+                db.clear_cursor(context, T::name()).await?;
+            }
 
-    #[async_trait]
-    impl Publisher for StdOut {
-        type Data = TransferReport;
-        type Info = ();
+            if backfill {
+                db.mark_backfilled(context, T::name()).await?;
+                info!("{}: Backfill of {:?} completed", T::name(), context);
+            }
 
-        async fn upload_data(&self, _info: Self::Info, data: Self::Data) -> Result<()> {
-            println!("REPORT {:?}", data);
-            Ok(())
+            Ok(found_new)
         }
-    }
 
-    #[tokio::test]
-    #[ignore]
-    async fn live_run_transfer_fetcher() {
-        init();
+        /// Processes the contexts with at most `concurrency` of them at the
+        /// same time. Contexts which were not started because of a shutdown
+        /// are left out of the results.
+        async fn fetch_contexts<T>(
+            fetcher: &T,
+            db: &Database,
+            contexts: Vec<Context>,
+            stats: &ScrapingStats,
+            row_amount: usize,
+            concurrency: usize,
+            dry_run: bool,
+            shutdown: &watch::Receiver<bool>,
+        ) -> Vec<(Context, Result<bool>)>
+        where
+            T: 'static + Send + Sync + FetchChainData,
+        {
+            stream::iter(contexts)
+                .map(move |context| async move {
+                    if *shutdown.borrow() {
+                        return None;
+                    }
+                    // The concurrent contexts don't send their first requests
+                    // at the same instant.
+                    if concurrency > 1 {
+                        let jitter = thread_rng().gen_range(0..CONTEXT_JITTER * 1_000);
+                        let mut shutdown = shutdown.clone();
+                        if sleep_or_shutdown(Duration::from_micros(jitter), &mut shutdown).await {
+                            return None;
+                        }
+                    }
 
-        info!("Running live test for transfer fetcher");
+                    let span = info_span!(
+                        "context",
+                        stash = context.stash.as_str(),
+                        network = context.network.as_str()
+                    );
+                    let res = fetch_context(fetcher, db, &context, stats, row_amount, dry_run)
+                        .instrument(span)
+                        .await;
+
+                    Some((context, res))
+                })
+                .buffer_unordered(concurrency.max(1))
+                .filter_map(|res| async move { res })
+                .collect()
+                .await
+        }
+
+        /// Processes all contexts once, retrying failed ones right away if
+        /// configured. Returns an error if contexts failed and none of the
+        /// others succeeded, so a fully failed cycle is not hidden.
+        async fn fetch_cycle<T>(
+            fetcher: &T,
+            db: &Database,
+            contexts: &Arc<RwLock<Vec<Context>>>,
+            stats: &ScrapingStats,
+            cooldown: &mut Cooldown,
+            not_found: &mut NotFoundTracker,
+            failures: &mut FailureTracker,
+            immediate_retry: usize,
+            settings: &ModuleSettings,
+            dry_run: bool,
+            shutdown: &watch::Receiver<bool>,
+        ) -> Result<()>
+        where
+            T: 'static + Send + Sync + FetchChainData,
+        {
+            let mut failed = vec![];
+            let mut succeeded = 0;
+
+            // Only hold the lock for the copy, so writers are not blocked for
+            // a whole cycle.
+            let snapshot = contexts.read().await.clone();
+            let mut pending = vec![];
+            for context in snapshot {
+                if not_found.is_disabled(&context) {
+                    trace!("{}: Skipping {:?} (not found)", T::name(), context);
+                    continue;
+                }
+
+                if !cooldown.should_poll(&context) {
+                    trace!("{}: Skipping {:?} (cooldown)", T::name(), context);
+                    continue;
+                }
+
+                if !failures.should_poll(&context) {
+                    trace!("{}: Skipping {:?} (failing)", T::name(), context);
+                    continue;
+                }
+
+                pending.push(context);
+            }
+
+            // A failing context must not prevent the others from being
+            // processed.
+            let results = fetch_contexts(
+                fetcher,
+                db,
+                pending,
+                stats,
+                settings.row_amount,
+                settings.concurrency,
+                dry_run,
+                shutdown,
+            )
+            .await;
+
+            for (context, res) in results {
+                match res {
+                    Ok(found_new) => {
+                        succeeded += 1;
+                        not_found.reset(&context);
+                        failures.reset(&context);
+                        cooldown.record(&context, found_new);
+                    }
+                    // Unknown accounts are handled by the policy, retrying
+                    // won't help.
+                    Err(err) if is_not_found(&err) => {
+                        not_found.record(T::name(), &context, stats);
+                    }
+                    Err(err) => failed.push((context, err)),
+                }
+            }
+
+            if *shutdown.borrow() {
+                return Ok(());
+            }
+
+            // Retry the failed contexts right away instead of waiting for the
+            // next cycle.
+            let mut retries = 0;
+            while !failed.is_empty() && retries < immediate_retry {
+                retries += 1;
+                debug!(
+                    "{}: Retrying {} failed contexts (attempt {})",
+                    T::name(),
+                    failed.len(),
+                    retries
+                );
+
+                let results = fetch_contexts(
+                    fetcher,
+                    db,
+                    failed.into_iter().map(|(context, _)| context).collect(),
+                    stats,
+                    settings.row_amount,
+                    settings.concurrency,
+                    dry_run,
+                    shutdown,
+                )
+                .await;
+
+                if *shutdown.borrow() {
+                    return Ok(());
+                }
+
+                let mut still_failed = vec![];
+                for (context, res) in results {
+                    match res {
+                        Ok(found_new) => {
+                            succeeded += 1;
+                            failures.reset(&context);
+                            cooldown.record(&context, found_new);
+                        }
+                        Err(err) => still_failed.push((context, err)),
+                    }
+                }
+
+                failed = still_failed;
+            }
+
+            // The remaining contexts are only retried in the next cycle. The
+            // cycle itself still completed, so the other contexts are not held
+            // back by the failed ones.
+            let failed_count = failed.len();
+            let mut last_err = None;
+            for (context, err) in failed {
+                let count = failures.record(T::name(), &context);
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+                increment_counter!("monitor_fetch_errors_total", "fetcher" => T::name());
+                error!(
+                    "{}: Failed to process {:?} ({} consecutive failures): {:?}",
+                    T::name(),
+                    context,
+                    count,
+                    err
+                );
+                last_err = Some(err);
+            }
+
+            match last_err {
+                Some(err) if succeeded == 0 => {
+                    Err(err.context(format!("all {} processed contexts failed", failed_count)))
+                }
+                _ => Ok(()),
+            }
+        }
+
+        async fn local<T>(
+            fetcher: &T,
+            db: &Database,
+            contexts: &Arc<RwLock<Vec<Context>>>,
+            alerter: &Option<Arc<OpsAlerter>>,
+            stats: &ScrapingStats,
+            cooldown: &mut Cooldown,
+            not_found: &mut NotFoundTracker,
+            failures: &mut FailureTracker,
+            immediate_retry: usize,
+            settings: &ModuleSettings,
+            module_permits: &Option<Arc<Semaphore>>,
+            once: bool,
+            dry_run: bool,
+            shutdown: &mut watch::Receiver<bool>,
+        ) -> Result<()>
+        where
+            T: 'static + Send + Sync + FetchChainData,
+        {
+            loop {
+                // Held for the whole pass, released before the pause.
+                let permit = match module_permits {
+                    Some(permits) => match acquire_or_shutdown(permits, shutdown).await {
+                        Some(permit) => Some(permit),
+                        None => return Ok(()),
+                    },
+                    None => None,
+                };
+
+                fetch_cycle(
+                    fetcher,
+                    db,
+                    contexts,
+                    stats,
+                    cooldown,
+                    not_found,
+                    failures,
+                    immediate_retry,
+                    settings,
+                    dry_run,
+                    shutdown,
+                )
+                .instrument(info_span!("fetch_cycle", module = T::name()))
+                .await?;
+                drop(permit);
+
+                if *shutdown.borrow() {
+                    return Ok(());
+                }
+
+                // Only reached if at least one context succeeded, or none
+                // failed.
+                stats.record_pass(T::name());
+                if let Some(alerter) = alerter {
+                    alerter.report_success(T::name()).await;
+                }
+
+                if once {
+                    return Ok(());
+                }
+
+                // Once all accounts have been processed, pause so other active
+                // fetchers get a share of the request budget (the rate limit
+                // of the `ChainApi`).
+                if sleep_or_shutdown(Duration::from_secs(settings.loop_interval), shutdown).await {
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut fetcher = T::new(self.db.clone(), Arc::clone(&self.api));
+        if let Some(notifier) = &self.notifier {
+            fetcher.set_notifier(notifier.clone());
+        }
+        if let Some(thresholds) = &self.thresholds {
+            fetcher.set_transfer_thresholds(Arc::clone(thresholds));
+        }
+        if let Some(filter) = &self.extrinsic_filter {
+            fetcher.set_extrinsic_filter(filter.clone());
+        }
+        let db = self.db.clone();
+        let contexts = Arc::clone(&self.contexts);
+        let alerter = self.alerter.clone();
+        let stats = Arc::clone(&self.stats);
+        stats.register_fetcher(T::name());
+        let mut cooldown = Cooldown::new(self.cooldown.clone());
+        let mut not_found = NotFoundTracker::new(self.not_found);
+        let mut failures = FailureTracker::new(self.skip_failing.clone());
+        let immediate_retry = self.immediate_retry;
+        let module_permits = self.module_permits.clone();
+        let once = self.once;
+        let dry_run = self.dry_run;
+        let mut shutdown = self.shutdown_rx.clone();
+        let mut last_err = Timestamp::now();
+
+        let task = tokio::spawn(async move {
+            if start_delay.as_millis() > 0 {
+                debug!(
+                    "{}: Delaying start by {}ms",
+                    T::name(),
+                    start_delay.as_millis()
+                );
+                if sleep_or_shutdown(start_delay, &mut shutdown).await {
+                    return;
+                }
+            }
+
+            info!("{}: Running event loop...", T::name());
+            loop {
+                let res = local(
+                    &fetcher,
+                    &db,
+                    &contexts,
+                    &alerter,
+                    &stats,
+                    &mut cooldown,
+                    &mut not_found,
+                    &mut failures,
+                    immediate_retry,
+                    &settings,
+                    &module_permits,
+                    once,
+                    dry_run,
+                    &mut shutdown,
+                )
+                .await;
+
+                // The failed contexts were already counted by the cycle.
+                if let Err(err) = res {
+                    // Only print errors when two or more occur within one
+                    // minute. Sometimes the Subscan API just returns an empty
+                    // value.
+                    if Timestamp::now().as_secs() - last_err.as_secs() < MAX_ERR_DIFF {
+                        error!(
+                            "Failed task while running fetcher '{}': {:?}",
+                            T::name(),
+                            err
+                        );
+                    } else {
+                        debug!(
+                            "(Acceptable) Failed task while running fetcher '{}'",
+                            T::name(),
+                        );
+                    }
+
+                    if let Some(alerter) = &alerter {
+                        alerter.report_failure(T::name(), &err).await;
+                    }
+
+                    last_err = Timestamp::now();
+
+                    // The failed contexts are not retried in the one-shot
+                    // mode, the errors are reflected by the stats.
+                    if once {
+                        break;
+                    }
+                } else {
+                    // Only returns successfully on shutdown or after the pass
+                    // in the one-shot mode.
+                    break;
+                }
+
+                let failed_sleep = Duration::from_secs(settings.failed_task_sleep);
+                if sleep_or_shutdown(failed_sleep, &mut shutdown).await {
+                    break;
+                }
+            }
+
+            info!("{}: Stopped", T::name());
+        });
+
+        self.tasks.push((T::name(), task));
+    }
+}
+
+/// Sleeps for the given duration, unless a shutdown is requested. Returns
+/// whether the shutdown was requested.
+async fn sleep_or_shutdown(duration: Duration, shutdown: &mut watch::Receiver<bool>) -> bool {
+    if *shutdown.borrow() {
+        return true;
+    }
+
+    tokio::select! {
+        _ = sleep(duration) => false,
+        // The sender is only dropped with the service.
+        _ = shutdown.changed() => true,
+    }
+}
+
+/// Waits for a permit of the semaphore. Returns `None` on shutdown.
+async fn acquire_or_shutdown<'a>(
+    permits: &'a Semaphore,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Option<SemaphorePermit<'a>> {
+    if *shutdown.borrow() {
+        return None;
+    }
+
+    tokio::select! {
+        // The semaphore is never closed.
+        permit = permits.acquire() => permit.ok(),
+        _ = shutdown.changed() => None,
+    }
+}
+
+/// Calculates the start delay of the module at the given index. Each module
+/// starts at a random point within its own `[index * delay, (index + 1) *
+/// delay)` window, so no two modules issue their first request at the same
+/// instant.
+fn start_delay(index: usize, delay: u64) -> Duration {
+    if delay == 0 {
+        return Duration::from_secs(0);
+    }
+
+    // Saturates instead of overflowing on huge configured delays.
+    let window = delay.saturating_mul(1_000);
+    let jitter = thread_rng().gen_range(0..window);
+    Duration::from_millis((index as u64).saturating_mul(window).saturating_add(jitter))
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportModule {
+    #[serde(alias = "transfer")]
+    Transfers,
+    RewardsSlashes,
+    Nominations,
+}
+
+/// When a report instance is generated. Each instance tracks its own last
+/// report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSchedule {
+    /// Seconds between two reports.
+    cadence: u64,
+    /// Only include the entries of the last `window` seconds, if set.
+    window: Option<u64>,
+    last_report: Option<Timestamp>,
+}
+
+impl ReportSchedule {
+    pub fn new(cadence: u64, window: Option<u64>) -> Self {
+        ReportSchedule {
+            cadence: cadence,
+            window: window,
+            last_report: None,
+        }
+    }
+    fn is_due(&self, now: Timestamp) -> bool {
+        match self.last_report {
+            Some(last) => now.as_secs().saturating_sub(last.as_secs()) >= self.cadence,
+            None => true,
+        }
+    }
+    fn mark(&mut self, now: Timestamp) {
+        self.last_report = Some(now);
+    }
+}
+
+impl Default for ReportSchedule {
+    fn default() -> Self {
+        Self::new(LOOP_INTERVAL, None)
+    }
+}
+
+/// Publishes the reports to each destination, with at most `concurrency`
+/// destinations in flight. Each destination receives the reports in order
+/// and a failed upload skips its remaining reports, but does not abort the
+/// other destinations. All failures are collected into a single error.
+async fn publish_reports<T, P>(
+    generator: &T,
+    destinations: &[(Arc<P>, <P as Publisher>::Info)],
+    reports: Vec<<T as GenerateReport<P>>::Report>,
+    concurrency: usize,
+) -> Result<()>
+where
+    T: GenerateReport<P>,
+    P: Publisher,
+    <T as GenerateReport<P>>::Report: Clone,
+    <P as Publisher>::Info: Clone,
+{
+    let reports = &reports;
+    let mut failures: Vec<_> = stream::iter(destinations.iter().cloned().enumerate())
+        .map(|(idx, (publisher, info))| async move {
+            for report in reports {
+                debug!("New report generated, uploading...");
+                let res = generator
+                    .publish(Arc::clone(&publisher), info.clone(), report.clone())
+                    .await;
+
+                if let Err(err) = res {
+                    return Some((idx, err));
+                }
+            }
+
+            None
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|failure| async move { failure })
+        .collect()
+        .await;
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    failures.sort_by_key(|(idx, _)| *idx);
+    for (idx, err) in &failures {
+        error!(
+            "{}: failed to publish report to destination #{}: {:?}",
+            <T as GenerateReport<P>>::name(),
+            idx + 1,
+            err
+        );
+    }
+
+    Err(anyhow!(
+        "failed to publish to {} of {} destinations: {}",
+        failures.len(),
+        destinations.len(),
+        failures
+            .iter()
+            .map(|(idx, err)| format!("#{} ({})", idx + 1, err))
+            .collect::<Vec<String>>()
+            .join(", ")
+    ))
+}
+
+/// Generates the reports and publishes them to the destinations if the
+/// schedule is due. The schedule is marked even if some destinations
+/// failed, so the others don't receive the reports twice. Returns `false`
+/// if there was no data to report on.
+async fn report_if_due<T, P>(
+    generator: &T,
+    schedule: &mut ReportSchedule,
+    destinations: &[(Arc<P>, <P as Publisher>::Info)],
+    concurrency: usize,
+    now: Timestamp,
+) -> Result<bool>
+where
+    T: GenerateReport<P>,
+    P: Publisher,
+    <T as GenerateReport<P>>::Report: Clone,
+    <P as Publisher>::Info: Clone,
+{
+    if !schedule.is_due(now) {
+        return Ok(true);
+    }
+
+    let data = match generator.fetch_data().await? {
+        Some(data) => data,
+        None => {
+            schedule.mark(now);
+            return Ok(false);
+        }
+    };
+
+    // Not marked if generating fails, nothing was published yet.
+    let reports = generator.generate(&data).await?;
+    let res = publish_reports(generator, destinations, reports, concurrency).await;
+    schedule.mark(now);
+
+    res.map(|_| true)
+}
+
+pub struct ReportGenerator {
+    db: DatabaseReader,
+    contexts: Arc<RwLock<Vec<Context>>>,
+    publish_concurrency: usize,
+}
+
+impl ReportGenerator {
+    pub fn new(db: DatabaseReader) -> Self {
+        ReportGenerator {
+            db: db,
+            contexts: Default::default(),
+            publish_concurrency: DEFAULT_PUBLISH_CONCURRENCY,
+        }
+    }
+    /// Maximum number of destinations published to at the same time.
+    pub fn set_publish_concurrency(&mut self, limit: usize) {
+        self.publish_concurrency = limit;
+    }
+    // TODO: make this part of `new()` and wrap it in an `Arc`.
+    pub async fn add_contexts(&mut self, mut contexts: Vec<Context>) {
+        self.contexts.write().await.append(&mut contexts);
+    }
+    /// Runs a report instance with its own schedule, publishing to all of the
+    /// destinations. The same module can be registered multiple times, e.g.
+    /// an hourly summary and a daily digest.
+    pub async fn run_scheduled<P>(
+        &mut self,
+        module: ReportModule,
+        schedule: ReportSchedule,
+        destinations: Vec<(Arc<P>, <P as Publisher>::Info)>,
+    ) -> Result<()>
+    where
+        P: 'static + Send + Sync + Publisher,
+        <P as Publisher>::Data:
+            Send + Sync + From<TransferReport> + From<RewardSlashReport> + From<NominationReport>,
+        <P as Publisher>::Info: Send + Sync + Clone,
+    {
+        if schedule.window.is_some() && module == ReportModule::Nominations {
+            return Err(anyhow!(
+                "rolling report windows are not supported for nominations"
+            ));
+        }
+
+        match module {
+            ReportModule::Transfers => {
+                let generator =
+                    TransferReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts))
+                        .with_window(schedule.window);
+                self.do_run(generator, schedule, destinations).await;
+            }
+            ReportModule::RewardsSlashes => {
+                let generator =
+                    RewardSlashReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts))
+                        .with_window(schedule.window);
+                self.do_run(generator, schedule, destinations).await;
+            }
+            ReportModule::Nominations => {
+                let generator =
+                    NominationReportGenerator::new(self.db.clone(), Arc::clone(&self.contexts));
+                self.do_run(generator, schedule, destinations).await;
+            }
+        }
+
+        Ok(())
+    }
+    async fn do_run<T, P>(
+        &self,
+        generator: T,
+        mut schedule: ReportSchedule,
+        destinations: Vec<(Arc<P>, <P as Publisher>::Info)>,
+    ) where
+        T: 'static + Send + Sync + GenerateReport<P>,
+        P: 'static + Send + Sync + Publisher,
+        <T as GenerateReport<P>>::Data: Send + Sync,
+        <T as GenerateReport<P>>::Report: Send + Sync + Clone,
+        <P as Publisher>::Info: Send + Sync + Clone,
+    {
+        async fn local<T, P>(
+            generator: &T,
+            schedule: &mut ReportSchedule,
+            destinations: &[(Arc<P>, <P as Publisher>::Info)],
+            concurrency: usize,
+        ) -> Result<()>
+        where
+            P: 'static + Send + Sync + Publisher,
+            T: 'static + Send + Sync + GenerateReport<P>,
+            <T as GenerateReport<P>>::Report: Send + Sync + Clone,
+            <P as Publisher>::Info: Send + Sync + Clone,
+        {
+            let mut first_run = true;
+            loop {
+                let now = Timestamp::now();
+                let found =
+                    report_if_due(generator, schedule, destinations, concurrency, now).await?;
+
+                if !found && first_run {
+                    warn!("No data found to generate report");
+                    first_run = false;
+                }
+
+                sleep(Duration::from_secs(schedule.cadence.min(LOOP_INTERVAL))).await;
+            }
+        }
+
+        let concurrency = self.publish_concurrency;
+        tokio::spawn(async move {
+            info!("{}: Running event loop...", T::name());
+
+            loop {
+                if let Err(err) =
+                    local::<T, P>(&generator, &mut schedule, &destinations, concurrency).await
+                {
+                    error!(
+                        "Failed task while running report generator '{}': {:?}",
+                        T::name(),
+                        err
+                    );
+                }
+
+                sleep(Duration::from_secs(FAILED_TASK_SLEEP)).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_api::{
+        Account, Extrinsic, GovernanceVote, Judgement, Nomination, StashAccountDisplay,
+    };
+    use crate::database::DatabaseReader;
+    use crate::health::{self, HealthCheck};
+    use crate::publishing::GoogleDrive;
+    use crate::tests::{db, init, metrics};
+    use crate::wait_blocking;
+    use hyper::{Body, Request, StatusCode};
+    use metrics_util::DebugValue;
+    use std::sync::Arc;
+    use std::vec;
+    use tokio::time::Instant;
+
+    struct StdOut;
+
+    #[async_trait]
+    impl Publisher for StdOut {
+        type Data = TransferReport;
+        type Info = ();
+
+        async fn upload_data(&self, _info: Self::Info, data: Self::Data) -> Result<()> {
+            println!("REPORT {:?}", data);
+            Ok(())
+        }
+    }
+
+    /// Returns the transfers of the given blocks, newest first.
+    fn transfers(blocks: std::ops::Range<u64>) -> Vec<Transfer> {
+        blocks
+            .rev()
+            .map(|block| Transfer {
+                block_num: BlockNumber::from(block),
+                extrinsic_index: format!("{}-1", block).into(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Decides whether the request of a context and page fails.
+    type Failure = Box<dyn Fn(&Context, usize) -> Option<anyhow::Error> + Send + Sync>;
+
+    /// Serves the same transfers (newest first) to every context in pages,
+    /// like Subscan, and records the requests. There are never any rewards
+    /// or slashes.
+    #[derive(Default)]
+    struct TestBackend {
+        transfers: std::sync::Mutex<Vec<Transfer>>,
+        /// The number of validators every context nominates.
+        nominations: usize,
+        /// The requested pages per stash, with the time of the request.
+        requests: std::sync::Mutex<Vec<(String, usize, Instant)>>,
+        failure: Option<Failure>,
+        delay: Duration,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl TestBackend {
+        fn new(blocks: std::ops::Range<u64>) -> Self {
+            let backend = TestBackend::default();
+            backend.add(blocks);
+            backend
+        }
+        /// Fails the requests for which `failure` returns an error.
+        fn with_failure<F>(mut self, failure: F) -> Self
+        where
+            F: 'static + Send + Sync + Fn(&Context, usize) -> Option<anyhow::Error>,
+        {
+            self.failure = Some(Box::new(failure));
+            self
+        }
+        /// Delays every response.
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+        /// Lets every context nominate `count` validators.
+        fn with_nominations(mut self, count: usize) -> Self {
+            self.nominations = count;
+            self
+        }
+        /// Adds new transfers on top.
+        fn add(&self, blocks: std::ops::Range<u64>) {
+            self.transfers
+                .lock()
+                .unwrap()
+                .splice(0..0, transfers(blocks));
+        }
+        /// The pages requested for the stash, in order.
+        fn pages(&self, stash: &str) -> Vec<usize> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(s, _, _)| s == stash)
+                .map(|(_, page, _)| *page)
+                .collect()
+        }
+        /// The times of all requests, in order.
+        fn times(&self) -> Vec<Instant> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, _, time)| *time)
+                .collect()
+        }
+        /// Forgets the recorded requests.
+        fn reset(&self) {
+            self.requests.lock().unwrap().clear();
+        }
+        fn max_in_flight(&self) -> usize {
+            self.max_in_flight.load(Ordering::SeqCst)
+        }
+        async fn request(&self, context: &Context, page: usize) -> Result<()> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((context.stash.clone(), page, Instant::now()));
+
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+            if self.delay.as_millis() > 0 {
+                sleep(self.delay).await;
+            }
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            match self.failure.as_ref().and_then(|f| f(context, page)) {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ChainBackend for TestBackend {
+        async fn request_transfer(
+            &self,
+            context: &Context,
+            row: usize,
+            page: usize,
+        ) -> Result<Response<TransfersPage>> {
+            self.request(context, page).await?;
+
+            // Pages start at 1, as requested by `fetch_context`.
+            let transfers: Vec<Transfer> = self
+                .transfers
+                .lock()
+                .unwrap()
+                .iter()
+                .skip((page - 1) * row)
+                .take(row)
+                .cloned()
+                .collect();
+
+            let mut resp: Response<TransfersPage> = Default::default();
+            if !transfers.is_empty() {
+                resp.data.transfers = Some(transfers);
+            }
+            Ok(resp)
+        }
+        async fn request_reward_slash(
+            &self,
+            context: &Context,
+            _row: usize,
+            page: usize,
+        ) -> Result<Response<RewardsSlashesPage>> {
+            self.request(context, page).await?;
+            Ok(Default::default())
+        }
+        async fn request_nominations(
+            &self,
+            context: &Context,
+        ) -> Result<Response<NominationsPage>> {
+            // Not paged, recorded as the first page.
+            self.request(context, 1).await?;
+
+            let nominations = (0..self.nominations)
+                .map(|index| Nomination {
+                    stash_account_display: StashAccountDisplay {
+                        address: format!("validator-{}", index),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .collect();
+
+            let mut resp: Response<NominationsPage> = Default::default();
+            resp.data.list = Some(nominations);
+            Ok(resp)
+        }
+    }
+
+    /// Runs a single pass of the `TransferFetcher` and waits for it to
+    /// finish.
+    async fn run_pass(
+        mut service: ScrapingService<'_>,
+        settings: ModuleSettings,
+    ) -> Arc<ScrapingStats> {
+        service.set_run_once(true);
+        let stats = service.stats();
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), settings)
+            .await;
+
+        finish(service).await;
+        stats
+    }
+
+    /// Waits for the fetchers of a service in the one-shot mode to finish.
+    async fn finish(service: ScrapingService<'_>) {
+        tokio::time::timeout(Duration::from_secs(60), service.wait_finished())
+            .await
+            .expect("fetchers did not stop")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn judgement_downgrade_notify() {
+        let db = db().await;
+        let notifier = Arc::new(CollectAlerts::default());
+
+        let mut fetcher = JudgementFetcher::new(db, Arc::new(ChainApi::new()));
+        fetcher.set_notifier(notifier.clone());
+
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let response = |judgements: Vec<(u32, &str)>| {
+            let mut resp: Response<AccountPage> = Default::default();
+            resp.data.account = Some(Account {
+                address: alice.stash.clone(),
+                judgements: Some(
+                    judgements
+                        .into_iter()
+                        .map(|(index, judgement)| Judgement {
+                            index: index,
+                            judgement: judgement.to_string(),
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            });
+            resp
+        };
+
+        // Initial judgements of two registrars.
+        let resp = response(vec![(0, "Reasonable"), (1, "KnownGood")]);
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 2);
+        assert!(notifier.0.lock().unwrap().is_empty());
+
+        // Unchanged.
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 0);
+
+        // An upgrade is recorded, but not alerted.
+        let resp = response(vec![(0, "KnownGood"), (1, "KnownGood")]);
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 1);
+        assert!(notifier.0.lock().unwrap().is_empty());
+
+        // A downgrade and a revocation.
+        let resp = response(vec![(0, "LowQuality")]);
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 2);
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec![
+                "Judgement of registrar #0 for Alice (polkadot) changed from KnownGood to LowQuality",
+                "Judgement of registrar #1 for Alice (polkadot) changed from KnownGood to none (revoked)",
+            ]
+        );
+    }
+
+    /// Records the name of each created span with the name of its parent.
+    #[derive(Clone, Default)]
+    struct SpanCapture(Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for SpanCapture
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn new_span(
+            &self,
+            _attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|parent| parent.name().to_string());
+            self.0
+                .lock()
+                .unwrap()
+                .push((span.name().to_string(), parent));
+        }
+    }
+
+    #[tokio::test]
+    async fn tracing_span_hierarchy() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = SpanCapture::default();
+        // The test runtime is single-threaded, so the spawned fetcher uses
+        // this subscriber as well.
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let db = db().await;
+        let mut service = ScrapingService::with_api(db, Arc::new(TestBackend::new(0..5)));
+        service.add_contexts(vec![Context::alice()]).await;
+        run_pass(service, Default::default()).await;
+
+        let spans = capture.0.lock().unwrap().clone();
+        let expected = [
+            ("fetch_cycle", None),
+            ("context", Some("fetch_cycle")),
+            ("page", Some("context")),
+            ("store", Some("page")),
+        ];
+
+        for (name, parent) in &expected {
+            assert!(
+                spans.contains(&(name.to_string(), parent.map(|p| p.to_string()))),
+                "missing span '{}' in {:?}",
+                name,
+                spans
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn stats_counters() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..5);
+        let mut service = ScrapingService::with_api(db.clone(), Arc::new(backend));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+
+        let stats = run_pass(service, Default::default()).await;
+        assert_eq!(stats.requests(), 2);
+        assert_eq!(stats.events_stored(), 10);
+        assert_eq!(stats.errors(), 0);
+
+        let backend = TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down")));
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service.add_contexts(vec![Context::alice()]).await;
+
+        let stats = run_pass(service, Default::default()).await;
+        assert_eq!(stats.requests(), 1);
+        assert_eq!(stats.events_stored(), 0);
+        assert_eq!(stats.errors(), 1);
+    }
+
+    #[derive(Default)]
+    struct CollectAlerts(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl SendAlert for CollectAlerts {
+        async fn send_alert(&self, message: &str) -> Result<()> {
+            self.0.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn governance_votes_notify() {
+        let db = db().await;
+        let notifier = Arc::new(CollectAlerts::default());
+
+        let mut fetcher = GovernanceFetcher::new(db, Arc::new(ChainApi::new()));
+        fetcher.set_notifier(notifier.clone());
+
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let mut resp: Response<GovernanceVotesPage> = Default::default();
+        resp.data.list = Some(vec![GovernanceVote {
+            referendum_index: 42,
+            extrinsic_index: "100-1".to_string().into(),
+            status: "Aye".to_string(),
+            amount: "10".to_string(),
+            conviction: "2".to_string(),
+            voting_time: Timestamp::from(1_704_067_200),
+        }]);
+
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 1);
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec!["Alice (polkadot) voted Aye on referendum #42 with 10 DOT (conviction: 2)"]
+        );
+
+        // Already known votes are neither stored nor notified again.
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 0);
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn governance_proposals_notify() {
+        let db = db().await;
+        let notifier = Arc::new(CollectAlerts::default());
+
+        let mut fetcher = GovernanceProposalFetcher::new(db, Arc::new(ChainApi::new()));
+        fetcher.set_notifier(notifier.clone());
+
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let mut resp: Response<ExtrinsicsPage> = Default::default();
+        resp.data.extrinsics = Some(vec![Extrinsic {
+            extrinsic_index: "100-2".to_string().into(),
+            block_num: BlockNumber::from(100),
+            block_timestamp: Timestamp::from(1_704_067_200),
+            call_module: "democracy".to_string(),
+            call_module_function: "propose".to_string(),
+            success: true,
+            ..Default::default()
+        }]);
+
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 1);
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec!["Alice (polkadot) submitted a governance proposal in extrinsic 100-2 (block 100)"]
+        );
+
+        // Already known proposals are neither stored nor notified again.
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 0);
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn transfer_thresholds() {
+        let thresholds = TransferThresholds::new(
+            vec![(Network::Polkadot, Balance::from(100_000_000_000))]
+                .into_iter()
+                .collect(),
+        );
+
+        let transfer = |amount: &str| Transfer {
+            amount: amount.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            thresholds
+                .exceeded(Network::Polkadot, &transfer("10.5"))
+                .unwrap(),
+            Some(Balance::from(105_000_000_000))
+        );
+        // The threshold itself is not exceeded.
+        assert_eq!(
+            thresholds
+                .exceeded(Network::Polkadot, &transfer("10"))
+                .unwrap(),
+            None
+        );
+        // No threshold for the network.
+        assert_eq!(
+            thresholds
+                .exceeded(Network::Kusama, &transfer("1000"))
+                .unwrap(),
+            None
+        );
+        assert!(thresholds
+            .exceeded(Network::Polkadot, &transfer("1,5"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn large_transfers_notify() {
+        let db = db().await;
+        let notifier = Arc::new(CollectAlerts::default());
+
+        let mut fetcher = TransferFetcher::new(db, Arc::new(ChainApi::new()));
+        fetcher.set_notifier(notifier.clone());
+        fetcher.set_transfer_thresholds(Arc::new(TransferThresholds::new(
+            vec![(Network::Polkadot, Balance::from(100_000_000_000))]
+                .into_iter()
+                .collect(),
+        )));
+
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(
+            ["5", "12.5"]
+                .iter()
+                .enumerate()
+                .map(|(idx, amount)| Transfer {
+                    amount: amount.to_string(),
+                    extrinsic_index: format!("100-{}", idx).into(),
+                    from: alice.stash.clone(),
+                    to: "bob".to_string(),
+                    hash: format!("0x{:064x}", idx),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 2);
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec![format!(
+                "Large transfer of 12.5 DOT for Alice (polkadot, {stash}): {stash} -> bob, \
+                 extrinsic 0x{:064x}",
+                1,
+                stash = alice.stash
+            )]
+        );
+
+        // Already known transfers are not notified again.
+        assert_eq!(fetcher.store_data(&alice, &resp).await.unwrap(), 0);
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn service_with_custom_backend() {
+        let db = db().await;
+        let reader = db.reader();
+
+        let mut service = ScrapingService::with_api(db, Arc::new(TestBackend::new(100..103)));
+        service.add_contexts(vec![Context::alice()]).await;
+
+        let stats = run_pass(service, Default::default()).await;
+
+        let stored = reader
+            .fetch_transfers(
+                &[Context::alice()],
+                Timestamp::from(0),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stats.events_stored(), 3);
+    }
+
+    #[tokio::test]
+    async fn immediate_retry_failed_contexts() {
+        let db = db().await;
+
+        // Fails the first request of every context except Alice.
+        let attempted = std::sync::Mutex::new(HashSet::new());
+        let backend = TestBackend::new(0..5).with_failure(move |context, _| {
+            let first_attempt = attempted.lock().unwrap().insert(context.stash.clone());
+            if *context != Context::alice() && first_attempt {
+                Some(anyhow!("subscan hiccup"))
+            } else {
+                None
+            }
+        });
+
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob(), Context::eve()])
+            .await;
+        service.set_immediate_retry(1);
+
+        let stats = run_pass(service, Default::default()).await;
+
+        // All contexts are stored within the first cycle.
+        assert_eq!(stats.requests(), 5);
+        assert_eq!(stats.events_stored(), 15);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    /// Always fails for Bob, returns five new entries for everybody else.
+    fn broken_bob_backend() -> TestBackend {
+        TestBackend::new(0..5).with_failure(|context, _| {
+            if *context == Context::bob() {
+                Some(anyhow!("broken account"))
+            } else {
+                None
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn failed_context_completes_cycle() {
+        let db = db().await;
+
+        let mut service = ScrapingService::with_api(db, Arc::new(broken_bob_backend()));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob(), Context::eve()])
+            .await;
+
+        let stats = run_pass(service, Default::default()).await;
+
+        // The failure is recorded, but the pass still counts as completed.
+        assert_eq!(stats.events_stored(), 10);
+        assert_eq!(stats.errors(), 1);
+        assert!(stats.last_passes()["TransferFetcher"].is_some());
+    }
+
+    #[tokio::test]
+    async fn all_contexts_failed_cycle() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down")));
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+
+        let stats = run_pass(service, Default::default()).await;
+
+        // Every context failed, so the pass does not count as completed.
+        assert_eq!(stats.events_stored(), 0);
+        assert_eq!(stats.errors(), 2);
+        assert!(stats.last_passes()["TransferFetcher"].is_none());
+    }
+
+    #[tokio::test]
+    async fn sustained_failures_send_one_alert() {
+        let db = db().await;
+        let sender = Arc::new(CollectAlerts::default());
+
+        let backend =
+            Arc::new(TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down"))));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service.add_contexts(vec![Context::alice()]).await;
+        service.set_ops_alerter(Arc::new(OpsAlerter::new(sender.clone(), 3, 3600)));
+        service.set_dry_run(true);
+
+        tokio::time::pause();
+        let settings = ModuleSettings {
+            failed_task_sleep: 1,
+            ..Default::default()
+        };
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), settings)
+            .await;
+
+        // Well above the threshold of three failed cycles.
+        while backend.times().len() < 10 {
+            sleep(Duration::from_secs(1)).await;
+        }
+        service.shutdown().await;
+
+        let alerts = sender.0.lock().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].starts_with("TransferFetcher failed 3 consecutive times"));
+    }
+
+    #[tokio::test]
+    async fn healthy_with_failing_context() {
+        let db = db().await;
+
+        let mut service = ScrapingService::with_api(db.clone(), Arc::new(broken_bob_backend()));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+
+        let check = HealthCheck::new(db, 60).with_stats(service.stats());
+        run_pass(service, Default::default()).await;
+
+        // Bob keeps failing, the fetcher itself is live.
+        let status = check.check().await;
+        assert!(status.healthy);
+        assert!(status.stale.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unhealthy_with_all_contexts_failing() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down")));
+        let mut service = ScrapingService::with_api(db.clone(), Arc::new(backend));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+
+        let check = Arc::new(HealthCheck::new(db, 60).with_stats(service.stats()));
+        run_pass(service, Default::default()).await;
+
+        // Nothing is scraped, so the fetcher never completed a pass.
+        let resp =
+            health::handle(check, Request::get("/healthz").body(Body::empty()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn resume_from_cursor() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Full pages up to page 4. Fails on page 3 for Bob, simulating an
+        // interruption.
+        let backend = Arc::new(TestBackend::new(0..4 * ROW_AMOUNT as u64).with_failure(
+            |context, page| {
+                if *context == Context::bob() && page == 3 {
+                    Some(anyhow!("interrupted"))
+                } else {
+                    None
+                }
+            },
+        ));
+
+        // A previous run was interrupted on page 3 for Alice.
+        db.save_cursor(&alice, "TransferFetcher", 3).await.unwrap();
+
+        let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+        service.add_contexts(vec![alice.clone(), bob.clone()]).await;
+        run_pass(service, Default::default()).await;
+
+        // Resumed at the cursor, until the first empty page.
+        assert_eq!(backend.pages(&alice.stash), vec![3, 4, 5]);
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            None
+        );
+
+        // The progress of the interrupted paging is kept.
+        assert_eq!(backend.pages(&bob.stash), vec![1, 2, 3]);
+        assert_eq!(
+            db.load_cursor(&bob, "TransferFetcher").await.unwrap(),
+            Some(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn block_bounds() {
+        let db = db().await;
+        let reader = db.reader();
+        let mut alice = Context::alice();
+        alice.blocks = BlockBounds {
+            since: Some(BlockNumber::from(975)),
+            until: Some(BlockNumber::from(985)),
+        };
+        let bob = Context::bob();
+
+        // Five full pages, from block 1000 down to 951.
+        let backend = Arc::new(TestBackend::new(951..1_001));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service.add_contexts(vec![alice.clone(), bob.clone()]).await;
+        run_pass(service, Default::default()).await;
+
+        let blocks = |context: &Context| {
+            let reader = &reader;
+            let context = context.clone();
+            async move {
+                let mut blocks: Vec<BlockNumber> = reader
+                    .fetch_transfers(
+                        &[context],
+                        Timestamp::from(0),
+                        Timestamp::from(i64::MAX as u64),
+                    )
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|transfer| transfer.data.block_num)
+                    .collect();
+
+                blocks.sort();
+                blocks
+            }
+        };
+
+        // The first page is newer than the upper bound, the third one
+        // reaches below the lower bound.
+        assert_eq!(backend.pages(&alice.stash), vec![1, 2, 3]);
+        assert_eq!(
+            blocks(&alice).await,
+            (975..=985).map(BlockNumber::from).collect::<Vec<_>>()
+        );
+
+        // Unbounded, until the first empty page.
+        assert_eq!(backend.pages(&bob.stash), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(blocks(&bob).await.len(), 50);
+    }
+
+    #[tokio::test]
+    async fn backfill() {
+        let db = db().await;
+        let mut alice = Context::alice();
+        let bob = Context::bob();
+
+        // Full pages up to page 4, and more nominated validators than rows
+        // per page.
+        let backend =
+            Arc::new(TestBackend::new(0..4 * ROW_AMOUNT as u64).with_nominations(ROW_AMOUNT + 6));
+        let run = |contexts: Vec<Context>| {
+            let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+            async move {
+                service.add_contexts(contexts).await;
+                run_pass(service, Default::default()).await;
+            }
+        };
+        let run_nominations = |context: Context| {
+            let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+            async move {
+                service.add_contexts(vec![context]).await;
+                service.set_run_once(true);
+                service
+                    .run_fetcher::<NominationsFetcher>(Duration::from_secs(0), Default::default())
+                    .await;
+                finish(service).await;
+            }
+        };
+
+        // All entries are already known.
+        run(vec![alice.clone(), bob.clone()]).await;
+        run_nominations(alice.clone()).await;
+        backend.reset();
+
+        alice.backfill = true;
+        run(vec![alice.clone(), bob.clone()]).await;
+
+        // Not stopped by the known entries, until the first empty page.
+        assert_eq!(backend.pages(&alice.stash), vec![1, 2, 3, 4, 5]);
+        assert!(db.is_backfilled(&alice, "TransferFetcher").await.unwrap());
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            None
+        );
+        assert_eq!(backend.pages(&bob.stash), vec![1]);
+        assert!(!db.is_backfilled(&bob, "TransferFetcher").await.unwrap());
+
+        // The nominations have no pages, so their backfill is a single
+        // request.
+        backend.reset();
+        run_nominations(alice.clone()).await;
+
+        assert_eq!(backend.pages(&alice.stash), vec![1]);
+        assert!(db
+            .is_backfilled(&alice, "NominationsFetcher")
+            .await
+            .unwrap());
+
+        // Back to fetching only new entries.
+        backend.reset();
+        run(vec![alice.clone()]).await;
+
+        assert_eq!(backend.pages(&alice.stash), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn add_contexts_during_fetch() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..0).with_delay(Duration::from_secs(5));
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service.add_contexts(vec![Context::alice()]).await;
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        // Wait for the fetch pass to be in progress.
+        sleep(Duration::from_millis(100)).await;
+
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            service.add_contexts(vec![Context::bob()]),
+        )
+        .await
+        .expect("adding contexts blocked by running fetcher");
+
+        assert_eq!(
+            service.contexts_snapshot().await,
+            vec![Context::alice(), Context::bob()]
+        );
+    }
+
+    #[tokio::test]
+    async fn graceful_shutdown() {
+        let db = db().await;
+
+        // A single entry per context, which takes a while to fetch.
+        let backend = Arc::new(TestBackend::new(0..1).with_delay(Duration::from_millis(500)));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        let stats = service.stats();
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        // Wait for the first request to be in progress.
+        sleep(Duration::from_millis(100)).await;
+
+        tokio::time::timeout(Duration::from_secs(5), service.shutdown())
+            .await
+            .expect("shutdown did not complete");
+
+        // The context in progress was completed, the next one was skipped.
+        assert_eq!(stats.events_stored(), 1);
+        assert!(backend.pages(&Context::bob().stash).is_empty());
+    }
+
+    #[tokio::test]
+    async fn concurrent_contexts() {
+        let db = db().await;
+        let settings = ModuleSettings {
+            concurrency: 2,
+            ..Default::default()
+        };
+
+        // A single new entry per context, after the jitter of all of them.
+        let delay = Duration::from_millis(2 * CONTEXT_JITTER);
+        let backend = Arc::new(TestBackend::new(0..1).with_delay(delay));
+        let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+        service
+            .add_contexts(vec![Context::alice(), Context::bob(), Context::eve()])
+            .await;
+
+        let stats = run_pass(service, settings.clone()).await;
+
+        assert_eq!(backend.max_in_flight(), 2);
+        assert_eq!(backend.times().len(), 3);
+        // Counted per context.
+        assert_eq!(stats.events_stored(), 3);
+
+        let backend = Arc::new(TestBackend::new(0..1).with_delay(delay));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        // Nothing is stored, so the paused clock never races the database.
+        service.set_dry_run(true);
+
+        tokio::time::pause();
+        let start = Instant::now();
+        run_pass(service, settings).await;
+
+        // Both contexts start right away, but at random points within the
+        // jitter.
+        let times = backend.times();
+        assert_eq!(times.len(), 2);
+        assert_ne!(times[0], times[1]);
+        assert!(times
+            .iter()
+            .all(|time| *time - start < Duration::from_millis(CONTEXT_JITTER)));
+    }
+
+    #[tokio::test]
+    async fn max_active_modules() {
+        let db = db().await;
+
+        // No entries after a while, for both modules.
+        let backend = Arc::new(TestBackend::new(0..0).with_delay(Duration::from_millis(200)));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        service.set_run_once(true);
+        service.set_max_active_modules(1);
+        let stats = service.stats();
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+        service
+            .run_fetcher::<RewardsSlashesFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        finish(service).await;
+
+        // Both modules completed their pass, one after the other.
+        assert_eq!(stats.requests(), 4);
+        assert_eq!(backend.max_in_flight(), 1);
+        assert!(stats.last_passes().values().all(|pass| pass.is_some()));
+    }
+
+    #[tokio::test]
+    async fn partially_known_page() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        // Six entries, two per page, of which one on the second page is
+        // already known.
+        let mut known: Response<TransfersPage> = Default::default();
+        known.data.transfers = Some(transfers(3..4));
+        db.store_transfers(&alice, &known).await.unwrap();
+
+        let mut service = ScrapingService::with_api(db, Arc::new(TestBackend::new(0..6)));
+        service.add_contexts(vec![alice]).await;
+
+        let settings = ModuleSettings {
+            row_amount: 2,
+            ..Default::default()
+        };
+        let stats = run_pass(service, settings).await;
+
+        // Only one entry of the second page is new, the third page must still
+        // be fetched.
+        assert_eq!(stats.events_stored(), 5);
+    }
+
+    #[tokio::test]
+    async fn dry_run() {
+        let db = db().await;
+        let reader = db.reader();
+        let alice = Context::alice();
+
+        // Two full pages, then a partial one.
+        let backend = Arc::new(TestBackend::new(0..2 * ROW_AMOUNT as u64 + 1));
+        let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+        service.add_contexts(vec![alice.clone()]).await;
+        service.set_dry_run(true);
+
+        let stats = run_pass(service, Default::default()).await;
+
+        // Paged until the partial page, without storing anything.
+        assert_eq!(backend.pages(&alice.stash), vec![1, 2, 3]);
+        assert_eq!(stats.events_stored(), 0);
+        assert_eq!(stats.errors(), 0);
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            None
+        );
+        assert!(reader
+            .fetch_transfers(
+                &[alice],
+                Timestamp::from(0),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn dry_run_nominations() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        // More validators than rows per page.
+        let backend = Arc::new(TestBackend::new(0..0).with_nominations(ROW_AMOUNT + 6));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service.add_contexts(vec![alice.clone()]).await;
+        service.set_dry_run(true);
+        service.set_run_once(true);
+        let stats = service.stats();
+        service
+            .run_fetcher::<NominationsFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        finish(service).await;
+
+        // The nominations have no pages, so they are requested once.
+        assert_eq!(backend.pages(&alice.stash), vec![1]);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    #[tokio::test]
+    async fn run_once() {
+        let db = db().await;
+
+        let mut service = ScrapingService::with_api(db.clone(), Arc::new(TestBackend::new(0..5)));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        service.set_run_once(true);
+        let stats = service.stats();
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        tokio::time::timeout(Duration::from_secs(5), service.wait_finished())
+            .await
+            .expect("fetcher did not stop")
+            .unwrap();
+
+        // A single pass over both contexts.
+        assert_eq!(stats.requests(), 2);
+        assert_eq!(stats.events_stored(), 10);
+
+        // Failures are not retried, but recorded.
+        let backend = TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down")));
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service.add_contexts(vec![Context::alice()]).await;
+
+        let stats = run_pass(service, Default::default()).await;
+        assert_eq!(stats.errors(), 1);
+    }
+
+    #[tokio::test]
+    async fn warm_restart() {
+        metrics();
+        let insert_attempts = || {
+            metrics()
+                .into_iter()
+                .find(|(key, _)| key == "monitor_db_insert_attempts_total")
+                .map(|(_, value)| value)
+        };
+
+        let db = db().await;
+        let backend = Arc::new(TestBackend::new(100..125));
+
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+            service.add_contexts(vec![Context::alice()]).await;
+
+            let stats = run_pass(service, Default::default()).await;
+            runs.push((stats.requests(), stats.events_stored(), insert_attempts()));
+        }
+
+        // The restarted service stops paging at the first known page, without
+        // any write for the transfers stored by the first one.
+        assert_eq!(
+            runs,
+            vec![
+                (3, 25, Some(DebugValue::Counter(25))),
+                (1, 0, Some(DebugValue::Counter(25))),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn transfer_fetcher_paging() {
+        let db = db().await;
+        let alice = Context::alice();
+        let backend = Arc::new(TestBackend::new(0..25));
+
+        let run = || {
+            let mut service = ScrapingService::with_api(db.clone(), Arc::clone(&backend) as _);
+            let alice = alice.clone();
+            async move {
+                service.add_contexts(vec![alice]).await;
+                let stats = run_pass(service, Default::default()).await;
+                stats.events_stored()
+            }
+        };
+
+        // Pages through everything, until the partial page.
+        assert_eq!(run().await, 25);
+        assert_eq!(backend.pages(&alice.stash), vec![1, 2, 3]);
+
+        // The first page is already known.
+        backend.reset();
+        assert_eq!(run().await, 0);
+        assert_eq!(backend.pages(&alice.stash), vec![1]);
+
+        // New transfers on the first page, the second one is known.
+        backend.reset();
+        backend.add(25..27);
+        assert_eq!(run().await, 2);
+        assert_eq!(backend.pages(&alice.stash), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn watch_tasks_panic() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..0).with_failure(|_, _| panic!("bug in backend"));
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service.add_contexts(vec![Context::alice()]).await;
+        // Still waiting for its first request.
+        service
+            .run_fetcher::<RewardsSlashesFetcher>(Duration::from_secs(60), Default::default())
+            .await;
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+
+        let err = tokio::time::timeout(Duration::from_secs(5), service.watch_tasks())
+            .await
+            .expect("panic was not surfaced")
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("fetcher 'TransferFetcher' panicked"));
+        assert_eq!(service.running_modules(), vec!["RewardsSlashesFetcher"]);
+    }
+
+    /// The uploads in flight over all destinations.
+    #[derive(Default)]
+    struct InFlight {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    /// A destination counting the received reports, optionally failing on
+    /// every upload.
+    struct CountingPublisher {
+        in_flight: Arc<InFlight>,
+        fail: bool,
+        uploaded: AtomicUsize,
+    }
+
+    impl CountingPublisher {
+        /// Creates the destinations, the ones with a 1-based number for which
+        /// `fail` returns true are failing.
+        fn destinations<F: Fn(usize) -> bool>(
+            count: usize,
+            fail: F,
+        ) -> (Arc<InFlight>, Vec<(Arc<CountingPublisher>, ())>) {
+            let in_flight = Arc::new(InFlight::default());
+            let destinations = (1..=count)
+                .map(|num| {
+                    let publisher = CountingPublisher {
+                        in_flight: Arc::clone(&in_flight),
+                        fail: fail(num),
+                        uploaded: AtomicUsize::new(0),
+                    };
+                    (Arc::new(publisher), ())
+                })
+                .collect();
+
+            (in_flight, destinations)
+        }
+    }
+
+    #[async_trait]
+    impl Publisher for CountingPublisher {
+        type Data = usize;
+        type Info = ();
+
+        async fn upload_data(&self, _info: Self::Info, _data: Self::Data) -> Result<()> {
+            let current = self.in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.in_flight.max.fetch_max(current, Ordering::SeqCst);
+
+            sleep(Duration::from_millis(20)).await;
+            self.in_flight.current.fetch_sub(1, Ordering::SeqCst);
+
+            if self.fail {
+                return Err(anyhow!("destination unavailable"));
+            }
+
+            self.uploaded.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Generates the given number of reports.
+    struct CountingGenerator(usize);
+
+    #[async_trait]
+    impl GenerateReport<CountingPublisher> for CountingGenerator {
+        type Data = ();
+        type Report = usize;
+
+        fn name() -> &'static str {
+            "CountingGenerator"
+        }
+        async fn fetch_data(&self) -> Result<Option<Self::Data>> {
+            Ok(Some(()))
+        }
+        async fn generate(&self, _data: &Self::Data) -> Result<Vec<Self::Report>> {
+            Ok((1..=self.0).collect())
+        }
+        async fn publish(
+            &self,
+            publisher: Arc<CountingPublisher>,
+            info: (),
+            report: Self::Report,
+        ) -> Result<()> {
+            publisher.upload_data(info, report).await
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_reports_bounded() {
+        let generator = CountingGenerator(1);
+        let (in_flight, destinations) = CountingPublisher::destinations(20, |num| num % 3 == 0);
+
+        let reports = generator.generate(&()).await.unwrap();
+        let err = publish_reports(&generator, &destinations, reports, 4)
+            .await
+            .unwrap_err();
+
+        // Never more than the limit, but actually concurrent.
+        assert_eq!(in_flight.max.load(Ordering::SeqCst), 4);
+
+        // Failures don't abort the other destinations.
+        let uploaded: usize = destinations
+            .iter()
+            .map(|(publisher, _)| publisher.uploaded.load(Ordering::SeqCst))
+            .sum();
+        assert_eq!(uploaded, 14);
+        assert_eq!(
+            err.to_string(),
+            "failed to publish to 6 of 20 destinations: \
+             #3 (destination unavailable), #6 (destination unavailable), \
+             #9 (destination unavailable), #12 (destination unavailable), \
+             #15 (destination unavailable), #18 (destination unavailable)"
+        );
+    }
+
+    #[tokio::test]
+    async fn partially_failing_destinations() {
+        let generator = CountingGenerator(2);
+        let (_, destinations) = CountingPublisher::destinations(3, |num| num == 2);
+        let mut schedule = ReportSchedule::new(3_600, None);
+        let uploaded = || -> Vec<usize> {
+            destinations
+                .iter()
+                .map(|(publisher, _)| publisher.uploaded.load(Ordering::SeqCst))
+                .collect()
+        };
+
+        let now = Timestamp::from(1_704_067_200);
+        let err = report_if_due(&generator, &mut schedule, &destinations, 4, now)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("#2 (destination unavailable)"));
+        assert_eq!(uploaded(), vec![2, 0, 2]);
+
+        // The working destinations don't receive the reports again.
+        for step in 1..60 {
+            let now = Timestamp::from(now.as_secs() + step * 60);
+            assert!(
+                report_if_due(&generator, &mut schedule, &destinations, 4, now)
+                    .await
+                    .unwrap()
+            );
+        }
+        assert_eq!(uploaded(), vec![2, 0, 2]);
+
+        // Until the next report is due.
+        let now = Timestamp::from(now.as_secs() + 3_600);
+        assert!(
+            report_if_due(&generator, &mut schedule, &destinations, 4, now)
+                .await
+                .is_err()
+        );
+        assert_eq!(uploaded(), vec![4, 0, 4]);
+    }
+
+    #[test]
+    fn independent_report_schedules() {
+        let mut hourly = ReportSchedule::new(3_600, Some(3_600));
+        let mut daily = ReportSchedule::new(86_400, Some(86_400));
+
+        // Simulate two days of report loop iterations.
+        let (mut hourly_reports, mut daily_reports) = (0, 0);
+        for step in 0..(2 * 86_400 / LOOP_INTERVAL) {
+            let now = Timestamp::from(1_704_067_200 + step * LOOP_INTERVAL);
+
+            if hourly.is_due(now) {
+                hourly.mark(now);
+                hourly_reports += 1;
+            }
+            if daily.is_due(now) {
+                daily.mark(now);
+                daily_reports += 1;
+            }
+        }
+
+        assert_eq!(hourly_reports, 48);
+        assert_eq!(daily_reports, 2);
+    }
+
+    #[tokio::test]
+    async fn fetcher_metrics() {
+        assert!(metrics().is_empty());
+
+        let db = db().await;
+        let mut service = ScrapingService::with_api(db, Arc::new(TestBackend::new(0..5)));
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        run_pass(service, Default::default()).await;
+
+        let metrics = metrics();
+        let value = |name: &str| {
+            metrics
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+
+        assert_eq!(
+            value("monitor_events_stored_total"),
+            Some(&DebugValue::Counter(10))
+        );
+        assert_eq!(
+            value("monitor_fetch_requests_total"),
+            Some(&DebugValue::Counter(2))
+        );
+        assert_eq!(
+            value("monitor_events_fetched_total"),
+            Some(&DebugValue::Counter(10))
+        );
+        assert_eq!(
+            value("monitor_contexts"),
+            Some(&DebugValue::Gauge(2f64.into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn not_found_policy_applied() {
+        let db = db().await;
+
+        let backend = TestBackend::new(0..5).with_failure(|context, _| {
+            if *context == Context::eve() {
+                Some(ChainApiError::NotFound.into())
+            } else {
+                None
+            }
+        });
+        let mut service = ScrapingService::with_api(db, Arc::new(backend));
+        service
+            .add_contexts(vec![Context::alice(), Context::eve()])
+            .await;
+        service.set_not_found_policy(NotFoundPolicy::Disable { after: 1 });
+
+        let stats = run_pass(service, Default::default()).await;
+
+        // The unknown account is disabled instead of failing the cycle.
+        assert_eq!(stats.events_stored(), 5);
+        assert_eq!(stats.not_found(), 1);
+        assert_eq!(stats.disabled(), 1);
+        assert_eq!(stats.errors(), 0);
+    }
+
+    #[test]
+    fn not_found_tracker() {
+        let stats = ScrapingStats::default();
+        let eve = Context::eve();
+
+        // Warnings only.
+        let mut tracker = NotFoundTracker::new(NotFoundPolicy::Warn);
+        for _ in 0..5 {
+            tracker.record("TransferFetcher", &eve, &stats);
+        }
+        assert!(!tracker.is_disabled(&eve));
+
+        // Disabled after consecutive responses only.
+        let mut tracker = NotFoundTracker::new(NotFoundPolicy::Disable { after: 3 });
+        tracker.record("TransferFetcher", &eve, &stats);
+        tracker.record("TransferFetcher", &eve, &stats);
+        tracker.reset(&eve);
+        tracker.record("TransferFetcher", &eve, &stats);
+        tracker.record("TransferFetcher", &eve, &stats);
+        assert!(!tracker.is_disabled(&eve));
+        tracker.record("TransferFetcher", &eve, &stats);
+        assert!(tracker.is_disabled(&eve));
+
+        assert_eq!(stats.not_found(), 10);
+        assert_eq!(stats.disabled(), 1);
+    }
+
+    #[tokio::test]
+    async fn contexts_snapshot() {
+        let db = db().await;
+
+        let mut service = ScrapingService::new(db);
+        assert!(service.contexts_snapshot().await.is_empty());
+
+        service
+            .add_contexts(vec![Context::alice(), Context::bob()])
+            .await;
+        service.add_contexts(vec![Context::eve()]).await;
+
+        assert_eq!(
+            service.contexts_snapshot().await,
+            vec![Context::alice(), Context::bob(), Context::eve()]
+        );
+    }
+
+    #[tokio::test]
+    async fn remove_contexts() {
+        let db = db().await;
+
+        let mut service = ScrapingService::new(db);
+        service
+            .add_contexts(vec![Context::alice(), Context::bob(), Context::eve()])
+            .await;
+
+        let bob = Context::bob();
+        assert_eq!(service.remove_contexts(&[bob.id()]).await, 1);
+        assert_eq!(service.contexts.read().await.len(), 2);
+        assert_eq!(
+            service.contexts_snapshot().await,
+            vec![Context::alice(), Context::eve()]
+        );
+
+        // Same stash on another network is not removed.
+        let mut other = Context::alice();
+        other.network = crate::Network::Kusama;
+        assert_eq!(service.remove_contexts(&[other.id()]).await, 0);
+        assert_eq!(service.contexts.read().await.len(), 2);
+    }
+
+    #[test]
+    fn cooldown_grows_and_resets() {
+        let alice = Context::alice();
+        let mut cooldown = Cooldown::new(Some(CooldownConfig {
+            after: 2,
+            max_factor: 8,
+        }));
+
+        // Count the cycles in which alice was actually polled.
+        let mut polled = vec![];
+        for cycle in 0..20 {
+            if cooldown.should_poll(&alice) {
+                cooldown.record(&alice, false);
+                polled.push(cycle);
+            }
+        }
+
+        // The gaps between polls double up to the max factor.
+        assert_eq!(polled, vec![0, 1, 3, 7, 15]);
+
+        // Becomes active again.
+        while !cooldown.should_poll(&alice) {}
+        cooldown.record(&alice, true);
+        for _ in 0..5 {
+            assert!(cooldown.should_poll(&alice));
+            cooldown.record(&alice, true);
+        }
+
+        // Disabled cooldown.
+        let mut cooldown = Cooldown::new(None);
+        for _ in 0..10 {
+            assert!(cooldown.should_poll(&alice));
+            cooldown.record(&alice, false);
+        }
+    }
+
+    #[test]
+    fn failing_contexts_skipped() {
+        let alice = Context::alice();
+        let bob = Context::bob();
+        let mut failures = FailureTracker::new(Some(SkipFailingConfig {
+            after: 2,
+            skip_cycles: 3,
+        }));
+
+        // Alice keeps failing, Bob is fine.
+        let mut polled = vec![];
+        for cycle in 0..10 {
+            if failures.should_poll(&alice) {
+                failures.record("test", &alice);
+                polled.push(cycle);
+            }
+
+            assert!(failures.should_poll(&bob));
+            failures.reset(&bob);
+        }
+
+        assert_eq!(polled, vec![0, 1, 5, 9]);
+
+        // A success resets the failures.
+        while !failures.should_poll(&alice) {}
+        failures.reset(&alice);
+        assert_eq!(failures.record("test", &alice), 1);
+        assert!(failures.should_poll(&alice));
+
+        // Without config, failing contexts are never skipped.
+        let mut failures = FailureTracker::new(None);
+        for count in 1..10 {
+            assert!(failures.should_poll(&alice));
+            assert_eq!(failures.record("test", &alice), count);
+        }
+    }
+
+    #[test]
+    fn staggered_start_delays() {
+        for _ in 0..100 {
+            let delays: Vec<Duration> = (0..3).map(|idx| start_delay(idx, 5)).collect();
+
+            for (idx, delay) in delays.iter().enumerate() {
+                assert!(*delay >= Duration::from_secs(idx as u64 * 5));
+                assert!(*delay < Duration::from_secs((idx as u64 + 1) * 5));
+            }
+        }
+
+        // Staggering disabled.
+        assert_eq!(start_delay(0, 0), Duration::from_secs(0));
+        assert_eq!(start_delay(2, 0), Duration::from_secs(0));
+
+        // Huge delays saturate instead of overflowing.
+        assert_eq!(start_delay(2, u64::MAX), Duration::from_millis(u64::MAX));
+        assert!(start_delay(0, u64::MAX) < Duration::from_millis(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn staggered_module_starts() {
+        let db = db().await;
+        let modules = [ScrapingModule::Transfer, ScrapingModule::RewardsSlashes];
+
+        let backend = Arc::new(TestBackend::new(0..0));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service.add_contexts(vec![Context::alice()]).await;
+        // Nothing is stored, so the paused clock never races the database.
+        service.set_dry_run(true);
+        service.set_run_once(true);
+        service.set_start_delay(5);
+
+        tokio::time::pause();
+        let start = Instant::now();
+        for module in &modules {
+            service.run(module).await.unwrap();
+        }
+        finish(service).await;
+
+        // Each module sends its first request within its own window.
+        let times = backend.times();
+        assert_eq!(times.len(), 2);
+        assert!(times[0] - start < Duration::from_secs(5));
+        assert!(times[1] - start >= Duration::from_secs(5));
+        assert!(times[1] - start < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn failed_task_sleep() {
+        let db = db().await;
+
+        let backend =
+            Arc::new(TestBackend::new(0..5).with_failure(|_, _| Some(anyhow!("subscan down"))));
+        let mut service = ScrapingService::with_api(db, Arc::clone(&backend) as _);
+        service.add_contexts(vec![Context::alice()]).await;
+        service.set_dry_run(true);
+
+        tokio::time::pause();
+        let settings = ModuleSettings {
+            loop_interval: 1,
+            failed_task_sleep: 30,
+            ..Default::default()
+        };
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), settings)
+            .await;
+
+        while backend.times().len() < 2 {
+            sleep(Duration::from_secs(1)).await;
+        }
+        service.shutdown().await;
+
+        // The failed cycle pauses for the failed task sleep, not the loop
+        // interval.
+        let times = backend.times();
+        assert!(times[1] - times[0] >= Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn live_run_transfer_fetcher() {
+        init();
+
+        info!("Running live test for transfer fetcher");
+
+        let db = db().await;
+
+        let contexts = vec![Context::from(
+            "11uMPbeaEDJhUxzU4ZfWW9VQEsryP9XqFcNRfPdYda6aFWJ",
+        )];
+
+        let mut service = ScrapingService::new(db);
+        service.add_contexts(contexts).await;
+        service
+            .run_fetcher::<TransferFetcher>(Duration::from_secs(0), Default::default())
+            .await;
+        wait_blocking().await;
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn live_run_bonds_fetcher() {
+        init();
+
+        info!("Running live test for bonds fetcher");
 
         let db = db().await;
 
@@ -423,7 +3868,9 @@ mod tests {
 
         let mut service = ScrapingService::new(db);
         service.add_contexts(contexts).await;
-        service.run_fetcher::<TransferFetcher>().await;
+        service
+            .run_fetcher::<BondsFetcher>(Duration::from_secs(0), Default::default())
+            .await;
         wait_blocking().await;
     }
 
@@ -442,7 +3889,9 @@ mod tests {
 
         let mut service = ScrapingService::new(db);
         service.add_contexts(contexts).await;
-        service.run_fetcher::<RewardsSlashesFetcher>().await;
+        service
+            .run_fetcher::<RewardsSlashesFetcher>(Duration::from_secs(0), Default::default())
+            .await;
         wait_blocking().await;
     }
 
@@ -470,7 +3919,9 @@ mod tests {
 
         let generator = TransferReportGenerator::new(db, Arc::clone(&service.contexts));
 
-        service.do_run(generator, publisher, ()).await;
+        service
+            .do_run(generator, ReportSchedule::default(), vec![(publisher, ())])
+            .await;
         wait_blocking().await;
     }
 }
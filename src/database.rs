@@ -1,17 +1,270 @@
 use crate::chain_api::{
-    Nomination, NominationsPage, Response, RewardSlash, RewardsSlashesPage, Transfer, TransfersPage,
+    AccountBalance, BondEvent, BondsPage, Extrinsic, ExtrinsicHash, ExtrinsicsPage, GovernanceVote,
+    GovernanceVotesPage, Identity, Judgement, Nomination, NominationsPage, Response, RewardSlash,
+    RewardsSlashesPage, Transfer, TransfersPage,
 };
-use crate::{BlockNumber, Context, ContextId, Result, Timestamp};
-use bson::{doc, from_document, to_bson, to_document, Bson, Document};
+use crate::core::ScrapingModule;
+use crate::reporting::csv_escape;
+use crate::{Balance, BlockNumber, Context, ContextId, Result, Timestamp};
+use bson::oid::ObjectId;
+use bson::{doc, to_bson, to_document, Bson, Document};
+use chrono::NaiveDateTime;
 use futures::StreamExt;
-use mongodb::options::{FindOptions, UpdateOptions};
+use metrics::increment_counter;
+use mongodb::error::{Error as MongoError, ErrorKind, WriteFailure};
+use mongodb::options::{CountOptions, FindOptions, UpdateModifications, UpdateOptions};
 use mongodb::{Client, Database as MongoDb};
 use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
 
 const COLL_TRANSFER_RAW: &'static str = "raw_transfers";
 const COLL_REWARD_SLASH_RAW: &'static str = "raw_rewards_slashes";
 const COLL_NOMINATIONS_RAW: &'static str = "raw_nominations";
+const COLL_NOMINATION_SETS: &'static str = "nomination_sets";
+const COLL_GOVERNANCE_VOTES_RAW: &'static str = "raw_governance_votes";
+const COLL_GOVERNANCE_PROPOSALS_RAW: &'static str = "raw_governance_proposals";
+const COLL_BONDS_RAW: &'static str = "raw_bonds";
+const COLL_EXTRINSICS_RAW: &'static str = "raw_extrinsics";
+const COLL_JUDGEMENTS: &'static str = "judgements";
+const COLL_BALANCES: &'static str = "balance_snapshots";
+const COLL_IDENTITIES: &'static str = "identities";
+const COLL_LOCKS: &'static str = "locks";
+const COLL_CURSORS: &'static str = "cursors";
+const COLL_BACKFILLS: &'static str = "backfills";
+/// Marker documents of the migrations which already ran.
+const COLL_MIGRATIONS: &'static str = "migrations";
+/// Matches the monthly transfer collections, see `transfer_bucket`.
+const TRANSFER_BUCKET_PATTERN: &'static str = "^raw_transfers_[0-9]{4}_[0-9]{2}$";
+
+/// Raw event collections which expire with the retention, see
+/// `Database::setup_retention`.
+const RETENTION_COLLECTIONS: [&'static str; 6] = [
+    COLL_TRANSFER_RAW,
+    COLL_REWARD_SLASH_RAW,
+    COLL_GOVERNANCE_VOTES_RAW,
+    COLL_GOVERNANCE_PROPOSALS_RAW,
+    COLL_BONDS_RAW,
+    COLL_EXTRINSICS_RAW,
+];
+/// Raw event collections whose entries carry the block time of the event on
+/// the top level (besides the transfer buckets), see `Database::insert_if_new`.
+const BLOCK_TIME_COLLECTIONS: [&'static str; 5] = [
+    COLL_TRANSFER_RAW,
+    COLL_REWARD_SLASH_RAW,
+    COLL_GOVERNANCE_PROPOSALS_RAW,
+    COLL_BONDS_RAW,
+    COLL_EXTRINSICS_RAW,
+];
+/// Collections storing entries of a context (besides the transfer buckets),
+/// see `DatabaseReader::find_contexts_by_description`.
+const CONTEXT_COLLECTIONS: [&'static str; 11] = [
+    COLL_TRANSFER_RAW,
+    COLL_REWARD_SLASH_RAW,
+    COLL_NOMINATIONS_RAW,
+    COLL_NOMINATION_SETS,
+    COLL_GOVERNANCE_VOTES_RAW,
+    COLL_GOVERNANCE_PROPOSALS_RAW,
+    COLL_BONDS_RAW,
+    COLL_EXTRINSICS_RAW,
+    COLL_JUDGEMENTS,
+    COLL_BALANCES,
+    COLL_IDENTITIES,
+];
+/// Name of the TTL index on the `stored_at` field.
+const TTL_INDEX: &'static str = "stored_at_ttl";
+
+const INDEX_LOCK: &'static str = "index_setup";
+/// See `Database::migrate_block_time`.
+const MIGRATION_BLOCK_TIME: &'static str = "block_time";
+/// Seconds after which a lock is considered stale, e.g. if the holder crashed.
+const LOCK_EXPIRATION: u64 = 30;
+const LOCK_RETRY_INTERVAL: u64 = 500;
+const DUPLICATE_KEY: i32 = 11000;
+const NAMESPACE_NOT_FOUND: i32 = 26;
+const INDEX_NOT_FOUND: i32 = 27;
+const INDEX_OPTIONS_CONFLICT: i32 = 85;
+/// Attempts to connect to the database on startup, see `Database::connect`.
+pub const CONNECT_ATTEMPTS: u32 = 5;
+/// Seconds to wait after the first failed connection attempt, doubled with
+/// every further attempt.
+const CONNECT_RETRY_DELAY: u64 = 1;
+const MAX_CONNECT_RETRY_DELAY: u64 = 30;
+/// Seconds before the last exported id that are read again by incremental
+/// exports, see `DatabaseReader::export_ndjson`.
+const EXPORT_OVERLAP: u32 = 60;
+/// The unit in which aggregated token amounts are split into integers, so
+/// that each part fits into an `i64`. See `total_from_parts`.
+const TOTAL_PART_UNIT: i64 = 1_000_000_000_000_000_000;
+
+/// Name of the monthly collection a transfer with the given timestamp is
+/// stored in when time buckets are enabled, e.g. `raw_transfers_2024_01`.
+/// Returns `None` if the timestamp is out of range.
+fn transfer_bucket(timestamp: Timestamp) -> Option<String> {
+    let secs = i64::try_from(timestamp.as_secs()).ok()?;
+    NaiveDateTime::from_timestamp_opt(secs, 0)
+        .map(|date| format!("{}_{}", COLL_TRANSFER_RAW, date.format("%Y_%m")))
+}
+
+/// Names of the collections. A prefix allows multiple instances (e.g. staging
+/// and production) to share one database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectionNames {
+    prefix: String,
+}
+
+impl CollectionNames {
+    /// Prepends the prefix to all collection names, e.g. `staging_` results
+    /// in `staging_raw_transfers`. Only alphanumeric characters, `_` and `-`
+    /// are allowed.
+    pub fn with_prefix(prefix: &str) -> Result<Self> {
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow!("invalid collection prefix '{}'", prefix));
+        }
+
+        Ok(CollectionNames {
+            prefix: prefix.to_string(),
+        })
+    }
+    fn get(&self, coll: &str) -> String {
+        format!("{}{}", self.prefix, coll)
+    }
+    fn transfer_bucket(&self, timestamp: Timestamp) -> Option<String> {
+        transfer_bucket(timestamp).map(|bucket| self.get(&bucket))
+    }
+    /// Matches the monthly transfer collections with the prefix.
+    fn transfer_bucket_pattern(&self) -> String {
+        // The prefix contains no regex metacharacters.
+        TRANSFER_BUCKET_PATTERN.replacen('^', &format!("^{}", self.prefix), 1)
+    }
+}
+
+/// Escapes the regex metacharacters, so the value is matched literally.
+fn escape_regex(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn command_error_code(err: &MongoError) -> Option<i32> {
+    match err.kind.as_ref() {
+        ErrorKind::Command(err) => Some(err.code),
+        _ => None,
+    }
+}
+
+fn is_duplicate_key(err: &MongoError) -> bool {
+    match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(err)) => err.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
+
+/// The smallest id generated `EXPORT_OVERLAP` seconds before the given one.
+fn export_overlap_start(id: ObjectId) -> ObjectId {
+    let bytes = id.bytes();
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let mut start = [0; 12];
+    start[..4].copy_from_slice(&secs.saturating_sub(EXPORT_OVERLAP).to_be_bytes());
+    ObjectId::from_bytes(start)
+}
+
+/// Delay before the next connection attempt, after the given number of failed
+/// attempts.
+fn connect_retry_delay(attempt: u32) -> Duration {
+    let secs = CONNECT_RETRY_DELAY.saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1)));
+    Duration::from_secs(secs.min(MAX_CONNECT_RETRY_DELAY))
+}
+
+/// Matches the transfers of the contexts within the given block time range
+/// (inclusive).
+fn transfers_filter(ids: &[ContextId], from: Timestamp, to: Timestamp) -> Result<Document> {
+    Ok(doc! {
+        "context_id": {
+            "$in": ids.to_bson()?,
+        },
+        "$and": [
+            {
+                "block_timestamp": {
+                    "$gte": from.to_bson()?
+                }
+            },
+            {
+                "block_timestamp": {
+                    "$lte": to.to_bson()?
+                }
+            }
+        ]
+    })
+}
+
+/// Splits the Decimal128 total in the given field of an aggregation into
+/// integers. Decimal128 is not supported by the driver and its string
+/// representation might use an exponent. See `total_from_parts`.
+fn total_parts(field: &str) -> Document {
+    let total = format!("${}", field);
+    let int = doc! { "$trunc": total.clone() };
+
+    doc! {
+        "int_high": {
+            "$toLong": {
+                "$divide": [
+                    { "$subtract": [int.clone(), { "$mod": [int.clone(), TOTAL_PART_UNIT] }] },
+                    TOTAL_PART_UNIT,
+                ]
+            }
+        },
+        "int_low": { "$toLong": { "$mod": [int.clone(), TOTAL_PART_UNIT] } },
+        "frac": {
+            "$toLong": {
+                "$multiply": [{ "$subtract": [total, int] }, TOTAL_PART_UNIT]
+            }
+        },
+    }
+}
+
+/// Converts the integer parts of an aggregated amount back to plancks. The
+/// amount is `int_high * TOTAL_PART_UNIT + int_low` units plus
+/// `frac / TOTAL_PART_UNIT` of a unit, where a unit is a token with the given
+/// decimals (or a planck with none).
+fn total_from_parts(doc: &Document, decimals: u32) -> Result<Balance> {
+    let part = |key: &str| -> Result<u128> {
+        match doc.get_i64(key)? {
+            val if val < 0 => Err(anyhow!("negative transfer total: {:?}", doc)),
+            val => Ok(val as u128),
+        }
+    };
+    let (int_high, int_low, frac) = (part("int_high")?, part("int_low")?, part("frac")?);
+
+    // The fraction has 18 digits, of which only the network's decimals may be set.
+    let frac_unit = 10u128.pow(18 - decimals);
+    if frac % frac_unit != 0 {
+        return Err(anyhow!("transfer total below a planck: {:?}", doc));
+    }
+
+    int_high
+        .checked_mul(TOTAL_PART_UNIT as u128)
+        .and_then(|int| int.checked_add(int_low))
+        .and_then(|int| int.checked_mul(10u128.pow(decimals)))
+        .and_then(|plancks| plancks.checked_add(frac / frac_unit))
+        .map(Balance::from)
+        .ok_or_else(|| anyhow!("transfer total overflow: {:?}", doc))
+}
 
 /// Convenience trait. Converts a value to BSON.
 trait ToBson {
@@ -32,21 +285,520 @@ impl<T: Serialize> ToBson for T {
 #[serde(rename_all = "snake_case")]
 pub struct ContextData<'a, T: Clone> {
     pub context_id: ContextId<'a>,
+    // The tags of the context at the time the entry was stored.
+    #[serde(default)]
+    pub tags: Cow<'a, Vec<String>>,
+    // The description of the context at the time the entry was stored.
+    #[serde(default)]
+    pub description: Cow<'a, String>,
     pub timestamp: Timestamp,
     pub data: Cow<'a, T>,
 }
 
+/// The validators nominated by an account at the time of the entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NominationSet {
+    /// The validator stashes, sorted.
+    pub targets: Vec<String>,
+}
+
+/// Position of an incremental export. Passing it to the next export only
+/// emits entries stored after the previous one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportCursor {
+    last_id: Option<ObjectId>,
+    /// The exported ids within the overlap window before `last_id`, which
+    /// are skipped when the window is read again.
+    #[serde(default)]
+    recent: Vec<ObjectId>,
+}
+
+/// The number of transfers of an account over a time range, their summed
+/// amount and the fees paid on the outgoing ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextTotals {
+    pub context_id: ContextId<'static>,
+    pub count: u64,
+    pub amount: Balance,
+    pub fees: Balance,
+}
+
+/// A change of a registrar's judgement compared to the stored state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JudgementChange {
+    pub registrar: u32,
+    pub old: Option<Judgement>,
+    pub new: Option<Judgement>,
+}
+
+impl JudgementChange {
+    /// Whether the judgement was revoked or lowered.
+    pub fn is_downgrade(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(_), None) => true,
+            (Some(old), Some(new)) => new.rank() < old.rank(),
+            _ => false,
+        }
+    }
+    fn diff(old: &[Judgement], new: &[Judgement]) -> Vec<JudgementChange> {
+        let mut changes = vec![];
+
+        for prev in old {
+            let current = new.iter().find(|j| j.index == prev.index);
+            if current != Some(prev) {
+                changes.push(JudgementChange {
+                    registrar: prev.index,
+                    old: Some(prev.clone()),
+                    new: current.cloned(),
+                });
+            }
+        }
+
+        for current in new {
+            if !old.iter().any(|j| j.index == current.index) {
+                changes.push(JudgementChange {
+                    registrar: current.index,
+                    old: None,
+                    new: Some(current.clone()),
+                });
+            }
+        }
+
+        changes
+    }
+}
+
 #[derive(Clone)]
 pub struct Database {
     db: MongoDb,
+    colls: CollectionNames,
+    time_buckets: bool,
+    retention: Option<u64>,
+    /// Transfer buckets whose indexes are already set up, shared by all
+    /// clones.
+    indexed_buckets: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Database {
     pub async fn new(uri: &str, db: &str) -> Result<Self> {
+        Self::connect(uri, db, CollectionNames::default(), CONNECT_ATTEMPTS).await
+    }
+    /// Connects to the database and sets up the indexes of the given
+    /// collections. Retries with an exponential backoff, e.g. while the
+    /// database is still starting up, and fails after `max_attempts`.
+    pub async fn connect(
+        uri: &str,
+        db: &str,
+        colls: CollectionNames,
+        max_attempts: u32,
+    ) -> Result<Self> {
+        let mut attempt = 1;
+        loop {
+            match Self::try_connect(uri, db, &colls).await {
+                Ok(db) => return Ok(db),
+                Err(err) if attempt < max_attempts => {
+                    let delay = connect_retry_delay(attempt);
+                    warn!(
+                        "Database connection failed (attempt {}/{}), retrying in {}s: {:?}",
+                        attempt,
+                        max_attempts,
+                        delay.as_secs(),
+                        err
+                    );
+
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(anyhow!(
+                        "failed to connect to the database after {} attempt(s): {:?}",
+                        attempt,
+                        err
+                    ))
+                }
+            }
+        }
+    }
+    /// Creates the client without checking the connection or setting up the
+    /// indexes, so nothing is written to the database.
+    pub async fn connect_lazy(uri: &str, db: &str, colls: CollectionNames) -> Result<Self> {
         Ok(Database {
             db: Client::with_uri_str(uri).await?.database(db),
+            colls: colls,
+            time_buckets: false,
+            retention: None,
+            indexed_buckets: Default::default(),
         })
     }
+    async fn try_connect(uri: &str, db: &str, colls: &CollectionNames) -> Result<Self> {
+        let db = Self::connect_lazy(uri, db, colls.clone()).await?;
+
+        // The client connects lazily, so make sure the database is reachable
+        // before setting up the indexes.
+        db.ping().await?;
+
+        // Multiple replicas might start at the same time against a fresh
+        // database, so the index setup is serialized.
+        db.acquire_lock(INDEX_LOCK).await?;
+        let res = db.setup_indexes().await;
+        db.release_lock(INDEX_LOCK).await?;
+        res?;
+
+        // Might take longer than the lock expiration on large collections.
+        db.migrate_block_time().await?;
+
+        Ok(db)
+    }
+    async fn setup_indexes(&self) -> Result<()> {
+        let indexes = [
+            (COLL_TRANSFER_RAW, "data.extrinsic_index"),
+            (COLL_REWARD_SLASH_RAW, "data.extrinsic_hash"),
+            (COLL_NOMINATIONS_RAW, "data.stash_account_display.address"),
+            (COLL_GOVERNANCE_VOTES_RAW, "data.extrinsic_index"),
+            (COLL_GOVERNANCE_PROPOSALS_RAW, "data.extrinsic_index"),
+            (COLL_BONDS_RAW, "data.extrinsic_index"),
+            (COLL_EXTRINSICS_RAW, "data.extrinsic_index"),
+            (COLL_BALANCES, "timestamp"),
+            (COLL_CURSORS, "module"),
+            (COLL_BACKFILLS, "module"),
+        ];
+
+        for (coll, key) in &indexes {
+            self.create_unique_index(&self.colls.get(coll), key).await?;
+        }
+
+        for coll in &CONTEXT_COLLECTIONS {
+            self.create_description_index(&self.colls.get(coll)).await?;
+        }
+
+        for coll in &self.block_time_collections().await? {
+            self.create_block_time_index(coll).await?;
+        }
+
+        // For looking up the latest nomination set of an account.
+        self.db
+            .run_command(
+                doc! {
+                    "createIndexes": self.colls.get(COLL_NOMINATION_SETS),
+                    "indexes": [
+                        {
+                            "key": { "context_id": 1, "timestamp": -1 },
+                            "name": "context_id_timestamp",
+                        }
+                    ]
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Creates the index for looking up contexts by description. This is a
+    /// regular index rather than a text index, since the latter only matches
+    /// whole words. A case-insensitive regex scans the (smaller) index keys
+    /// instead of the documents.
+    async fn create_description_index(&self, coll: &str) -> Result<()> {
+        self.db
+            .run_command(
+                doc! {
+                    "createIndexes": coll,
+                    "indexes": [
+                        {
+                            "key": { "description": 1, "context_id": 1 },
+                            "name": "description_context_id",
+                        }
+                    ]
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Creates the index for querying the entries of contexts by block time
+    /// range.
+    async fn create_block_time_index(&self, coll: &str) -> Result<()> {
+        self.db
+            .run_command(
+                doc! {
+                    "createIndexes": coll,
+                    "indexes": [
+                        {
+                            "key": { "context_id": 1, "block_timestamp": 1 },
+                            "name": "context_id_block_timestamp",
+                        }
+                    ]
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// The collections whose entries carry the block time on the top level,
+    /// including the transfer buckets.
+    async fn block_time_collections(&self) -> Result<Vec<String>> {
+        let mut colls: Vec<String> = BLOCK_TIME_COLLECTIONS
+            .iter()
+            .map(|coll| self.colls.get(coll))
+            .collect();
+        colls.extend(
+            self.db
+                .list_collection_names(doc! {
+                    "name": {
+                        "$regex": self.colls.transfer_bucket_pattern(),
+                    }
+                })
+                .await?,
+        );
+
+        Ok(colls)
+    }
+    /// Copies the block time of entries stored before it was added on the top
+    /// level, so the range queries find them. Runs once per database, which
+    /// is recorded by a marker document. This runs without the index lock, so
+    /// replicas starting at the same time might both run it, which is
+    /// harmless.
+    async fn migrate_block_time(&self) -> Result<()> {
+        let migrations = self
+            .db
+            .collection::<Document>(&self.colls.get(COLL_MIGRATIONS));
+
+        let filter = doc! { "_id": MIGRATION_BLOCK_TIME };
+        if migrations.find_one(filter.clone(), None).await?.is_some() {
+            return Ok(());
+        }
+
+        for coll in &self.block_time_collections().await? {
+            self.backfill_block_time(coll).await?;
+        }
+
+        migrations
+            .update_one(
+                filter,
+                doc! {
+                    "$set": { "completed_at": Timestamp::now().to_bson()? },
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Copies the block time of the entries in the collection which lack it
+    /// on the top level.
+    async fn backfill_block_time(&self, coll: &str) -> Result<()> {
+        let res = self
+            .db
+            .collection::<Document>(coll)
+            .update_many(
+                doc! {
+                    "block_timestamp": { "$exists": false },
+                    "data.block_timestamp": { "$exists": true },
+                },
+                UpdateModifications::Pipeline(vec![doc! {
+                    "$set": { "block_timestamp": "$data.block_timestamp" }
+                }]),
+                None,
+            )
+            .await?;
+
+        if res.modified_count > 0 {
+            debug!(
+                "Added the block time to {} entries in {}",
+                res.modified_count, coll
+            );
+        }
+
+        Ok(())
+    }
+    /// Creates the unique index on the context and the given key. Does nothing
+    /// if the index already exists.
+    async fn create_unique_index(&self, coll: &str, key: &str) -> Result<()> {
+        let mut keys = doc! { "context_id": 1 };
+        keys.insert(key, 1);
+
+        self.db
+            .run_command(
+                doc! {
+                    "createIndexes": coll,
+                    "indexes": [
+                        {
+                            "key": keys,
+                            "name": format!("context_id_{}", key.replace(".", "_")),
+                            "unique": true,
+                        }
+                    ]
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Stores transfers in monthly collections, based on the block timestamp,
+    /// so old months can simply be dropped. Entries stored before enabling
+    /// this are not moved.
+    pub fn with_time_buckets(mut self, enabled: bool) -> Self {
+        self.time_buckets = enabled;
+        self
+    }
+    /// Lets MongoDB delete the raw events the given amount of days after they
+    /// were stored, based on their `stored_at` field. Takes effect with
+    /// `setup_retention`. Entries stored before lack the field and are kept.
+    pub fn with_retention(mut self, days: Option<u64>) -> Self {
+        self.retention = days;
+        self
+    }
+    /// Creates (or updates) the TTL indexes of the raw event collections
+    /// according to the retention, or removes them if it is disabled.
+    pub async fn setup_retention(&self) -> Result<()> {
+        let mut colls: Vec<String> = RETENTION_COLLECTIONS
+            .iter()
+            .map(|coll| self.colls.get(coll))
+            .collect();
+        colls.extend(
+            self.db
+                .list_collection_names(doc! {
+                    "name": {
+                        "$regex": self.colls.transfer_bucket_pattern(),
+                    }
+                })
+                .await?,
+        );
+
+        for coll in &colls {
+            self.setup_ttl_index(coll).await?;
+        }
+
+        Ok(())
+    }
+    async fn setup_ttl_index(&self, coll: &str) -> Result<()> {
+        let secs = match self.retention {
+            // MongoDB expects a 32-bit integer.
+            Some(days) => days.saturating_mul(24 * 60 * 60).min(i32::MAX as u64) as i64,
+            None => {
+                // Remove the index of a previously enabled retention.
+                let res = self
+                    .db
+                    .run_command(
+                        doc! {
+                            "dropIndexes": coll,
+                            "index": TTL_INDEX,
+                        },
+                        None,
+                    )
+                    .await;
+
+                match res {
+                    Err(err)
+                        if command_error_code(&err) == Some(INDEX_NOT_FOUND)
+                            || command_error_code(&err) == Some(NAMESPACE_NOT_FOUND) => {}
+                    res => {
+                        res?;
+                    }
+                }
+
+                return Ok(());
+            }
+        };
+
+        let res = self
+            .db
+            .run_command(
+                doc! {
+                    "createIndexes": coll,
+                    "indexes": [
+                        {
+                            "key": { "stored_at": 1 },
+                            "name": TTL_INDEX,
+                            "expireAfterSeconds": secs,
+                        }
+                    ]
+                },
+                None,
+            )
+            .await;
+
+        match res {
+            // The retention was changed.
+            Err(err) if command_error_code(&err) == Some(INDEX_OPTIONS_CONFLICT) => {
+                self.db
+                    .run_command(
+                        doc! {
+                            "collMod": coll,
+                            "index": {
+                                "name": TTL_INDEX,
+                                "expireAfterSeconds": secs,
+                            }
+                        },
+                        None,
+                    )
+                    .await?;
+            }
+            res => {
+                res?;
+            }
+        }
+
+        Ok(())
+    }
+    /// Acquires a short-lived lock shared by all instances using the same
+    /// database. Waits until the lock is free or has expired.
+    async fn acquire_lock(&self, name: &str) -> Result<()> {
+        let coll = self.db.collection::<Document>(&self.colls.get(COLL_LOCKS));
+
+        loop {
+            let now = Timestamp::now().as_secs() as i64;
+
+            // Remove the lock if the holder did not release it in time.
+            coll.delete_one(
+                doc! {
+                    "_id": name,
+                    "expires_at": {
+                        "$lt": now,
+                    }
+                },
+                None,
+            )
+            .await?;
+
+            match coll
+                .insert_one(
+                    doc! {
+                        "_id": name,
+                        "expires_at": now + LOCK_EXPIRATION as i64,
+                    },
+                    None,
+                )
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(err) if is_duplicate_key(&err) => {
+                    debug!("Waiting for database lock '{}'", name);
+                    sleep(Duration::from_millis(LOCK_RETRY_INTERVAL)).await;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+    async fn release_lock(&self, name: &str) -> Result<()> {
+        self.db
+            .collection::<Document>(&self.colls.get(COLL_LOCKS))
+            .delete_one(doc! { "_id": name }, None)
+            .await?;
+
+        Ok(())
+    }
+    /// Checks that the database server responds.
+    pub async fn ping(&self) -> Result<()> {
+        self.db.run_command(doc! { "ping": 1 }, None).await?;
+        Ok(())
+    }
     pub async fn check_connection(&self) -> Result<()> {
         use std::time::Duration;
         use tokio::time::timeout;
@@ -62,15 +814,74 @@ impl Database {
             Ok(())
         }
     }
+    /// Inserts the entry unless one matching the filter already exists.
+    /// Returns whether it was newly inserted. A duplicate key error (e.g. if
+    /// another instance stored the same entry concurrently) also means that
+    /// the entry exists, instead of failing the whole page. Adds the
+    /// `stored_at` field the retention is based on and, for events, the
+    /// `block_timestamp` field the time range queries are based on.
+    async fn insert_if_new<T>(&self, coll: &str, filter: Document, entry: &T) -> Result<bool>
+    where
+        T: Serialize,
+    {
+        let mut entry = entry.to_document()?;
+        // A date, so that a TTL index can expire the entry.
+        entry.insert("stored_at", bson::DateTime::now());
+        // The block time of the event, outside of the Subscan payload.
+        let block_time = entry
+            .get_document("data")
+            .ok()
+            .and_then(|data| data.get("block_timestamp"))
+            .cloned();
+        if let Some(block_time) = block_time {
+            entry.insert("block_timestamp", block_time);
+        }
+
+        increment_counter!("monitor_db_insert_attempts_total");
+        let res = self
+            .db
+            .collection::<Document>(coll)
+            .update_one(
+                filter,
+                doc! {
+                    "$setOnInsert": entry,
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await;
+
+        match res {
+            Ok(res) => {
+                assert_eq!(res.modified_count, 0);
+                Ok(res.upserted_id.is_some())
+            }
+            Err(err) if is_duplicate_key(&err) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+    /// Stores the transfers of the account. Transfers are unique per context
+    /// and extrinsic index (see `setup_indexes`), so already stored ones are
+    /// skipped. Returns how many were newly inserted.
     pub async fn store_transfer_event(
         &self,
         context: &Context,
         data: &Response<TransfersPage>,
     ) -> Result<usize> {
-        let coll = self
-            .db
-            .collection::<ContextData<Transfer>>(COLL_TRANSFER_RAW);
-
+        self.store_transfers(context, data)
+            .await
+            .map(|inserted| inserted.len())
+    }
+    /// Like `store_transfer_event`, but returns the newly inserted transfers
+    /// so they can be alerted on.
+    pub async fn store_transfers(
+        &self,
+        context: &Context,
+        data: &Response<TransfersPage>,
+    ) -> Result<Vec<Transfer>> {
         // Add the full context to each transfer, so the corresponding account
         // can be identified.
         let extrinsics: Vec<ContextData<Transfer>> = data
@@ -79,55 +890,132 @@ impl Database {
             .as_ref()
             .ok_or(anyhow!("No transfers found in response body"))?
             .iter()
+            // Subscan already filters by direction, this is just a safeguard.
+            .filter(|t| {
+                context
+                    .transfer_direction
+                    .matches(&context.stash, &t.from, &t.to)
+            })
+            .filter(|t| context.calls.matches(&t.call()))
             .map(|t| ContextData {
                 context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
                 timestamp: Timestamp::now(),
                 data: Cow::Borrowed(t),
             })
             .collect();
 
-        // Insert new entries. Return count of how many were newly inserted.
-        let mut count = 0;
+        let indexes = extrinsics
+            .iter()
+            .map(|extrinsic| extrinsic.data.extrinsic_index.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+
+        // Insert new entries. Return the newly inserted ones.
+        let mut inserted = vec![];
+        // The already stored transfers of the page, per collection.
+        let mut known: HashMap<String, Vec<Bson>> = HashMap::new();
         for extrinsic in &extrinsics {
-            let res = coll
-                .update_one(
-                    doc! {
-                        "context_id": context.id().to_bson()?,
-                        "data.extrinsic_index": extrinsic.data.extrinsic_index.to_bson()?,
-                    },
-                    doc! {
-                        "$setOnInsert": extrinsic.to_bson()?,
-                    },
-                    {
-                        let mut opt = UpdateOptions::default();
-                        opt.upsert = Some(true);
-                        Some(opt)
-                    },
-                )
-                .await?;
+            let coll_name = if self.time_buckets {
+                let bucket = self
+                    .colls
+                    .transfer_bucket(extrinsic.data.block_timestamp)
+                    .ok_or(anyhow!("Transfer timestamp out of range"))?;
+
+                // Buckets are created on demand, including their index. The
+                // indexes are only set up once per process.
+                if !self.indexed_buckets.lock().await.contains(&bucket) {
+                    self.create_unique_index(&bucket, "data.extrinsic_index")
+                        .await?;
+                    self.create_description_index(&bucket).await?;
+                    self.create_block_time_index(&bucket).await?;
+                    if self.retention.is_some() {
+                        self.setup_ttl_index(&bucket).await?;
+                    }
+                    self.indexed_buckets.lock().await.insert(bucket.clone());
+                }
 
-            assert_eq!(res.modified_count, 0);
-            res.upserted_id.map(|_| {
+                bucket
+            } else {
+                self.colls.get(COLL_TRANSFER_RAW)
+            };
+
+            if !known.contains_key(&coll_name) {
+                let stored = self
+                    .stored_keys(&coll_name, context, "data.extrinsic_index", &indexes)
+                    .await?;
+                known.insert(coll_name.clone(), stored);
+            }
+            if known[&coll_name].contains(&extrinsic.data.extrinsic_index.to_bson()?) {
+                continue;
+            }
+
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_index": extrinsic.data.extrinsic_index.to_bson()?,
+            };
+
+            if self.insert_if_new(&coll_name, filter, extrinsic).await? {
                 trace!(
                     "Added new transfer to database for {:?}: {:?}",
                     context,
                     extrinsic
                 );
-                count += 1;
-            });
+                inserted.push(extrinsic.data.as_ref().clone());
+            }
         }
 
-        Ok(count)
+        Ok(inserted)
+    }
+    /// Returns which of the given keys (values of the `field`) are already
+    /// stored for the context, with a single query instead of one insert
+    /// attempt per entry.
+    async fn stored_keys(
+        &self,
+        coll: &str,
+        context: &Context,
+        field: &str,
+        keys: &[Bson],
+    ) -> Result<Vec<Bson>> {
+        let mut filter = doc! {
+            "context_id": context.id().to_bson()?,
+        };
+        filter.insert(field, doc! { "$in": keys.to_vec() });
+
+        let mut cursor = self
+            .db
+            .collection::<Document>(coll)
+            .find(filter, {
+                let mut ops = FindOptions::default();
+                let mut projection = Document::new();
+                projection.insert(field, 1);
+                ops.projection = Some(projection);
+                Some(ops)
+            })
+            .await?;
+
+        let mut stored = vec![];
+        while let Some(doc) = cursor.next().await {
+            // Follow the path of the (nested) field.
+            let mut value = Bson::Document(doc?);
+            for name in field.split('.') {
+                value = match value {
+                    Bson::Document(mut doc) => doc
+                        .remove(name)
+                        .ok_or_else(|| anyhow!("stored entry without field '{}'", field))?,
+                    _ => return Err(anyhow!("stored entry without field '{}'", field)),
+                };
+            }
+            stored.push(value);
+        }
+
+        Ok(stored)
     }
     pub async fn store_reward_slash_event(
         &self,
         context: &Context,
         data: &Response<RewardsSlashesPage>,
     ) -> Result<usize> {
-        let coll = self
-            .db
-            .collection::<ContextData<RewardSlash>>(COLL_REWARD_SLASH_RAW);
-
         // Add the full context to each entry, so the corresponding account
         // can be identified.
         let reward_slashes: Vec<ContextData<RewardSlash>> = data
@@ -136,55 +1024,75 @@ impl Database {
             .as_ref()
             .ok_or(anyhow!("No rewards/slashes found in response body"))?
             .iter()
+            // The hash is used as the unique key, so malformed ones are
+            // rejected instead of silently deduplicating unrelated events.
+            .filter(|rs| {
+                let valid = rs.extrinsic_hash.is_valid();
+                if !valid {
+                    warn!(
+                        "Rejecting reward/slash with invalid extrinsic hash for {:?}: {:?}",
+                        context, rs
+                    );
+                }
+                valid
+            })
+            .filter(|rs| context.calls.matches(&rs.call()))
             .map(|rs| ContextData {
                 context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
                 timestamp: Timestamp::now(),
                 data: Cow::Borrowed(rs),
             })
             .collect();
 
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_REWARD_SLASH_RAW);
+        let keys = reward_slashes
+            .iter()
+            .map(|reward_slash| reward_slash.data.extrinsic_hash.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.extrinsic_hash", &keys)
+            .await?;
+
         // Insert new entries. Return count of how many were newly inserted.
         let mut count = 0;
-        for reward_slash in &reward_slashes {
-            let res = coll
-                .update_one(
-                    doc! {
-                        "context_id": context.id().to_bson()?,
-                        "data.extrinsic_hash": reward_slash.data.extrinsic_hash.to_bson()?,
-                    },
-                    doc! {
-                        "$setOnInsert": reward_slash.to_bson()?,
-                    },
-                    {
-                        let mut opt = UpdateOptions::default();
-                        opt.upsert = Some(true);
-                        Some(opt)
-                    },
-                )
-                .await?;
+        for (reward_slash, key) in reward_slashes.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
+            }
+
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_hash": key,
+            };
 
-            assert_eq!(res.modified_count, 0);
-            res.upserted_id.map(|_| {
+            if self.insert_if_new(&coll, filter, reward_slash).await? {
                 trace!(
                     "Added new rewards_slash to database for {:?}: {:?}",
                     context,
                     reward_slash
                 );
                 count += 1;
-            });
+            }
         }
 
         Ok(count)
     }
+    /// Stores the validators nominated by the account. Nominations carry no
+    /// extrinsic, so an entry is unique per context and validator stash
+    /// address (see `setup_indexes`): each nominated validator is stored once,
+    /// when it first shows up. Returns how many were newly inserted.
+    ///
+    /// The full selection is additionally stored as a `NominationSet`
+    /// whenever it differs from the previous one. Failing to do so is only
+    /// logged.
     pub async fn store_nomination_event(
         &self,
         context: &Context,
         data: &Response<NominationsPage>,
     ) -> Result<usize> {
-        let coll = self
-            .db
-            .collection::<ContextData<Nomination>>(COLL_NOMINATIONS_RAW);
-
         // Add the full context to each entry, so the corresponding account
         // can be identified.
         let validators: Vec<ContextData<Nomination>> = data
@@ -195,337 +1103,2794 @@ impl Database {
             .iter()
             .map(|v| ContextData {
                 context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
                 timestamp: Timestamp::now(),
                 data: Cow::Borrowed(v),
             })
             .collect();
 
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_NOMINATIONS_RAW);
+        let keys = validators
+            .iter()
+            .map(|validator| validator.data.stash_account_display.address.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.stash_account_display.address", &keys)
+            .await?;
+
         // Insert new entries. Return count of how many were newly inserted.
         let mut count = 0;
-        for validator in &validators {
-            let res = coll
-                .update_one(
-                    doc! {
-                        "context_id": context.id().to_bson()?,
-                        "data.stash_account_display.address": validator.data.stash_account_display.address.to_bson()?,
-                    },
-                    doc! {
-                        "$setOnInsert": validator.to_bson()?,
-                    },
-                    {
-                        let mut opt = UpdateOptions::default();
-                        opt.upsert = Some(true);
-                        Some(opt)
-                    },
-                )
-                .await?;
+        for (validator, key) in validators.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
+            }
+
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.stash_account_display.address": key,
+            };
 
-            assert_eq!(res.modified_count, 0);
-            res.upserted_id.map(|_| {
+            if self.insert_if_new(&coll, filter, validator).await? {
                 trace!(
-                    "Added new rewards_slash to database for {:?}: {:?}",
+                    "Added new nomination to database for {:?}: {:?}",
                     context,
                     validator
                 );
                 count += 1;
-            });
+            }
+        }
+
+        let targets = validators
+            .iter()
+            .map(|v| v.data.stash_account_display.address.clone())
+            .collect();
+        // Doesn't fail the stored validators, the set is compared again on
+        // the next fetch.
+        if let Err(err) = self.store_nomination_set(context, targets).await {
+            warn!(
+                "Failed to store the nomination set of {:?}: {:?}",
+                context, err
+            );
         }
 
         Ok(count)
     }
-    pub fn reader(&self) -> DatabaseReader {
-        DatabaseReader {
-            db: self.db.clone(),
+    /// Stores the nominated validators of the account if they changed since
+    /// the latest stored set, so the history of the selection is kept.
+    /// Subscan does not report the era of the nominations, so a set is only
+    /// identified by its targets. Returns whether a set was inserted.
+    async fn store_nomination_set(
+        &self,
+        context: &Context,
+        mut targets: Vec<String>,
+    ) -> Result<bool> {
+        targets.sort();
+        targets.dedup();
+
+        let latest = self.nomination_sets(context).await?.into_iter().next();
+        if latest.map(|entry| entry.data.targets == targets) == Some(true) {
+            return Ok(false);
         }
-    }
-}
 
-#[derive(Clone)]
-// TODO: Rename
-pub struct DatabaseReader {
-    db: MongoDb,
-}
+        let entry: ContextData<NominationSet> = ContextData {
+            context_id: context.id(),
+            tags: Cow::Borrowed(&context.tags),
+            description: Cow::Borrowed(&context.description),
+            timestamp: Timestamp::now(),
+            data: Cow::Owned(NominationSet { targets: targets }),
+        };
 
-impl DatabaseReader {
-    #[cfg(test)]
-    pub async fn new(uri: &str, db: &str) -> Result<Self> {
-        Ok(DatabaseReader {
-            db: Client::with_uri_str(uri).await?.database(db),
-        })
+        self.db
+            .collection::<Document>(&self.colls.get(COLL_NOMINATION_SETS))
+            .insert_one(entry.to_document()?, None)
+            .await?;
+
+        trace!(
+            "Added new nomination set to database for {:?}: {:?}",
+            context,
+            entry
+        );
+
+        Ok(true)
     }
-    pub async fn fetch_transfers<'a>(
+    /// The stored nomination sets of the account, newest first.
+    pub async fn nomination_sets(
         &self,
-        contexts: &[Context],
-        from: Timestamp,
-        to: Timestamp,
-    ) -> Result<Vec<ContextData<'a, Transfer>>> {
-        let coll = self
+        context: &Context,
+    ) -> Result<Vec<ContextData<'static, NominationSet>>> {
+        let mut cursor = self
             .db
-            .collection::<ContextData<Transfer>>(COLL_TRANSFER_RAW);
+            .collection::<ContextData<NominationSet>>(&self.colls.get(COLL_NOMINATION_SETS))
+            .find(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                },
+                {
+                    let mut ops = FindOptions::default();
+                    ops.sort = Some(doc! {
+                        "timestamp": -1,
+                        "_id": -1,
+                    });
+                    Some(ops)
+                },
+            )
+            .await?;
 
-        let mut cursor = coll.aggregate(vec![
-            doc!{
-                "$match": {
-                    "context_id": {
-                        "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
-                    },
-                    "$and": [
-                        {
-                            "data.block_timestamp": {
-                                "$gte": from.to_bson()?
-                            }
-                        },
-                        {
-                            "data.block_timestamp": {
-                                "$lte": to.to_bson()?
-                            }
-                        }
-                    ]
-                }
-            },
-            doc! {
-                "$sort": {
-                    "data.block_num": -1
-                }
+        let mut sets = vec![];
+        while let Some(entry) = cursor.next().await {
+            sets.push(entry?);
+        }
+
+        Ok(sets)
+    }
+    pub async fn store_bond_events(
+        &self,
+        context: &Context,
+        data: &Response<BondsPage>,
+    ) -> Result<usize> {
+        // Add the full context to each entry, so the corresponding account
+        // can be identified.
+        let bonds: Vec<ContextData<BondEvent>> = data
+            .data
+            .list
+            .as_ref()
+            .ok_or(anyhow!("No bond events found in response body"))?
+            .iter()
+            .map(|b| ContextData {
+                context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
+                timestamp: Timestamp::now(),
+                data: Cow::Borrowed(b),
+            })
+            .collect();
+
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_BONDS_RAW);
+        let keys = bonds
+            .iter()
+            .map(|bond| bond.data.extrinsic_index.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.extrinsic_index", &keys)
+            .await?;
+
+        // Insert new entries. Return count of how many were newly inserted.
+        let mut count = 0;
+        for (bond, key) in bonds.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
             }
-        ], None).await?;
 
-        let mut transfers = vec![];
-        while let Some(doc) = cursor.next().await {
-            transfers.push(from_document(doc?)?);
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_index": key,
+            };
+
+            if self.insert_if_new(&coll, filter, bond).await? {
+                trace!(
+                    "Added new bond event to database for {:?}: {:?}",
+                    context,
+                    bond
+                );
+                count += 1;
+            }
         }
 
-        Ok(transfers)
+        Ok(count)
     }
-    pub async fn fetch_rewards_slashes<'a>(
+    /// Stores the extrinsics of the account which pass its call filter.
+    /// Extrinsics are unique per context and extrinsic index (see
+    /// `setup_indexes`). Returns how many were newly inserted.
+    pub async fn store_extrinsic_event(
         &self,
-        contexts: &[Context],
-        from: BlockNumber,
-        to: BlockNumber,
-    ) -> Result<Vec<ContextData<'a, RewardSlash>>> {
-        let coll = self
-            .db
-            .collection::<ContextData<RewardSlash>>(COLL_REWARD_SLASH_RAW);
+        context: &Context,
+        data: &Response<ExtrinsicsPage>,
+    ) -> Result<usize> {
+        // Add the full context to each entry, so the corresponding account
+        // can be identified.
+        let extrinsics: Vec<ContextData<Extrinsic>> = data
+            .data
+            .extrinsics
+            .as_ref()
+            .ok_or(anyhow!("No extrinsics found in response body"))?
+            .iter()
+            .filter(|e| context.calls.matches(&e.call()))
+            .map(|e| ContextData {
+                context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
+                timestamp: Timestamp::now(),
+                data: Cow::Borrowed(e),
+            })
+            .collect();
 
-        let mut cursor = coll.find(doc!{
-            "context_id": {
-                "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
-            },
-            "$and": [
-                {
-                    "data.block_num": {
-                        "$gte": from.to_bson()?
-                    }
-                },
-                {
-                    "data.block_num": {
-                        "$lte": to.to_bson()?
-                    }
-                }
-            ]
-        }, {
-            let mut ops = FindOptions::default();
-            ops.sort = Some(doc! {
-                "data.block_num": -1
-            });
-            Some(ops)
-        }).await?;
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_EXTRINSICS_RAW);
+        let keys = extrinsics
+            .iter()
+            .map(|extrinsic| extrinsic.data.extrinsic_index.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.extrinsic_index", &keys)
+            .await?;
 
-        let mut rewards_slashes = vec![];
-        while let Some(doc) = cursor.next().await {
-            rewards_slashes.push(doc?);
+        // Insert new entries. Return count of how many were newly inserted.
+        let mut count = 0;
+        for (extrinsic, key) in extrinsics.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
+            }
+
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_index": key,
+            };
+
+            if self.insert_if_new(&coll, filter, extrinsic).await? {
+                trace!(
+                    "Added new extrinsic to database for {:?}: {:?}",
+                    context,
+                    extrinsic
+                );
+                count += 1;
+            }
         }
 
-        Ok(rewards_slashes)
+        Ok(count)
     }
-    pub async fn fetch_nominations<'a>(
+    /// Stores the governance votes of the account. Unlike the other store
+    /// methods, the newly inserted votes are returned so they can be alerted
+    /// on.
+    pub async fn store_governance_votes(
         &self,
-        contexts: &[Context],
-    ) -> Result<Vec<ContextData<'a, Nomination>>> {
-        let coll = self
-            .db
-            .collection::<ContextData<Nomination>>(COLL_NOMINATIONS_RAW);
+        context: &Context,
+        data: &Response<GovernanceVotesPage>,
+    ) -> Result<Vec<GovernanceVote>> {
+        // Add the full context to each entry, so the corresponding account
+        // can be identified.
+        let votes: Vec<ContextData<GovernanceVote>> = data
+            .data
+            .list
+            .as_ref()
+            .ok_or(anyhow!("No governance votes found in response body"))?
+            .iter()
+            .map(|v| ContextData {
+                context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
+                timestamp: Timestamp::now(),
+                data: Cow::Borrowed(v),
+            })
+            .collect();
 
-        let mut cursor = coll.find(doc!{
-            "context_id": {
-                "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
-            },
-        }, None).await?;
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_GOVERNANCE_VOTES_RAW);
+        let keys = votes
+            .iter()
+            .map(|vote| vote.data.extrinsic_index.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.extrinsic_index", &keys)
+            .await?;
 
-        let mut validators = vec![];
-        while let Some(doc) = cursor.next().await {
-            validators.push(doc?);
+        // Insert new entries. Return the newly inserted ones.
+        let mut inserted = vec![];
+        for (vote, key) in votes.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
+            }
+
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_index": key,
+            };
+
+            if self.insert_if_new(&coll, filter, vote).await? {
+                trace!(
+                    "Added new governance vote to database for {:?}: {:?}",
+                    context,
+                    vote
+                );
+                inserted.push(vote.data.as_ref().clone());
+            }
         }
 
-        Ok(validators)
+        Ok(inserted)
     }
-}
+    /// Stores the governance proposals submitted by the account, i.e. the
+    /// extrinsics of the proposal call. Like the votes, the newly inserted
+    /// proposals are returned so they can be alerted on.
+    pub async fn store_governance_proposals(
+        &self,
+        context: &Context,
+        data: &Response<ExtrinsicsPage>,
+    ) -> Result<Vec<Extrinsic>> {
+        // Add the full context to each entry, so the corresponding account
+        // can be identified.
+        let proposals: Vec<ContextData<Extrinsic>> = data
+            .data
+            .extrinsics
+            .as_ref()
+            .ok_or(anyhow!("No governance proposals found in response body"))?
+            .iter()
+            .map(|e| ContextData {
+                context_id: context.id(),
+                tags: Cow::Borrowed(&context.tags),
+                description: Cow::Borrowed(&context.description),
+                timestamp: Timestamp::now(),
+                data: Cow::Borrowed(e),
+            })
+            .collect();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::chain_api::{Response, TransfersPage};
-    use crate::tests::db;
-    use crate::Context;
+        // The entries of the page which are already stored are skipped.
+        let coll = self.colls.get(COLL_GOVERNANCE_PROPOSALS_RAW);
+        let keys = proposals
+            .iter()
+            .map(|proposal| proposal.data.extrinsic_index.to_bson())
+            .collect::<Result<Vec<Bson>>>()?;
+        let known = self
+            .stored_keys(&coll, context, "data.extrinsic_index", &keys)
+            .await?;
 
-    #[tokio::test]
-    async fn store_transfer_event() {
-        let db = db().await;
+        // Insert new entries. Return the newly inserted ones.
+        let mut inserted = vec![];
+        for (proposal, key) in proposals.iter().zip(keys) {
+            if known.contains(&key) {
+                continue;
+            }
 
-        // Must now have an influence on data.
-        let alice = Context::alice();
-        let bob = Context::bob();
+            let filter = doc! {
+                "context_id": context.id().to_bson()?,
+                "data.extrinsic_index": key,
+            };
 
-        // Gen test data
-        let mut resp: Response<TransfersPage> = Default::default();
-        resp.data.transfers = Some(vec![Default::default(); 10]);
-        resp.data
-            .transfers
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .enumerate()
-            .for_each(|(idx, t)| t.extrinsic_index = idx.to_string().into());
+            if self.insert_if_new(&coll, filter, proposal).await? {
+                trace!(
+                    "Added new governance proposal to database for {:?}: {:?}",
+                    context,
+                    proposal
+                );
+                inserted.push(proposal.data.as_ref().clone());
+            }
+        }
 
-        // New data is inserted
-        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        Ok(inserted)
+    }
+    /// Replaces the stored registrar judgements of the account. Returns the
+    /// changes compared to the previously stored judgements.
+    pub async fn store_judgements(
+        &self,
+        context: &Context,
+        judgements: &[Judgement],
+    ) -> Result<Vec<JudgementChange>> {
+        let coll = self
+            .db
+            .collection::<ContextData<Vec<Judgement>>>(&self.colls.get(COLL_JUDGEMENTS));
 
-        // No new data is inserted
-        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 0);
+        let previous = coll
+            .find_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                },
+                None,
+            )
+            .await?
+            .map(|entry| entry.data.into_owned())
+            .unwrap_or_default();
 
-        // Gen new test data
-        let mut new_resp: Response<TransfersPage> = Default::default();
-        new_resp.data.transfers = Some(vec![Default::default(); 15]);
-        new_resp
-            .data
-            .transfers
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .enumerate()
-            .for_each(|(idx, t)| t.extrinsic_index = (idx + 10).to_string().into());
+        let entry: ContextData<Vec<Judgement>> = ContextData {
+            context_id: context.id(),
+            tags: Cow::Borrowed(&context.tags),
+            description: Cow::Borrowed(&context.description),
+            timestamp: Timestamp::now(),
+            data: Cow::Owned(judgements.to_vec()),
+        };
 
-        // New data is inserted
-        let count = db.store_transfer_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 15);
+        coll.update_one(
+            doc! {
+                "context_id": context.id().to_bson()?,
+            },
+            doc! {
+                "$set": entry.to_bson()?,
+            },
+            {
+                let mut opt = UpdateOptions::default();
+                opt.upsert = Some(true);
+                Some(opt)
+            },
+        )
+        .await?;
+
+        Ok(JudgementChange::diff(&previous, judgements))
+    }
+    /// Stores the identity of the account, replacing the previous one.
+    /// Returns 1 if the identity changed (or is new), 0 otherwise.
+    pub async fn store_identity(&self, context: &Context, identity: &Identity) -> Result<usize> {
+        let coll = self
+            .db
+            .collection::<ContextData<Identity>>(&self.colls.get(COLL_IDENTITIES));
+
+        let filter = doc! {
+            "context_id": context.id().to_bson()?,
+        };
+
+        let previous = coll.find_one(filter.clone(), None).await?;
+        if previous.map(|entry| entry.data.as_ref() == identity) == Some(true) {
+            return Ok(0);
+        }
+
+        let entry = ContextData {
+            context_id: context.id(),
+            tags: Cow::Borrowed(&context.tags),
+            description: Cow::Borrowed(&context.description),
+            timestamp: Timestamp::now(),
+            data: Cow::Borrowed(identity),
+        };
+
+        coll.update_one(
+            filter,
+            doc! {
+                "$set": entry.to_bson()?,
+            },
+            {
+                let mut opt = UpdateOptions::default();
+                opt.upsert = Some(true);
+                Some(opt)
+            },
+        )
+        .await?;
+
+        Ok(1)
+    }
+    /// Stores the balance snapshot of the account for the given (rounded)
+    /// time. A snapshot already stored for that time is replaced, so
+    /// repeated passes don't add more entries. Returns 1 if the snapshot is
+    /// new, 0 otherwise.
+    pub async fn store_balance_snapshot(
+        &self,
+        context: &Context,
+        timestamp: Timestamp,
+        balance: &AccountBalance,
+    ) -> Result<usize> {
+        let entry = ContextData {
+            context_id: context.id(),
+            tags: Cow::Borrowed(&context.tags),
+            description: Cow::Borrowed(&context.description),
+            timestamp: timestamp,
+            data: Cow::Borrowed(balance),
+        };
+
+        let res = self
+            .db
+            .collection::<Document>(&self.colls.get(COLL_BALANCES))
+            .update_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "timestamp": timestamp.to_bson()?,
+                },
+                doc! {
+                    "$set": entry.to_bson()?,
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await?;
+
+        Ok(if res.upserted_id.is_some() { 1 } else { 0 })
+    }
+    /// The page to continue fetching from, if paging through the entries of
+    /// the context was interrupted.
+    pub async fn load_cursor(&self, context: &Context, module: &str) -> Result<Option<usize>> {
+        let cursor = self
+            .db
+            .collection::<Document>(&self.colls.get(COLL_CURSORS))
+            .find_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "module": module,
+                },
+                None,
+            )
+            .await?;
+
+        match cursor {
+            Some(cursor) => Ok(Some(cursor.get_i64("page")? as usize)),
+            None => Ok(None),
+        }
+    }
+    pub async fn save_cursor(&self, context: &Context, module: &str, page: usize) -> Result<()> {
+        self.db
+            .collection::<Document>(&self.colls.get(COLL_CURSORS))
+            .update_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "module": module,
+                },
+                doc! {
+                    "$set": {
+                        "page": page as i64,
+                    }
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Removes the cursor once all pages have been processed.
+    pub async fn clear_cursor(&self, context: &Context, module: &str) -> Result<()> {
+        self.db
+            .collection::<Document>(&self.colls.get(COLL_CURSORS))
+            .delete_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "module": module,
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Whether the full history of the context has already been fetched by
+    /// the module, see `Context::backfill`.
+    pub async fn is_backfilled(&self, context: &Context, module: &str) -> Result<bool> {
+        let backfill = self
+            .db
+            .collection::<Document>(&self.colls.get(COLL_BACKFILLS))
+            .find_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "module": module,
+                },
+                None,
+            )
+            .await?;
+
+        Ok(backfill.is_some())
+    }
+    pub async fn mark_backfilled(&self, context: &Context, module: &str) -> Result<()> {
+        self.db
+            .collection::<Document>(&self.colls.get(COLL_BACKFILLS))
+            .update_one(
+                doc! {
+                    "context_id": context.id().to_bson()?,
+                    "module": module,
+                },
+                doc! {
+                    "$set": {
+                        "completed": Timestamp::now().to_bson()?,
+                    }
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+    /// Returns the stored transfers of the context within the given block
+    /// time range (inclusive), newest first. The read counterpart of
+    /// `store_transfer_event`.
+    pub async fn get_transfers(
+        &self,
+        id: ContextId<'_>,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<Transfer>> {
+        let filter = transfers_filter(&[id], from, to)?;
+
+        Ok(self
+            .reader()
+            .find_transfers(filter, from, to)
+            .await?
+            .into_iter()
+            .map(|entry| entry.data.into_owned())
+            .collect())
+    }
+    /// Counts the transfers of each context within the given block time range
+    /// (inclusive) and sums up their amounts and the fees of the outgoing
+    /// ones, without loading the entries. Transfers without a fee are
+    /// ignored for the latter. The totals are summed up as Decimal128 and
+    /// returned as integer parts, so no precision is lost for totals of up to
+    /// 34 digits. Contexts without transfers are included with zero totals.
+    pub async fn aggregate_transfer_totals(
+        &self,
+        contexts: &[Context],
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<ContextTotals>> {
+        let ids: Vec<ContextId> = contexts.iter().map(|c| c.id()).collect();
+        let pipeline = vec![
+            doc! {
+                "$match": transfers_filter(&ids, from, to)?,
+            },
+            doc! {
+                "$group": {
+                    "_id": "$context_id",
+                    "count": { "$sum": 1 },
+                    "amount": { "$sum": { "$toDecimal": "$data.amount" } },
+                    // The fees are stored in plancks. Missing fees are null,
+                    // which is ignored by `$sum`.
+                    "fees": {
+                        "$sum": {
+                            "$cond": [
+                                { "$eq": ["$data.from", "$context_id.stash"] },
+                                { "$toDecimal": "$data.fee" },
+                                null,
+                            ]
+                        }
+                    },
+                }
+            },
+            doc! {
+                "$project": {
+                    "count": 1,
+                    "amount": total_parts("amount"),
+                    "fees": total_parts("fees"),
+                }
+            },
+        ];
+
+        let mut totals: Vec<ContextTotals> = contexts
+            .iter()
+            .map(|context| ContextTotals {
+                context_id: ContextId {
+                    stash: Cow::Owned(context.stash.clone()),
+                    network: context.network,
+                },
+                count: 0,
+                amount: Balance::default(),
+                fees: Balance::default(),
+            })
+            .collect();
+
+        // Time buckets are aggregated separately, then summed up.
+        for coll in &self.reader().transfer_collections(from, to).await? {
+            let mut cursor = self
+                .db
+                .collection::<Document>(coll)
+                .aggregate(pipeline.clone(), None)
+                .await?;
+
+            while let Some(doc) = cursor.next().await {
+                let doc = doc?;
+                let id: ContextId = bson::from_bson(
+                    doc.get("_id")
+                        .cloned()
+                        .ok_or(anyhow!("missing context id in aggregation"))?,
+                )?;
+                let count = match doc.get("count") {
+                    Some(Bson::Int32(count)) => *count as u64,
+                    Some(Bson::Int64(count)) => *count as u64,
+                    other => return Err(anyhow!("invalid transfer count: {:?}", other)),
+                };
+                let amount = total_from_parts(doc.get_document("amount")?, id.network.decimals())?;
+                let fees = total_from_parts(doc.get_document("fees")?, 0)?;
+
+                if let Some(entry) = totals.iter_mut().find(|entry| entry.context_id == id) {
+                    entry.count += count;
+                    entry.amount = entry.amount.checked_add(amount)?;
+                    entry.fees = entry.fees.checked_add(fees)?;
+                }
+            }
+        }
+
+        Ok(totals)
+    }
+    pub fn reader(&self) -> DatabaseReader {
+        DatabaseReader {
+            db: self.db.clone(),
+            colls: self.colls.clone(),
+            max_documents: None,
+            time_buckets: self.time_buckets,
+        }
+    }
+}
+
+#[derive(Clone)]
+// TODO: Rename
+pub struct DatabaseReader {
+    db: MongoDb,
+    colls: CollectionNames,
+    max_documents: Option<u64>,
+    time_buckets: bool,
+}
+
+impl DatabaseReader {
+    #[cfg(test)]
+    pub async fn new(uri: &str, db: &str) -> Result<Self> {
+        Ok(DatabaseReader {
+            db: Client::with_uri_str(uri).await?.database(db),
+            colls: CollectionNames::default(),
+            max_documents: None,
+            time_buckets: false,
+        })
+    }
+    /// Limits how many documents the `fetch_*` methods load into memory.
+    /// Queries exceeding it fail instead of loading everything.
+    pub fn with_max_documents(mut self, max: Option<u64>) -> Self {
+        self.max_documents = max;
+        self
+    }
+    async fn check_result_size<S: AsRef<str>>(&self, colls: &[S], filter: &Document) -> Result<()> {
+        let max = match self.max_documents {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+
+        let mut count = 0;
+        for coll in colls {
+            count += self
+                .db
+                .collection::<Document>(coll.as_ref())
+                .count_documents(filter.clone(), {
+                    // No need to count further than the limit.
+                    let mut opt = CountOptions::default();
+                    opt.limit = Some(max + 1 - count);
+                    Some(opt)
+                })
+                .await?;
+
+            if count > max {
+                return Err(anyhow!(
+                    "range too large: more than {} entries would be loaded, \
+                     please use a smaller range",
+                    max
+                ));
+            }
+        }
+
+        Ok(())
+    }
+    /// Collections holding the transfers within the given time range, newest
+    /// first. Without time buckets, that's always the single raw collection.
+    async fn transfer_collections(&self, from: Timestamp, to: Timestamp) -> Result<Vec<String>> {
+        if !self.time_buckets {
+            return Ok(vec![self.colls.get(COLL_TRANSFER_RAW)]);
+        }
+
+        let first = match self.colls.transfer_bucket(from) {
+            Some(first) => first,
+            None => return Ok(vec![]),
+        };
+        // Unbounded if the end is out of range.
+        let last = self.colls.transfer_bucket(to);
+
+        let mut colls: Vec<String> = self
+            .db
+            .list_collection_names(doc! {
+                "name": {
+                    "$regex": self.colls.transfer_bucket_pattern(),
+                }
+            })
+            .await?
+            .into_iter()
+            .filter(|name| *name >= first && last.as_ref().map(|l| name <= l).unwrap_or(true))
+            .collect();
+
+        colls.sort_by(|a, b| b.cmp(a));
+        Ok(colls)
+    }
+    /// Fetches the transfers matching the filter from all collections covering
+    /// the time range, sorted by block number (descending).
+    async fn find_transfers<'a>(
+        &self,
+        filter: Document,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<ContextData<'a, Transfer>>> {
+        let colls = self.transfer_collections(from, to).await?;
+        self.check_result_size(&colls, &filter).await?;
+
+        let mut transfers = vec![];
+        for coll in &colls {
+            let mut cursor = self
+                .db
+                .collection::<ContextData<Transfer>>(coll)
+                .find(filter.clone(), {
+                    let mut ops = FindOptions::default();
+                    ops.sort = Some(doc! {
+                        "data.block_num": -1
+                    });
+                    Some(ops)
+                })
+                .await?;
+
+            while let Some(doc) = cursor.next().await {
+                transfers.push(doc?);
+            }
+        }
+
+        if colls.len() > 1 {
+            // Stable, so the order within a bucket is kept.
+            transfers.sort_by(|a, b| b.data.block_num.cmp(&a.data.block_num));
+        }
+
+        Ok(transfers)
+    }
+    /// Fetches the stored transfers of the contexts (stash and network)
+    /// whose block time lies within the given range, both ends inclusive.
+    pub async fn fetch_transfers<'a>(
+        &self,
+        contexts: &[Context],
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<ContextData<'a, Transfer>>> {
+        let ids: Vec<ContextId> = contexts.iter().map(|c| c.id()).collect();
+        self.find_transfers(transfers_filter(&ids, from, to)?, from, to)
+            .await
+    }
+    /// Exports the stored transfers of the contexts within the given time
+    /// range as CSV. Without any transfers, only the header is written.
+    pub async fn export_transfers(
+        &self,
+        contexts: &[Context],
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<u8>> {
+        let transfers = self.fetch_transfers(contexts, from, to).await?;
+        transfers_csv(contexts, &transfers)
+    }
+    /// Writes the stored entries of the given module as newline-delimited
+    /// JSON, in insertion order, as they are read from the database. Only
+    /// entries stored after the passed cursor are exported. Returns the
+    /// cursor to resume from.
+    ///
+    /// The ids are generated before the entries are stored, so with
+    /// concurrent stores or multiple replicas an entry might become visible
+    /// after entries with a greater id. The ids of the last `EXPORT_OVERLAP`
+    /// seconds are therefore read again, skipping the entries exported
+    /// before. Entries which take longer to become visible, e.g. because of
+    /// clock skew between replicas, are missed.
+    ///
+    /// Judgements, identities and balance snapshots are replaced in place, so
+    /// the cursor would miss their updates. They are always exported in full.
+    pub async fn export_ndjson<W: Write>(
+        &self,
+        module: &ScrapingModule,
+        contexts: &[Context],
+        since: ExportCursor,
+        mut writer: W,
+    ) -> Result<ExportCursor> {
+        let colls = match module {
+            ScrapingModule::Transfer => {
+                self.transfer_collections(Timestamp::from(0), Timestamp::from(u64::MAX))
+                    .await?
+            }
+            ScrapingModule::RewardsSlashes => vec![self.colls.get(COLL_REWARD_SLASH_RAW)],
+            ScrapingModule::Nominations => vec![self.colls.get(COLL_NOMINATIONS_RAW)],
+            ScrapingModule::Governance => vec![
+                self.colls.get(COLL_GOVERNANCE_VOTES_RAW),
+                self.colls.get(COLL_GOVERNANCE_PROPOSALS_RAW),
+            ],
+            ScrapingModule::Bonds => vec![self.colls.get(COLL_BONDS_RAW)],
+            ScrapingModule::Extrinsics { .. } => vec![self.colls.get(COLL_EXTRINSICS_RAW)],
+            ScrapingModule::Judgements => vec![self.colls.get(COLL_JUDGEMENTS)],
+            ScrapingModule::Balance => vec![self.colls.get(COLL_BALANCES)],
+            ScrapingModule::Identity => vec![self.colls.get(COLL_IDENTITIES)],
+        };
+
+        let upserted = matches!(
+            module,
+            ScrapingModule::Judgements | ScrapingModule::Balance | ScrapingModule::Identity
+        );
+
+        let mut filter = doc! {
+            "context_id": {
+                "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
+            },
+        };
+        if let (Some(last_id), false) = (since.last_id, upserted) {
+            filter.insert("_id", doc! { "$gte": export_overlap_start(last_id) });
+        }
+
+        // Each collection is read in insertion order, along with its next
+        // document.
+        let mut cursors = vec![];
+        for coll in &colls {
+            let mut cursor = self
+                .db
+                .collection::<Document>(coll)
+                .find(filter.clone(), {
+                    let mut ops = FindOptions::default();
+                    ops.sort = Some(doc! {
+                        "_id": 1
+                    });
+                    Some(ops)
+                })
+                .await?;
+
+            let next = cursor.next().await.transpose()?;
+            cursors.push((cursor, next));
+        }
+
+        let mut last_id = since.last_id;
+        let mut exported: HashSet<ObjectId> = since.recent.into_iter().collect();
+        loop {
+            // Time buckets are not filled in insertion order, so the oldest of
+            // the next documents of all collections is written first.
+            let oldest = cursors
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, (_, next))| next.as_ref().map(|doc| (idx, doc)))
+                .min_by_key(|(_, doc)| doc.get_object_id("_id").ok())
+                .map(|(idx, _)| idx);
+
+            let (cursor, next) = match oldest {
+                Some(idx) => &mut cursors[idx],
+                None => break,
+            };
+            let following = cursor.next().await.transpose()?;
+            let mut doc = match std::mem::replace(next, following) {
+                Some(doc) => doc,
+                None => break,
+            };
+
+            if !upserted {
+                let id = doc.get_object_id("_id")?;
+                if !exported.insert(id) {
+                    continue;
+                }
+
+                last_id = last_id.max(Some(id));
+            }
+            doc.remove("_id");
+
+            serde_json::to_writer(&mut writer, &Bson::Document(doc).into_relaxed_extjson())?;
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()?;
+
+        let mut recent: Vec<ObjectId> = match last_id {
+            Some(last_id) => exported
+                .into_iter()
+                .filter(|id| *id >= export_overlap_start(last_id))
+                .collect(),
+            None => vec![],
+        };
+        recent.sort();
+
+        Ok(ExportCursor {
+            last_id: last_id,
+            recent: recent,
+        })
+    }
+    /// Fetches the transfers of all contexts with the given tag, as stored
+    /// with each entry.
+    pub async fn fetch_transfers_by_tag<'a>(
+        &self,
+        tag: &str,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<ContextData<'a, Transfer>>> {
+        let filter = doc! {
+            "tags": tag,
+            "$and": [
+                {
+                    "block_timestamp": {
+                        "$gte": from.to_bson()?
+                    }
+                },
+                {
+                    "block_timestamp": {
+                        "$lte": to.to_bson()?
+                    }
+                }
+            ]
+        };
+
+        self.find_transfers(filter, from, to).await
+    }
+    /// Looks up the contexts whose description contains the given value,
+    /// ignoring case. The description is the one stored with each entry, so
+    /// only contexts with stored entries are found.
+    pub async fn find_contexts_by_description(
+        &self,
+        substring: &str,
+    ) -> Result<Vec<ContextId<'static>>> {
+        let mut colls: Vec<String> = CONTEXT_COLLECTIONS
+            .iter()
+            .map(|coll| self.colls.get(coll))
+            .collect();
+        colls.extend(
+            self.db
+                .list_collection_names(doc! {
+                    "name": {
+                        "$regex": self.colls.transfer_bucket_pattern(),
+                    }
+                })
+                .await?,
+        );
+
+        let filter = doc! {
+            "description": {
+                "$regex": escape_regex(substring),
+                "$options": "i",
+            }
+        };
+
+        let mut ids: Vec<ContextId<'static>> = vec![];
+        for coll in &colls {
+            let found = self
+                .db
+                .collection::<Document>(coll)
+                .distinct("context_id", filter.clone(), None)
+                .await?;
+
+            for id in found {
+                let id: ContextId<'static> = bson::from_bson(id)?;
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        ids.sort_by(|a, b| a.stash.cmp(&b.stash));
+        Ok(ids)
+    }
+    /// Whether the event with the given extrinsic hash is already stored for
+    /// the context by the given module. Only the transfers, rewards/slashes
+    /// and extrinsics carry the hash. Counts at most one document, so it's
+    /// cheaper than fetching the entry.
+    pub async fn exists(
+        &self,
+        module: &ScrapingModule,
+        context: &Context,
+        extrinsic_hash: &ExtrinsicHash,
+    ) -> Result<bool> {
+        let (colls, key) = match module {
+            ScrapingModule::Transfer => (
+                self.transfer_collections(Timestamp::from(0), Timestamp::from(u64::MAX))
+                    .await?,
+                "data.hash",
+            ),
+            ScrapingModule::RewardsSlashes => (
+                vec![self.colls.get(COLL_REWARD_SLASH_RAW)],
+                "data.extrinsic_hash",
+            ),
+            ScrapingModule::Extrinsics { .. } => (
+                vec![self.colls.get(COLL_EXTRINSICS_RAW)],
+                "data.extrinsic_hash",
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "module {:?} does not store extrinsic hashes",
+                    module
+                ))
+            }
+        };
+
+        let mut filter = doc! {
+            "context_id": context.id().to_bson()?,
+        };
+        filter.insert(key, extrinsic_hash.as_str());
+
+        for coll in &colls {
+            let count = self
+                .db
+                .collection::<Document>(coll)
+                .count_documents(filter.clone(), {
+                    let mut opt = CountOptions::default();
+                    opt.limit = Some(1);
+                    Some(opt)
+                })
+                .await?;
+
+            if count > 0 {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+    pub async fn fetch_rewards_slashes<'a>(
+        &self,
+        contexts: &[Context],
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<ContextData<'a, RewardSlash>>> {
+        let filter = doc! {
+            "context_id": {
+                "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
+            },
+            "$and": [
+                {
+                    "data.block_num": {
+                        "$gte": from.to_bson()?
+                    }
+                },
+                {
+                    "data.block_num": {
+                        "$lte": to.to_bson()?
+                    }
+                }
+            ]
+        };
+
+        self.find_rewards_slashes(filter).await
+    }
+    /// Like `fetch_rewards_slashes`, but within the given time range, analogous
+    /// to `fetch_transfers`. Entries stored without a block timestamp are not
+    /// included.
+    pub async fn fetch_rewards_slashes_by_timestamp<'a>(
+        &self,
+        contexts: &[Context],
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<Vec<ContextData<'a, RewardSlash>>> {
+        let ids: Vec<ContextId> = contexts.iter().map(|c| c.id()).collect();
+        let filter = doc! {
+            "context_id": {
+                "$in": ids.to_bson()?,
+            },
+            "block_timestamp": {
+                "$gt": 0,
+                "$gte": from.to_bson()?,
+                "$lte": to.to_bson()?,
+            }
+        };
+
+        self.find_rewards_slashes(filter).await
+    }
+    /// Fetches the rewards/slashes matching the filter, sorted by block number
+    /// (descending).
+    async fn find_rewards_slashes<'a>(
+        &self,
+        filter: Document,
+    ) -> Result<Vec<ContextData<'a, RewardSlash>>> {
+        let coll = self
+            .db
+            .collection::<ContextData<RewardSlash>>(&self.colls.get(COLL_REWARD_SLASH_RAW));
+
+        self.check_result_size(&[self.colls.get(COLL_REWARD_SLASH_RAW)], &filter)
+            .await?;
+
+        let mut cursor = coll
+            .find(filter, {
+                let mut ops = FindOptions::default();
+                ops.sort = Some(doc! {
+                    "data.block_num": -1
+                });
+                Some(ops)
+            })
+            .await?;
+
+        let mut rewards_slashes = vec![];
+        while let Some(doc) = cursor.next().await {
+            rewards_slashes.push(doc?);
+        }
+
+        Ok(rewards_slashes)
+    }
+    pub async fn fetch_nominations<'a>(
+        &self,
+        contexts: &[Context],
+    ) -> Result<Vec<ContextData<'a, Nomination>>> {
+        let coll = self
+            .db
+            .collection::<ContextData<Nomination>>(&self.colls.get(COLL_NOMINATIONS_RAW));
+
+        let filter = doc! {
+            "context_id": {
+                "$in": contexts.iter().map(|c| c.id()).collect::<Vec<ContextId>>().to_bson()?,
+            },
+        };
+
+        self.check_result_size(&[self.colls.get(COLL_NOMINATIONS_RAW)], &filter)
+            .await?;
+
+        let mut cursor = coll.find(filter, None).await?;
+
+        let mut validators = vec![];
+        while let Some(doc) = cursor.next().await {
+            validators.push(doc?);
+        }
+
+        Ok(validators)
+    }
+}
+
+fn transfers_csv(contexts: &[Context], transfers: &[ContextData<Transfer>]) -> Result<Vec<u8>> {
+    let mut csv = String::from("stash,network,description,block,timestamp,from,to,amount,hash\n");
+    for entry in transfers {
+        let context = contexts
+            .iter()
+            .find(|c| c.id() == entry.context_id)
+            .ok_or(anyhow!("No context found for exported transfer"))?;
+
+        let transfer = entry.data.as_ref();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            context.stash,
+            context.network.as_str(),
+            csv_escape(&context.description),
+            transfer.block_num,
+            transfer.block_timestamp,
+            transfer.from,
+            transfer.to,
+            transfer.amount,
+            transfer.hash,
+        ));
+    }
+
+    Ok(csv.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_api::{BondKind, Response, TransfersPage};
+    use crate::tests::db;
+    use crate::{CallFilter, Context, TransferDirection};
+    use rand::{thread_rng, Rng};
+
+    /// Generates a valid extrinsic hash from the index.
+    fn hash(idx: usize) -> String {
+        format!("0x{:064x}", idx)
+    }
+
+    #[tokio::test]
+    async fn concurrent_index_setup() {
+        let name = format!(
+            "monitoring_test_{}",
+            thread_rng().gen_range(u32::MIN..u32::MAX)
+        );
+
+        let (first, second) = tokio::join!(
+            Database::new("mongodb://localhost:27017/", &name),
+            Database::new("mongodb://localhost:27017/", &name),
+        );
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+
+        // The lock was released.
+        let db = Database::new("mongodb://localhost:27017/", &name).await;
+        assert!(db.is_ok());
+    }
+
+    #[test]
+    fn connect_backoff() {
+        let delays: Vec<u64> = (1..=7).map(|n| connect_retry_delay(n).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(connect_retry_delay(u32::MAX).as_secs(), 30);
+    }
+
+    #[test]
+    fn transfer_total_parts() {
+        let parts = |int_high: i64, int_low: i64, frac: i64| {
+            doc! {
+                "int_high": int_high,
+                "int_low": int_low,
+                "frac": frac,
+            }
+        };
+
+        // 1_200_000_000_000_000_000_001.5 tokens.
+        let doc = parts(1_200, 1, 500_000_000_000_000_000);
+        assert_eq!(
+            total_from_parts(&doc, 10).unwrap(),
+            Balance::parse_tokens("1200000000000000000001.5", 10).unwrap()
+        );
+        assert_eq!(
+            total_from_parts(&doc, 12).unwrap(),
+            Balance::parse_tokens("1200000000000000000001.5", 12).unwrap()
+        );
+
+        // Below a planck.
+        assert!(total_from_parts(&parts(0, 1, 1), 10).is_err());
+        // Negative or overflowing.
+        assert!(total_from_parts(&parts(0, -1, 0), 10).is_err());
+        assert!(total_from_parts(&parts(i64::MAX, 0, 0), 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn connect_unreachable() {
+        let start = std::time::Instant::now();
+        let err = Database::connect(
+            "mongodb://localhost:1/?serverSelectionTimeoutMS=100",
+            "monitoring_test",
+            CollectionNames::default(),
+            2,
+        )
+        .await
+        .err()
+        .unwrap();
+
+        // Retried once, after a delay.
+        assert!(err.to_string().contains("after 2 attempt(s)"));
+        assert!(start.elapsed() >= std::time::Duration::from_secs(CONNECT_RETRY_DELAY));
+    }
+
+    #[test]
+    fn collection_names() {
+        let colls = CollectionNames::default();
+        assert_eq!(colls.get(COLL_TRANSFER_RAW), "raw_transfers");
+
+        let colls = CollectionNames::with_prefix("staging_").unwrap();
+        assert_eq!(colls.get(COLL_TRANSFER_RAW), "staging_raw_transfers");
+        assert_eq!(
+            colls
+                .transfer_bucket(Timestamp::from(1_704_067_200))
+                .unwrap(),
+            "staging_raw_transfers_2024_01"
+        );
+        assert_eq!(
+            colls.transfer_bucket_pattern(),
+            "^staging_raw_transfers_[0-9]{4}_[0-9]{2}$"
+        );
+
+        assert!(CollectionNames::with_prefix("staging.").is_err());
+        assert!(CollectionNames::with_prefix("a$b").is_err());
+    }
+
+    #[tokio::test]
+    async fn collection_prefix() {
+        let name = format!(
+            "monitoring_test_{}",
+            thread_rng().gen_range(u32::MIN..u32::MAX)
+        );
+        let alice = Context::alice();
+
+        let staging = Database::connect(
+            "mongodb://localhost:27017/",
+            &name,
+            CollectionNames::with_prefix("staging_").unwrap(),
+            1,
+        )
+        .await
+        .unwrap();
+        let production = Database::new("mongodb://localhost:27017/", &name)
+            .await
+            .unwrap();
+
+        // Both instances share the database, but not the collections.
+        staging
+            .save_cursor(&alice, "TransferFetcher", 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            production
+                .load_cursor(&alice, "TransferFetcher")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            staging
+                .load_cursor(&alice, "TransferFetcher")
+                .await
+                .unwrap(),
+            Some(3)
+        );
+    }
+
+    /// Returns the `expireAfterSeconds` of the TTL index, if it exists.
+    async fn ttl_index(db: &Database, coll: &str) -> Option<i64> {
+        let res = db
+            .db
+            .run_command(doc! { "listIndexes": coll }, None)
+            .await
+            .unwrap();
+
+        res.get_document("cursor")
+            .unwrap()
+            .get_array("firstBatch")
+            .unwrap()
+            .iter()
+            .filter_map(|index| index.as_document())
+            .find(|index| index.get_str("name").ok() == Some(TTL_INDEX))
+            .map(|index| match index.get("expireAfterSeconds") {
+                Some(Bson::Int32(secs)) => *secs as i64,
+                Some(Bson::Int64(secs)) => *secs,
+                other => panic!("unexpected value: {:?}", other),
+            })
+    }
+
+    #[tokio::test]
+    async fn retention() {
+        let db = db().await.with_retention(Some(30));
+        let alice = Context::alice();
+
+        let transfer: ContextData<Transfer> = ContextData {
+            context_id: alice.id(),
+            tags: Cow::Owned(vec![]),
+            description: Cow::Owned(String::new()),
+            timestamp: Timestamp::from(0),
+            data: Cow::Owned(Transfer {
+                extrinsic_index: "1-1".to_string().into(),
+                ..Default::default()
+            }),
+        };
+        let filter = doc! {
+            "context_id": alice.id().to_bson().unwrap(),
+            "data.extrinsic_index": "1-1",
+        };
+        db.insert_if_new(COLL_TRANSFER_RAW, filter.clone(), &transfer)
+            .await
+            .unwrap();
+
+        // Entries carry the date the TTL index is based on.
+        let stored = db
+            .db
+            .collection::<Document>(COLL_TRANSFER_RAW)
+            .find_one(filter, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(stored.get("stored_at"), Some(Bson::DateTime(_))));
+
+        db.setup_retention().await.unwrap();
+        assert_eq!(ttl_index(&db, COLL_TRANSFER_RAW).await, Some(30 * 86400));
+        assert_eq!(ttl_index(&db, COLL_BONDS_RAW).await, Some(30 * 86400));
+        assert_eq!(ttl_index(&db, COLL_NOMINATIONS_RAW).await, None);
+
+        // Changing the retention updates the index.
+        let db = db.with_retention(Some(60));
+        db.setup_retention().await.unwrap();
+        assert_eq!(ttl_index(&db, COLL_TRANSFER_RAW).await, Some(60 * 86400));
+
+        // Disabling it removes the index.
+        let db = db.with_retention(None);
+        db.setup_retention().await.unwrap();
+        assert_eq!(ttl_index(&db, COLL_TRANSFER_RAW).await, None);
+        // Nothing to remove anymore.
+        db.setup_retention().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn store_transfer_event() {
+        let db = db().await;
+
+        // Must now have an influence on data.
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Gen test data
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| t.extrinsic_index = idx.to_string().into());
+
+        // New data is inserted
+        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 10);
+
+        // No new data is inserted
+        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Gen new test data
+        let mut new_resp: Response<TransfersPage> = Default::default();
+        new_resp.data.transfers = Some(vec![Default::default(); 15]);
+        new_resp
+            .data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| t.extrinsic_index = (idx + 10).to_string().into());
+
+        // New data is inserted
+        let count = db.store_transfer_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 15);
+
+        // No new data is inserted
+        let count = db.store_transfer_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Insert previous data (under a new context)
+        let count = db.store_transfer_event(&bob, &resp).await.unwrap();
+        assert_eq!(count, 10);
+    }
+
+    #[tokio::test]
+    async fn store_transfer_event_direction() {
+        let db = db().await;
+
+        let mut alice = Context::alice();
+        alice.transfer_direction = TransferDirection::Sent;
+
+        // Gen test data, alice sends every second transfer.
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| {
+                t.extrinsic_index = idx.to_string().into();
+                if idx % 2 == 0 {
+                    t.from = alice.stash.clone();
+                } else {
+                    t.to = alice.stash.clone();
+                }
+            });
+
+        // Only outgoing transfers are stored.
+        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 5);
+    }
+
+    #[tokio::test]
+    async fn store_reward_slash_event_call_filter() {
+        let db = db().await;
+
+        let mut alice = Context::alice();
+        alice.calls = CallFilter {
+            include: vec!["staking.*".to_string()],
+            exclude: vec!["staking.Slash".to_string()],
+        };
+
+        let calls = [
+            ("staking", "Reward"),
+            ("staking", "Slash"),
+            ("staking", "Reward"),
+            ("balances", "Deposit"),
+        ];
+
+        let mut resp: Response<RewardsSlashesPage> = Default::default();
+        resp.data.list = Some(
+            calls
+                .iter()
+                .enumerate()
+                .map(|(idx, (module, event))| RewardSlash {
+                    module_id: module.to_string(),
+                    event_id: event.to_string(),
+                    extrinsic_hash: hash(idx).into(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+
+        // Only the allowlisted rewards are stored.
+        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn store_reward_slash_event() {
+        let db = db().await;
+
+        // Must now have an influence on data.
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Gen test data
+        let mut resp: Response<RewardsSlashesPage> = Default::default();
+        resp.data.list = Some(vec![Default::default(); 10]);
+        resp.data
+            .list
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, e)| e.extrinsic_hash = hash(idx).into());
+
+        // New data is inserted
+        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 10);
+
+        // No new data is inserted
+        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Gen new test data
+        let mut new_resp: Response<RewardsSlashesPage> = Default::default();
+        new_resp.data.list = Some(vec![Default::default(); 15]);
+        new_resp
+            .data
+            .list
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, e)| e.extrinsic_hash = hash(idx + 10).into());
+
+        // New data is inserted
+        let count = db.store_reward_slash_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 15);
+
+        // No new data is inserted
+        let count = db.store_reward_slash_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Insert previous data (under a new context)
+        let count = db.store_reward_slash_event(&bob, &resp).await.unwrap();
+        assert_eq!(count, 10);
+    }
+
+    #[tokio::test]
+    async fn insert_if_new_duplicate_key() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let transfer: ContextData<Transfer> = ContextData {
+            context_id: alice.id(),
+            tags: Cow::Owned(vec![]),
+            description: Cow::Owned(String::new()),
+            timestamp: Timestamp::from(0),
+            data: Cow::Owned(Transfer {
+                extrinsic_index: "1-1".to_string().into(),
+                ..Default::default()
+            }),
+        };
+        let filter = doc! {
+            "context_id": alice.id().to_bson().unwrap(),
+            "data.extrinsic_index": "1-1",
+        };
+
+        assert!(db
+            .insert_if_new(COLL_TRANSFER_RAW, filter.clone(), &transfer)
+            .await
+            .unwrap());
+        assert!(!db
+            .insert_if_new(COLL_TRANSFER_RAW, filter.clone(), &transfer)
+            .await
+            .unwrap());
+
+        // The filter does not match the stored entry, but the unique index
+        // rejects it, like a concurrent insert of another instance would.
+        let mut filter = filter;
+        filter.insert("data.amount", "100");
+        assert!(!db
+            .insert_if_new(COLL_TRANSFER_RAW, filter, &transfer)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn cursors() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            None
+        );
+
+        db.save_cursor(&alice, "TransferFetcher", 2).await.unwrap();
+        db.save_cursor(&alice, "TransferFetcher", 3).await.unwrap();
+        db.save_cursor(&bob, "TransferFetcher", 5).await.unwrap();
+
+        // Per context and module.
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            db.load_cursor(&bob, "TransferFetcher").await.unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            db.load_cursor(&alice, "NominationsFetcher").await.unwrap(),
+            None
+        );
+
+        db.clear_cursor(&alice, "TransferFetcher").await.unwrap();
+        assert_eq!(
+            db.load_cursor(&alice, "TransferFetcher").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            db.load_cursor(&bob, "TransferFetcher").await.unwrap(),
+            Some(5)
+        );
+    }
+
+    #[tokio::test]
+    async fn backfills() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        assert!(!db.is_backfilled(&alice, "TransferFetcher").await.unwrap());
+
+        db.mark_backfilled(&alice, "TransferFetcher").await.unwrap();
+        // Marking it again is fine.
+        db.mark_backfilled(&alice, "TransferFetcher").await.unwrap();
+
+        assert!(db.is_backfilled(&alice, "TransferFetcher").await.unwrap());
+        assert!(!db
+            .is_backfilled(&alice, "NominationsFetcher")
+            .await
+            .unwrap());
+        assert!(!db
+            .is_backfilled(&Context::bob(), "TransferFetcher")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn get_transfers() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        let transfer = |idx: usize, timestamp: u64| Transfer {
+            block_num: BlockNumber::from(idx as u64),
+            block_timestamp: Timestamp::from(timestamp),
+            extrinsic_index: format!("{}-1", idx).into(),
+            from: alice.stash.clone(),
+            hash: hash(idx),
+            ..Default::default()
+        };
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![
+            transfer(1, 1_000),
+            transfer(2, 1_000),
+            transfer(3, 2_000),
+        ]);
+        assert_eq!(db.store_transfer_event(&alice, &resp).await.unwrap(), 3);
+        assert_eq!(db.store_transfer_event(&bob, &resp).await.unwrap(), 3);
+
+        let blocks = |transfers: Vec<Transfer>| {
+            transfers
+                .into_iter()
+                .map(|t| t.block_num)
+                .collect::<Vec<BlockNumber>>()
+        };
+
+        let all = db
+            .get_transfers(alice.id(), Timestamp::from(1_000), Timestamp::from(2_000))
+            .await
+            .unwrap();
+        assert_eq!(all[0], transfer(3, 2_000));
+        assert_eq!(
+            blocks(all),
+            vec![
+                BlockNumber::from(3),
+                BlockNumber::from(2),
+                BlockNumber::from(1)
+            ]
+        );
+
+        let first = db
+            .get_transfers(alice.id(), Timestamp::from(0), Timestamp::from(1_999))
+            .await
+            .unwrap();
+        assert_eq!(
+            blocks(first),
+            vec![BlockNumber::from(2), BlockNumber::from(1)]
+        );
+
+        let second = db
+            .get_transfers(
+                alice.id(),
+                Timestamp::from(1_001),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap();
+        assert_eq!(blocks(second), vec![BlockNumber::from(3)]);
+
+        let none = db
+            .get_transfers(
+                alice.id(),
+                Timestamp::from(2_001),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[tokio::test]
+    async fn find_contexts_by_description() {
+        let db = db().await;
+
+        let mut alice = Context::alice();
+        alice.description = "W3F Treasury 1".to_string();
+        let mut bob = Context::bob();
+        bob.description = "W3F Treasury 2 (cold)".to_string();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Transfer {
+            extrinsic_index: "1-1".to_string().into(),
+            from: alice.stash.clone(),
+            ..Default::default()
+        }]);
+        db.store_transfer_event(&alice, &resp).await.unwrap();
+        db.store_transfer_event(&bob, &resp).await.unwrap();
+
+        let mut both = vec![alice.id(), bob.id()];
+        both.sort_by(|a, b| a.stash.cmp(&b.stash));
+
+        let found = db
+            .reader()
+            .find_contexts_by_description("w3f treasury")
+            .await
+            .unwrap();
+        assert_eq!(found, both);
+
+        let found = db
+            .reader()
+            .find_contexts_by_description("Treasury 2")
+            .await
+            .unwrap();
+        assert_eq!(found, vec![bob.id()]);
+
+        // Matched literally, not as a regex.
+        let found = db
+            .reader()
+            .find_contexts_by_description("2 (cold)")
+            .await
+            .unwrap();
+        assert_eq!(found, vec![bob.id()]);
+        let found = db
+            .reader()
+            .find_contexts_by_description("Treasury .")
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[tokio::test]
+    async fn aggregate_transfer_totals() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+        let eve = Context::eve();
+
+        let transfer = |idx: usize, timestamp: u64, amount: &str| Transfer {
+            amount: amount.to_string(),
+            block_num: BlockNumber::from(idx as u64),
+            block_timestamp: Timestamp::from(timestamp),
+            extrinsic_index: format!("{}-1", idx).into(),
+            hash: hash(idx),
+            ..Default::default()
+        };
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![
+            transfer(1, 1_000, "1.5"),
+            transfer(2, 1_000, "12345678.0123456789"),
+            // Would be formatted in scientific notation as Decimal128.
+            transfer(5, 1_000, "1200000000000000000000"),
+            // Outside of the range.
+            transfer(3, 3_000, "10"),
+        ]);
+        db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![transfer(4, 2_000, "0.1")]);
+        db.store_transfer_event(&bob, &resp).await.unwrap();
+
+        let totals = db
+            .aggregate_transfer_totals(
+                &[alice.clone(), bob.clone(), eve.clone()],
+                Timestamp::from(0),
+                Timestamp::from(2_000),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(totals.len(), 3);
+        assert_eq!(totals[0].context_id, alice.id());
+        assert_eq!(totals[0].count, 3);
+        assert_eq!(
+            totals[0].amount,
+            Balance::parse_tokens(
+                "1200000000000012345679.5123456789",
+                alice.network.decimals()
+            )
+            .unwrap()
+        );
+        assert_eq!(totals[1].context_id, bob.id());
+        assert_eq!(totals[1].count, 1);
+        assert_eq!(
+            totals[1].amount,
+            Balance::parse_tokens("0.1", bob.network.decimals()).unwrap()
+        );
+        // Without transfers.
+        assert_eq!(totals[2].context_id, eve.id());
+        assert_eq!(totals[2].count, 0);
+        assert_eq!(totals[2].amount, Balance::default());
+        assert_eq!(totals[2].fees, Balance::default());
+    }
+
+    #[tokio::test]
+    async fn transfers_and_rewards_separate() {
+        let db = db().await;
+        let reader = db.reader();
+        let alice = Context::alice();
+
+        assert_ne!(COLL_TRANSFER_RAW, COLL_REWARD_SLASH_RAW);
+
+        // Both entries belong to the same extrinsic.
+        let mut transfers: Response<TransfersPage> = Default::default();
+        transfers.data.transfers = Some(vec![Transfer {
+            block_num: BlockNumber::from(100),
+            block_timestamp: Timestamp::from(1_000),
+            extrinsic_index: "100-1".to_string().into(),
+            hash: hash(1),
+            ..Default::default()
+        }]);
+
+        let mut rewards: Response<RewardsSlashesPage> = Default::default();
+        rewards.data.list = Some(vec![RewardSlash {
+            block_num: BlockNumber::from(100),
+            extrinsic_hash: hash(1).into(),
+            ..Default::default()
+        }]);
+
+        let count = db.store_transfer_event(&alice, &transfers).await.unwrap();
+        assert_eq!(count, 1);
+        let count = db.store_reward_slash_event(&alice, &rewards).await.unwrap();
+        assert_eq!(count, 1);
+
+        let res = reader
+            .fetch_transfers(&[alice.clone()], Timestamp::from(0), Timestamp::from(2_000))
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].data.as_ref(), &transfers.data.transfers.unwrap()[0]);
+
+        let res = reader
+            .fetch_rewards_slashes(&[alice], BlockNumber::from(0), BlockNumber::from(200))
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].data.as_ref(), &rewards.data.list.unwrap()[0]);
+    }
+
+    #[tokio::test]
+    async fn store_reward_slash_invalid_hash() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let mut resp: Response<RewardsSlashesPage> = Default::default();
+        resp.data.list = Some(vec![Default::default(); 3]);
+        let list = resp.data.list.as_mut().unwrap();
+        list[0].extrinsic_hash = hash(0).into();
+        list[1].extrinsic_hash = "".to_string().into();
+        list[2].extrinsic_hash = "0x1234".to_string().into();
+
+        // Only the valid entry is stored.
+        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn store_nomination_event() {
+        let db = db().await;
+
+        // Must now have an influence on data.
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Gen test data
+        let mut resp: Response<NominationsPage> = Default::default();
+        resp.data.list = Some(vec![Default::default(); 10]);
+        resp.data
+            .list
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, e)| e.stash_account_display.address = idx.to_string().into());
+
+        // New data is inserted
+        let count = db.store_nomination_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 10);
+
+        // No new data is inserted
+        let count = db.store_nomination_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Gen new test data
+        let mut new_resp: Response<NominationsPage> = Default::default();
+        new_resp.data.list = Some(vec![Default::default(); 15]);
+        new_resp
+            .data
+            .list
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, e)| e.stash_account_display.address = (idx + 10).to_string().into());
+
+        // New data is inserted
+        let count = db.store_nomination_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 15);
+
+        // No new data is inserted
+        let count = db.store_nomination_event(&bob, &new_resp).await.unwrap();
+        assert_eq!(count, 0);
+
+        // Insert previous data (under a new context)
+        let count = db.store_nomination_event(&bob, &resp).await.unwrap();
+        assert_eq!(count, 10);
+    }
+
+    #[tokio::test]
+    async fn store_nomination_sets() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let store = |targets: &[&str]| {
+            let mut resp: Response<NominationsPage> = Default::default();
+            resp.data.list = Some(
+                targets
+                    .iter()
+                    .map(|target| {
+                        let mut nomination: Nomination = Default::default();
+                        nomination.stash_account_display.address = target.to_string();
+                        nomination
+                    })
+                    .collect(),
+            );
+            let db = &db;
+            let alice = &alice;
+            async move { db.store_nomination_event(alice, &resp).await.unwrap() }
+        };
+
+        store(&["A", "B"]).await;
+        // Same selection in a different order.
+        store(&["B", "A"]).await;
+        store(&["A", "C"]).await;
+        // Returning to a previous selection is a change, too.
+        store(&["A", "B"]).await;
+
+        let sets: Vec<Vec<String>> = db
+            .nomination_sets(&alice)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.data.into_owned().targets)
+            .collect();
+
+        assert_eq!(
+            sets,
+            vec![
+                vec!["A".to_string(), "B".to_string()],
+                vec!["A".to_string(), "C".to_string()],
+                vec!["A".to_string(), "B".to_string()],
+            ]
+        );
+
+        // Sets are tracked per account.
+        assert!(db
+            .nomination_sets(&Context::bob())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn fetch_transfers() {
+        let db = db().await;
+        let report = db.reader();
+
+        // Must now have an influence on data.
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Gen test data
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| {
+                t.block_timestamp = Timestamp::from(idx as u64 * 100);
+                t.extrinsic_index = idx.to_string().into();
+            });
+
+        // New data is inserted
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        // Fetch data
+        let res = report
+            .fetch_transfers(&[alice], Timestamp::from(300), Timestamp::from(800))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.iter()
+                .map(|c| c.data.clone().into_owned())
+                .collect::<Vec<Transfer>>()
+                .as_slice(),
+            &resp.data.transfers.unwrap()[3..9]
+        );
+
+        // Fetch data (invalid)
+        let res = report
+            .fetch_transfers(&[bob], Timestamp::from(300), Timestamp::from(800))
+            .await
+            .unwrap();
+
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn block_time_range() {
+        let db = db().await;
+        let report = db.reader();
+        let alice = Context::alice();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 2]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| {
+                t.block_timestamp = Timestamp::from((idx as u64 + 1) * 1_000);
+                t.extrinsic_index = idx.to_string().into();
+            });
+
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        // The block time is stored on the top level.
+        let stored = db
+            .db
+            .collection::<Document>(COLL_TRANSFER_RAW)
+            .find_one(doc! { "data.extrinsic_index": "1" }, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.get("block_timestamp"),
+            Some(&Timestamp::from(2_000).to_bson().unwrap())
+        );
+
+        let res = report
+            .fetch_transfers(
+                &[alice.clone()],
+                Timestamp::from(1_500),
+                Timestamp::from(2_500),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].data.block_timestamp, Timestamp::from(2_000));
+
+        // Entries stored without the field are backfilled once.
+        let insert_old = |idx: &str, timestamp: u64| {
+            let mut old = stored.clone();
+            old.remove("_id");
+            old.remove("block_timestamp");
+            old.insert("data", {
+                let mut data = old.get_document("data").unwrap().clone();
+                data.insert("extrinsic_index", idx);
+                data.insert(
+                    "block_timestamp",
+                    Timestamp::from(timestamp).to_bson().unwrap(),
+                );
+                data
+            });
+
+            let db = &db;
+            async move {
+                db.db
+                    .collection::<Document>(COLL_TRANSFER_RAW)
+                    .insert_one(old, None)
+                    .await
+                    .unwrap();
+            }
+        };
+
+        // The migration already ran on the empty database while connecting.
+        insert_old("2", 2_200).await;
+        db.migrate_block_time().await.unwrap();
+        let range = || {
+            report.fetch_transfers(
+                std::slice::from_ref(&alice),
+                Timestamp::from(1_500),
+                Timestamp::from(2_500),
+            )
+        };
+        assert_eq!(range().await.unwrap().len(), 1);
+
+        db.db
+            .collection::<Document>(COLL_MIGRATIONS)
+            .delete_one(doc! { "_id": MIGRATION_BLOCK_TIME }, None)
+            .await
+            .unwrap();
+        db.migrate_block_time().await.unwrap();
+        assert_eq!(range().await.unwrap().len(), 2);
+
+        // Only once.
+        insert_old("3", 2_300).await;
+        db.migrate_block_time().await.unwrap();
+        assert_eq!(range().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn aggregate_fees_paid() {
+        let db = db().await;
+        let report = db.reader();
+
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        // Gen test data: alice sends five transfers with fees and receives
+        // five without.
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| {
+                t.amount = "1".to_string();
+                t.block_timestamp = Timestamp::from(idx as u64 * 100);
+                t.extrinsic_index = idx.to_string().into();
+                if idx % 2 == 0 {
+                    t.from = alice.stash.clone();
+                    t.fee = Some(Balance::from(156_000_000));
+                } else {
+                    t.to = alice.stash.clone();
+                }
+            });
+
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        // Fees round-trip (absent fees are stored as null).
+        let res = report
+            .fetch_transfers(&[alice.clone()], Timestamp::from(0), Timestamp::from(900))
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 10);
+        for entry in &res {
+            if entry.data.from == alice.stash {
+                assert_eq!(entry.data.fee, Some(Balance::from(156_000_000)));
+            } else {
+                assert_eq!(entry.data.fee, None);
+            }
+        }
+
+        // Aggregate fees (only idx 4, 6 and 8 are in range and outgoing).
+        let res = db
+            .aggregate_transfer_totals(
+                &[alice.clone(), bob.clone()],
+                Timestamp::from(300),
+                Timestamp::from(800),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].context_id, alice.id());
+        assert_eq!(res[0].count, 6);
+        assert_eq!(res[0].fees, Balance::from(3 * 156_000_000));
+        assert_eq!(res[1].context_id, bob.id());
+        assert_eq!(res[1].fees, Balance::default());
+    }
+
+    #[tokio::test]
+    async fn fetch_transfers_by_tag() {
+        let db = db().await;
+        let report = db.reader();
+
+        let mut alice = Context::alice();
+        alice.tags = vec!["exchange".to_string(), "eu".to_string()];
+        let mut bob = Context::bob();
+        bob.tags = vec!["validator".to_string()];
+
+        // Gen test data
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 4]);
+        resp.data
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| {
+                t.block_timestamp = Timestamp::from(idx as u64 * 100);
+                t.extrinsic_index = idx.to_string().into();
+            });
+
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+        let _ = db.store_transfer_event(&bob, &resp).await.unwrap();
+
+        let res = report
+            .fetch_transfers_by_tag("eu", Timestamp::from(0), Timestamp::from(1000))
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 4);
+        assert!(res.iter().all(|entry| entry.context_id == alice.id()));
+        assert_eq!(res[0].tags.as_ref(), &alice.tags);
+
+        let res = report
+            .fetch_transfers_by_tag("validator", Timestamp::from(100), Timestamp::from(200))
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert!(res.iter().all(|entry| entry.context_id == bob.id()));
+
+        let res = report
+            .fetch_transfers_by_tag("high-risk", Timestamp::from(0), Timestamp::from(1000))
+            .await
+            .unwrap();
+
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn store_bond_events() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        let mut resp: Response<BondsPage> = Default::default();
+        resp.data.list = Some(
+            [BondKind::Bond, BondKind::Unbond, BondKind::Rebond]
+                .iter()
+                .enumerate()
+                .map(|(idx, kind)| BondEvent {
+                    extrinsic_index: format!("{}-1", idx).into(),
+                    kind: *kind,
+                    amount: "10".to_string(),
+                    era: 1_000 + idx as u32,
+                    ..Default::default()
+                })
+                .collect(),
+        );
+
+        assert_eq!(db.store_bond_events(&alice, &resp).await.unwrap(), 3);
+        assert_eq!(db.store_bond_events(&alice, &resp).await.unwrap(), 0);
+        assert_eq!(db.store_bond_events(&bob, &resp).await.unwrap(), 3);
+
+        let stored = db
+            .db
+            .collection::<ContextData<BondEvent>>(COLL_BONDS_RAW)
+            .find_one(
+                doc! {
+                    "context_id": alice.id().to_bson().unwrap(),
+                    "data.extrinsic_index": "1-1",
+                },
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(stored.data.kind, BondKind::Unbond);
+        assert_eq!(stored.data.era, 1_001);
+    }
+
+    #[tokio::test]
+    async fn store_extrinsic_event() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let mut resp: Response<ExtrinsicsPage> = Default::default();
+        resp.data.extrinsics = Some(
+            ["bond", "bond_extra"]
+                .iter()
+                .enumerate()
+                .map(|(idx, call)| Extrinsic {
+                    extrinsic_index: format!("{}-1", idx).into(),
+                    call_module: "staking".to_string(),
+                    call_module_function: call.to_string(),
+                    success: true,
+                    ..Default::default()
+                })
+                .collect(),
+        );
+
+        assert_eq!(db.store_extrinsic_event(&alice, &resp).await.unwrap(), 2);
+        assert_eq!(db.store_extrinsic_event(&alice, &resp).await.unwrap(), 0);
+
+        // Excluded calls are not stored.
+        let mut bob = Context::bob();
+        bob.calls = CallFilter {
+            include: vec![],
+            exclude: vec!["staking.bond_*".to_string()],
+        };
+        assert_eq!(db.store_extrinsic_event(&bob, &resp).await.unwrap(), 1);
+
+        let stored = db
+            .db
+            .collection::<ContextData<Extrinsic>>(COLL_EXTRINSICS_RAW)
+            .find_one(
+                doc! {
+                    "context_id": alice.id().to_bson().unwrap(),
+                    "data.extrinsic_index": "1-1",
+                },
+                None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(stored.data.call(), "staking.bond_extra");
+    }
+
+    #[tokio::test]
+    async fn store_identity() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let mut identity = Identity {
+            display: Some("Alice".to_string()),
+            judgements: vec![Judgement {
+                index: 0,
+                judgement: "Reasonable".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(db.store_identity(&alice, &identity).await.unwrap(), 1);
+        // Unchanged.
+        assert_eq!(db.store_identity(&alice, &identity).await.unwrap(), 0);
+
+        identity.judgements[0].judgement = "KnownGood".to_string();
+        assert_eq!(db.store_identity(&alice, &identity).await.unwrap(), 1);
+        assert_eq!(db.store_identity(&alice, &identity).await.unwrap(), 0);
+
+        // Still a single entry.
+        let count = db
+            .db
+            .collection::<Document>(COLL_IDENTITIES)
+            .count_documents(None, None)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn store_balance_snapshot() {
+        let db = db().await;
+        let alice = Context::alice();
+        let bob = Context::bob();
+
+        let balance = |free: u128| AccountBalance {
+            free: Balance::from(free),
+            ..Default::default()
+        };
+
+        let count = db
+            .store_balance_snapshot(&alice, Timestamp::from(3_600), &balance(1))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Same pass, replaced.
+        let count = db
+            .store_balance_snapshot(&alice, Timestamp::from(3_600), &balance(2))
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        // Next pass or other context.
+        let count = db
+            .store_balance_snapshot(&alice, Timestamp::from(7_200), &balance(3))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let count = db
+            .store_balance_snapshot(&bob, Timestamp::from(3_600), &balance(4))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let snapshots: Vec<ContextData<AccountBalance>> = db
+            .db
+            .collection::<ContextData<AccountBalance>>(COLL_BALANCES)
+            .find(
+                doc! {
+                    "context_id": alice.id().to_bson().unwrap(),
+                },
+                {
+                    let mut ops = FindOptions::default();
+                    ops.sort = Some(doc! {
+                        "timestamp": 1
+                    });
+                    Some(ops)
+                },
+            )
+            .await
+            .unwrap()
+            .map(|doc| doc.unwrap())
+            .collect()
+            .await;
+
+        let free: Vec<Balance> = snapshots.iter().map(|s| s.data.free).collect();
+        assert_eq!(free, vec![Balance::from(2), Balance::from(3)]);
+    }
+
+    #[tokio::test]
+    async fn export_transfers() {
+        let db = db().await;
+        let report = db.reader();
+
+        let mut alice = Context::alice();
+        alice.description = "Alice, \"cold\" wallet".to_string();
+
+        // Only the header without any transfers.
+        let csv = report
+            .export_transfers(
+                &[alice.clone()],
+                Timestamp::from(0),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "stash,network,description,block,timestamp,from,to,amount,hash\n"
+        );
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Transfer {
+            amount: "1.5".to_string(),
+            block_num: BlockNumber::from(100),
+            block_timestamp: Timestamp::from(1_704_067_200),
+            extrinsic_index: "100-1".to_string().into(),
+            from: alice.stash.clone(),
+            to: "bob".to_string(),
+            hash: hash(1),
+            ..Default::default()
+        }]);
 
-        // No new data is inserted
-        let count = db.store_transfer_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 0);
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
 
-        // Insert previous data (under a new context)
-        let count = db.store_transfer_event(&bob, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        let csv = report
+            .export_transfers(
+                &[alice.clone()],
+                Timestamp::from(0),
+                Timestamp::from(i64::MAX as u64),
+            )
+            .await
+            .unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{stash},polkadot,\"Alice, \"\"cold\"\" wallet\",100,1704067200,{stash},bob,1.5,{}",
+                hash(1),
+                stash = alice.stash
+            )
+        );
     }
 
     #[tokio::test]
-    async fn store_reward_slash_event() {
+    async fn export_ndjson() {
         let db = db().await;
+        let report = db.reader();
 
-        // Must now have an influence on data.
         let alice = Context::alice();
         let bob = Context::bob();
 
         // Gen test data
-        let mut resp: Response<RewardsSlashesPage> = Default::default();
-        resp.data.list = Some(vec![Default::default(); 10]);
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
         resp.data
-            .list
+            .transfers
             .as_mut()
             .unwrap()
             .iter_mut()
             .enumerate()
-            .for_each(|(idx, e)| e.extrinsic_hash = idx.to_string().into());
+            .for_each(|(idx, t)| {
+                t.block_num = BlockNumber::from(idx as u64);
+                t.extrinsic_index = idx.to_string().into();
+            });
 
-        // New data is inserted
-        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        let mut first = resp.clone();
+        first.data.transfers.as_mut().unwrap().truncate(6);
 
-        // No new data is inserted
-        let count = db.store_reward_slash_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 0);
+        let _ = db.store_transfer_event(&alice, &first).await.unwrap();
+        let _ = db.store_transfer_event(&bob, &first).await.unwrap();
 
-        // Gen new test data
-        let mut new_resp: Response<RewardsSlashesPage> = Default::default();
-        new_resp.data.list = Some(vec![Default::default(); 15]);
-        new_resp
-            .data
-            .list
-            .as_mut()
+        // Full export.
+        let mut out = vec![];
+        let cursor = report
+            .export_ndjson(
+                &ScrapingModule::Transfer,
+                &[alice.clone()],
+                ExportCursor::default(),
+                &mut out,
+            )
+            .await
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
             .unwrap()
-            .iter_mut()
-            .enumerate()
-            .for_each(|(idx, e)| e.extrinsic_hash = (idx + 10).to_string().into());
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
 
-        // New data is inserted
-        let count = db.store_reward_slash_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 15);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0]["context_id"]["stash"], alice.stash.as_str());
+        assert_eq!(lines[0]["data"]["block_num"], 0);
+        assert!(lines[0].get("_id").is_none());
 
-        // No new data is inserted
-        let count = db.store_reward_slash_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 0);
+        // Nothing new.
+        let mut out = vec![];
+        let cursor = report
+            .export_ndjson(
+                &ScrapingModule::Transfer,
+                &[alice.clone()],
+                cursor,
+                &mut out,
+            )
+            .await
+            .unwrap();
 
-        // Insert previous data (under a new context)
-        let count = db.store_reward_slash_event(&bob, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        assert!(out.is_empty());
+
+        // Incremental export only contains the new entries.
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        let mut out = vec![];
+        let _ = report
+            .export_ndjson(&ScrapingModule::Transfer, &[alice], cursor, &mut out)
+            .await
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["data"]["block_num"], 6);
+        assert_eq!(lines[3]["data"]["block_num"], 9);
     }
 
     #[tokio::test]
-    async fn store_nomination_event() {
+    async fn export_ndjson_late_entries() {
         let db = db().await;
-
-        // Must now have an influence on data.
+        let report = db.reader();
         let alice = Context::alice();
-        let bob = Context::bob();
 
-        // Gen test data
-        let mut resp: Response<NominationsPage> = Default::default();
-        resp.data.list = Some(vec![Default::default(); 10]);
-        resp.data
-            .list
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .enumerate()
-            .for_each(|(idx, e)| e.stash_account_display.address = idx.to_string().into());
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Transfer {
+            extrinsic_index: "1-1".to_string().into(),
+            ..Default::default()
+        }]);
+        db.store_transfer_event(&alice, &resp).await.unwrap();
 
-        // New data is inserted
-        let count = db.store_nomination_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        let mut out = vec![];
+        let cursor = report
+            .export_ndjson(
+                &ScrapingModule::Transfer,
+                &[alice.clone()],
+                ExportCursor::default(),
+                &mut out,
+            )
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
 
-        // No new data is inserted
-        let count = db.store_nomination_event(&alice, &resp).await.unwrap();
-        assert_eq!(count, 0);
+        // An entry whose id was generated before the last exported one, but
+        // which was stored afterwards.
+        let bytes = cursor.last_id.unwrap().bytes();
+        let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) - 10;
+        let mut late = [0xff; 12];
+        late[..4].copy_from_slice(&secs.to_be_bytes());
 
-        // Gen new test data
-        let mut new_resp: Response<NominationsPage> = Default::default();
-        new_resp.data.list = Some(vec![Default::default(); 15]);
-        new_resp
-            .data
-            .list
-            .as_mut()
+        db.db
+            .collection::<Document>(&db.colls.get(COLL_TRANSFER_RAW))
+            .insert_one(
+                doc! {
+                    "_id": ObjectId::from_bytes(late),
+                    "context_id": alice.id().to_bson().unwrap(),
+                    "data": {
+                        "extrinsic_index": "2-1",
+                    },
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut out = vec![];
+        let cursor = report
+            .export_ndjson(
+                &ScrapingModule::Transfer,
+                &[alice.clone()],
+                cursor,
+                &mut out,
+            )
+            .await
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
             .unwrap()
-            .iter_mut()
-            .enumerate()
-            .for_each(|(idx, e)| e.stash_account_display.address = (idx + 10).to_string().into());
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["data"]["extrinsic_index"], "2-1");
 
-        // New data is inserted
-        let count = db.store_nomination_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 15);
+        // Entries are only exported once.
+        let mut out = vec![];
+        report
+            .export_ndjson(&ScrapingModule::Transfer, &[alice], cursor, &mut out)
+            .await
+            .unwrap();
+        assert!(out.is_empty());
+    }
 
-        // No new data is inserted
-        let count = db.store_nomination_event(&bob, &new_resp).await.unwrap();
-        assert_eq!(count, 0);
+    #[tokio::test]
+    async fn export_ndjson_updated_entries() {
+        let db = db().await;
+        let reader = db.reader();
+        let alice = Context::alice();
 
-        // Insert previous data (under a new context)
-        let count = db.store_nomination_event(&bob, &resp).await.unwrap();
-        assert_eq!(count, 10);
+        let mut identity = Identity {
+            display: Some("Alice".to_string()),
+            ..Default::default()
+        };
+        db.store_identity(&alice, &identity).await.unwrap();
+
+        let mut out = vec![];
+        let cursor = reader
+            .export_ndjson(
+                &ScrapingModule::Identity,
+                &[alice.clone()],
+                ExportCursor::default(),
+                &mut out,
+            )
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+        // Replaced in place, but exported again.
+        identity.display = Some("Alice Updated".to_string());
+        db.store_identity(&alice, &identity).await.unwrap();
+
+        let mut out = vec![];
+        reader
+            .export_ndjson(&ScrapingModule::Identity, &[alice], cursor, &mut out)
+            .await
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["data"]["display"], "Alice Updated");
     }
 
     #[tokio::test]
-    async fn fetch_transfers() {
+    async fn fetch_rewards_slashes() {
         let db = db().await;
         let report = db.reader();
 
@@ -534,39 +3899,39 @@ mod tests {
         let bob = Context::bob();
 
         // Gen test data
-        let mut resp: Response<TransfersPage> = Default::default();
-        resp.data.transfers = Some(vec![Default::default(); 10]);
+        let mut resp: Response<RewardsSlashesPage> = Default::default();
+        resp.data.list = Some(vec![Default::default(); 10]);
         resp.data
-            .transfers
+            .list
             .as_mut()
             .unwrap()
             .iter_mut()
             .enumerate()
             .for_each(|(idx, t)| {
-                t.block_timestamp = Timestamp::from(idx as u64 * 100);
-                t.extrinsic_index = idx.to_string().into();
+                t.block_num = BlockNumber::from(idx as u64 * 100);
+                t.extrinsic_hash = hash(idx).into();
             });
 
         // New data is inserted
-        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+        let _ = db.store_reward_slash_event(&alice, &resp).await.unwrap();
 
         // Fetch data
         let res = report
-            .fetch_transfers(&[alice], Timestamp::from(300), Timestamp::from(800))
+            .fetch_rewards_slashes(&[alice], BlockNumber::from(300), BlockNumber::from(800))
             .await
             .unwrap();
 
         assert_eq!(
             res.iter()
                 .map(|c| c.data.clone().into_owned())
-                .collect::<Vec<Transfer>>()
+                .collect::<Vec<RewardSlash>>()
                 .as_slice(),
-            &resp.data.transfers.unwrap()[3..9]
+            &resp.data.list.unwrap()[3..9]
         );
 
         // Fetch data (invalid)
         let res = report
-            .fetch_transfers(&[bob], Timestamp::from(300), Timestamp::from(800))
+            .fetch_rewards_slashes(&[bob], BlockNumber::from(300), BlockNumber::from(800))
             .await
             .unwrap();
 
@@ -574,51 +3939,249 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn fetch_rewards_slashes() {
+    async fn fetch_rewards_slashes_by_timestamp() {
         let db = db().await;
-        let report = db.reader();
+        let alice = Context::alice();
+
+        let mut resp: Response<RewardsSlashesPage> = Default::default();
+        resp.data.list = Some(
+            (0..4)
+                .map(|idx| RewardSlash {
+                    block_num: BlockNumber::from(idx as u64),
+                    // The first one lacks a timestamp.
+                    block_timestamp: Timestamp::from(idx as u64 * 1_000),
+                    extrinsic_hash: hash(idx).into(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        db.store_reward_slash_event(&alice, &resp).await.unwrap();
+
+        let reader = &db.reader();
+        let blocks = |from: u64, to: u64| async move {
+            reader
+                .fetch_rewards_slashes_by_timestamp(
+                    &[Context::alice()],
+                    Timestamp::from(from),
+                    Timestamp::from(to),
+                )
+                .await
+                .unwrap()
+                .iter()
+                .map(|entry| entry.data.block_num)
+                .collect::<Vec<BlockNumber>>()
+        };
+
+        assert_eq!(
+            blocks(0, i64::MAX as u64).await,
+            vec![
+                BlockNumber::from(3),
+                BlockNumber::from(2),
+                BlockNumber::from(1)
+            ]
+        );
+        assert_eq!(blocks(1_500, 2_000).await, vec![BlockNumber::from(2)]);
+        assert!(blocks(3_001, i64::MAX as u64).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn exists() {
+        let db = db().await;
+        let reader = db.reader();
 
-        // Must now have an influence on data.
         let alice = Context::alice();
         let bob = Context::bob();
 
-        // Gen test data
         let mut resp: Response<RewardsSlashesPage> = Default::default();
-        resp.data.list = Some(vec![Default::default(); 10]);
+        resp.data.list = Some(vec![RewardSlash {
+            extrinsic_hash: hash(1).into(),
+            ..Default::default()
+        }]);
+
+        let _ = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Transfer {
+            from: alice.stash.clone(),
+            hash: hash(3),
+            ..Default::default()
+        }]);
+
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        let known = ExtrinsicHash::new(hash(1)).unwrap();
+        let unknown = ExtrinsicHash::new(hash(2)).unwrap();
+        let transfer = ExtrinsicHash::new(hash(3)).unwrap();
+
+        let rewards = ScrapingModule::RewardsSlashes;
+        assert!(reader.exists(&rewards, &alice, &known).await.unwrap());
+        assert!(!reader.exists(&rewards, &alice, &unknown).await.unwrap());
+        assert!(!reader.exists(&rewards, &bob, &known).await.unwrap());
+        assert!(!reader.exists(&rewards, &alice, &transfer).await.unwrap());
+
+        let transfers = ScrapingModule::Transfer;
+        assert!(reader.exists(&transfers, &alice, &transfer).await.unwrap());
+        assert!(!reader.exists(&transfers, &alice, &known).await.unwrap());
+
+        assert!(reader
+            .exists(&ScrapingModule::Nominations, &alice, &known)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn max_documents() {
+        let db = db().await;
+        let alice = Context::alice();
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 10]);
         resp.data
-            .list
+            .transfers
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(idx, t)| t.extrinsic_index = idx.to_string().into());
+
+        let _ = db.store_transfer_event(&alice, &resp).await.unwrap();
+
+        let from = Timestamp::from(0);
+        let to = Timestamp::from(i64::MAX as u64);
+
+        // Within the limit.
+        let reader = db.reader().with_max_documents(Some(10));
+        let res = reader
+            .fetch_transfers(&[alice.clone()], from, to)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 10);
+
+        // Above the limit.
+        let reader = db.reader().with_max_documents(Some(5));
+        let err = reader
+            .fetch_transfers(&[alice], from, to)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("range too large"));
+    }
+
+    #[test]
+    fn transfer_bucket_names() {
+        assert_eq!(
+            transfer_bucket(Timestamp::from(1_704_067_200)).unwrap(),
+            "raw_transfers_2024_01"
+        );
+        assert_eq!(
+            transfer_bucket(Timestamp::from(1_706_745_599)).unwrap(),
+            "raw_transfers_2024_01"
+        );
+        assert_eq!(
+            transfer_bucket(Timestamp::from(1_706_745_600)).unwrap(),
+            "raw_transfers_2024_02"
+        );
+        assert!(transfer_bucket(Timestamp::from(u64::MAX)).is_none());
+    }
+
+    #[tokio::test]
+    async fn time_buckets() {
+        let db = db().await.with_time_buckets(true);
+        let reader = db.reader();
+        let alice = Context::alice();
+
+        // Two transfers in January and two in February 2024.
+        let timestamps = [1_704_844_800, 1_706_745_599, 1_706_745_600, 1_707_523_200];
+
+        let mut resp: Response<TransfersPage> = Default::default();
+        resp.data.transfers = Some(vec![Default::default(); 4]);
+        resp.data
+            .transfers
             .as_mut()
             .unwrap()
             .iter_mut()
             .enumerate()
             .for_each(|(idx, t)| {
-                t.block_num = BlockNumber::from(idx as u64 * 100);
-                t.extrinsic_hash = idx.to_string().into();
+                t.block_num = BlockNumber::from(idx as u64);
+                t.block_timestamp = Timestamp::from(timestamps[idx]);
+                t.extrinsic_index = idx.to_string().into();
             });
 
-        // New data is inserted
-        let _ = db.store_reward_slash_event(&alice, &resp).await.unwrap();
+        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 4);
 
-        // Fetch data
-        let res = report
-            .fetch_rewards_slashes(&[alice], BlockNumber::from(300), BlockNumber::from(800))
+        // The indexes of the buckets are only set up once, for all clones.
+        let mut indexed: Vec<String> = db
+            .clone()
+            .indexed_buckets
+            .lock()
             .await
-            .unwrap();
+            .iter()
+            .cloned()
+            .collect();
+        indexed.sort();
+        assert_eq!(
+            indexed,
+            vec!["raw_transfers_2024_01", "raw_transfers_2024_02"]
+        );
+
+        // Still deduplicated within the buckets.
+        let count = db.store_transfer_event(&alice, &resp).await.unwrap();
+        assert_eq!(count, 0);
 
+        // Entries are routed by month.
+        for (coll, expected) in &[
+            ("raw_transfers_2024_01", 2),
+            ("raw_transfers_2024_02", 2),
+            (COLL_TRANSFER_RAW, 0),
+        ] {
+            let count = db
+                .db
+                .collection::<Document>(coll)
+                .count_documents(doc! {}, None)
+                .await
+                .unwrap();
+            assert_eq!(count, *expected);
+        }
+
+        // Range within a single bucket.
+        let res = reader
+            .fetch_transfers(
+                &[alice.clone()],
+                Timestamp::from(1_706_745_600),
+                Timestamp::from(1_709_251_199),
+            )
+            .await
+            .unwrap();
         assert_eq!(
             res.iter()
-                .map(|c| c.data.clone().into_owned())
-                .collect::<Vec<RewardSlash>>()
-                .as_slice(),
-            &resp.data.list.unwrap()[3..9]
+                .map(|t| t.data.block_num)
+                .collect::<Vec<BlockNumber>>(),
+            vec![BlockNumber::from(3), BlockNumber::from(2)]
         );
 
-        // Fetch data (invalid)
-        let res = report
-            .fetch_rewards_slashes(&[bob], BlockNumber::from(300), BlockNumber::from(800))
+        // Range spanning both buckets.
+        let res = reader
+            .fetch_transfers(
+                &[alice.clone()],
+                Timestamp::from(1_705_000_000),
+                Timestamp::from(1_707_000_000),
+            )
             .await
             .unwrap();
+        assert_eq!(
+            res.iter()
+                .map(|t| t.data.block_num)
+                .collect::<Vec<BlockNumber>>(),
+            vec![BlockNumber::from(2), BlockNumber::from(1)]
+        );
 
-        assert!(res.is_empty());
+        // The size limit applies to all buckets combined.
+        let err = reader
+            .clone()
+            .with_max_documents(Some(3))
+            .fetch_transfers(&[alice], Timestamp::from(0), Timestamp::from(1_800_000_000))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("range too large"));
     }
 }
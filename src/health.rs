@@ -0,0 +1,157 @@
+use crate::core::ScrapingStats;
+use crate::database::Database;
+use crate::{Result, Timestamp};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+
+/// Seconds the database may take to respond to a ping.
+const PING_TIMEOUT: u64 = 5;
+
+/// Determines whether the monitor is healthy: the database responds and
+/// every fetcher completed a pass over all contexts recently. A pass counts
+/// as completed even if single contexts failed, but not if all of them did.
+pub struct HealthCheck {
+    db: Database,
+    stats: Option<Arc<ScrapingStats>>,
+    max_age: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    pub database: bool,
+    /// Fetchers without a completed pass within the maximum age.
+    pub stale: Vec<&'static str>,
+}
+
+impl HealthCheck {
+    pub fn new(db: Database, max_age: u64) -> Self {
+        HealthCheck {
+            db: db,
+            stats: None,
+            max_age: max_age,
+        }
+    }
+    /// Checks the fetchers reporting to the given stats.
+    pub fn with_stats(self, stats: Arc<ScrapingStats>) -> Self {
+        HealthCheck {
+            stats: Some(stats),
+            ..self
+        }
+    }
+    pub async fn check(&self) -> HealthStatus {
+        let database = matches!(
+            timeout(Duration::from_secs(PING_TIMEOUT), self.db.ping()).await,
+            Ok(Ok(()))
+        );
+
+        let now = Timestamp::now();
+        let mut stale: Vec<&'static str> = self
+            .stats
+            .iter()
+            .flat_map(|stats| stats.last_passes())
+            .filter(|(_, last)| match last {
+                Some(last) => (now - *last).as_secs() > self.max_age,
+                None => true,
+            })
+            .map(|(name, _)| name)
+            .collect();
+        stale.sort();
+
+        HealthStatus {
+            healthy: database && stale.is_empty(),
+            database: database,
+            stale: stale,
+        }
+    }
+}
+
+pub(crate) async fn handle(check: Arc<HealthCheck>, req: Request<Body>) -> Response<Body> {
+    if req.uri().path() != "/healthz" {
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::NOT_FOUND;
+        return resp;
+    }
+
+    let status = check.check().await;
+    let body = serde_json::to_string(&status).unwrap_or_default();
+
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = if status.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+    resp
+}
+
+/// Serves the health status on `/healthz`, with status 200 if healthy and 503
+/// otherwise.
+pub async fn serve(addr: SocketAddr, check: Arc<HealthCheck>) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let check = Arc::clone(&check);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let check = Arc::clone(&check);
+                async move { Ok::<_, Infallible>(handle(check, req).await) }
+            }))
+        }
+    });
+
+    Server::try_bind(&addr)?.serve(make_service).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::db;
+
+    #[tokio::test]
+    async fn stale_fetchers() {
+        let stats = Arc::new(ScrapingStats::default());
+        stats.register_fetcher("TransferFetcher");
+        stats.register_fetcher("NominationsFetcher");
+
+        let check = Arc::new(HealthCheck::new(db().await, 60).with_stats(Arc::clone(&stats)));
+
+        // No pass was completed yet.
+        let status = check.check().await;
+        assert!(status.database);
+        assert!(!status.healthy);
+        assert_eq!(status.stale, vec!["NominationsFetcher", "TransferFetcher"]);
+
+        stats.record_pass("TransferFetcher");
+        let resp = handle(
+            Arc::clone(&check),
+            Request::get("/healthz").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(
+            body,
+            r#"{"healthy":false,"database":true,"stale":["NominationsFetcher"]}"#
+        );
+
+        stats.record_pass("NominationsFetcher");
+        let resp = handle(
+            Arc::clone(&check),
+            Request::get("/healthz").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = handle(check, Request::get("/other").body(Body::empty()).unwrap()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
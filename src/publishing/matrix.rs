@@ -0,0 +1,115 @@
+use super::{send_with_retry, Publisher, ReportMessage};
+use crate::Result;
+use rand::{thread_rng, Rng};
+use reqwest::{Client, Url};
+
+/// Posts reports as messages into a Matrix room.
+pub struct Matrix {
+    client: Client,
+    homeserver: Url,
+    access_token: String,
+    room_id: String,
+}
+
+impl Matrix {
+    pub fn new(homeserver: &str, access_token: &str, room_id: &str) -> Result<Self> {
+        let homeserver = Url::parse(homeserver)?;
+        if homeserver.cannot_be_a_base() {
+            return Err(anyhow!("invalid Matrix homeserver URL '{}'", homeserver));
+        }
+
+        Ok(Matrix {
+            client: Client::new(),
+            homeserver: homeserver,
+            access_token: access_token.to_string(),
+            room_id: room_id.to_string(),
+        })
+    }
+    /// The endpoint for sending a message event, with a new transaction ID.
+    fn message_url(&self) -> Url {
+        let txn_id = thread_rng().gen::<u64>().to_string();
+
+        let mut url = self.homeserver.clone();
+        // Checked in `new()`.
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .extend(&["_matrix", "client", "r0", "rooms"])
+            .push(&self.room_id)
+            .extend(&["send", "m.room.message"])
+            .push(&txn_id);
+
+        url
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MessageEvent {
+    msgtype: &'static str,
+    body: String,
+    format: &'static str,
+    formatted_body: String,
+}
+
+impl From<&ReportMessage> for MessageEvent {
+    fn from(message: &ReportMessage) -> Self {
+        MessageEvent {
+            msgtype: "m.text",
+            body: format!("{}\n\n{}", message.title, message.text),
+            format: "org.matrix.custom.html",
+            formatted_body: format!(
+                "<strong>{}</strong><pre>{}</pre>",
+                escape_html(&message.title),
+                escape_html(&message.text)
+            ),
+        }
+    }
+}
+
+fn escape_html(val: &str) -> String {
+    val.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[async_trait]
+impl Publisher for Matrix {
+    type Data = ReportMessage;
+    type Info = ();
+
+    async fn upload_data(&self, _info: Self::Info, data: Self::Data) -> Result<()> {
+        let req = self
+            .client
+            .put(self.message_url())
+            .bearer_auth(&self.access_token)
+            .json(&MessageEvent::from(&data));
+
+        send_with_retry(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_url() {
+        let matrix = Matrix::new("https://matrix.org/", "token", "!abc:matrix.org").unwrap();
+        let url = matrix.message_url().to_string();
+        assert!(url.starts_with(
+            "https://matrix.org/_matrix/client/r0/rooms/!abc:matrix.org/send/m.room.message/"
+        ));
+
+        let event = MessageEvent::from(&ReportMessage {
+            title: "Transfers".to_string(),
+            text: "<1 DOT>".to_string(),
+            content: serde_json::Value::Null,
+        });
+        assert_eq!(event.body, "Transfers\n\n<1 DOT>");
+        assert_eq!(
+            event.formatted_body,
+            "<strong>Transfers</strong><pre>&lt;1 DOT&gt;</pre>"
+        );
+    }
+}
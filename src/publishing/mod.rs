@@ -1,7 +1,19 @@
 use crate::Result;
+use reqwest::RequestBuilder;
+use tokio::time::{sleep, Duration};
+
 mod google_drive;
+mod matrix;
+mod webhook;
 
 pub use self::google_drive::{GoogleDrive, GoogleDriveUploadInfo, GoogleStoragePayload};
+pub use self::matrix::Matrix;
+pub use self::webhook::Webhook;
+
+/// Number of attempts to send a report message before giving up.
+const PUBLISH_ATTEMPTS: u32 = 3;
+/// Seconds to wait after the first failed attempt, doubled on each retry.
+const PUBLISH_RETRY_DELAY: u64 = 2;
 
 #[async_trait]
 pub trait Publisher {
@@ -11,3 +23,45 @@ pub trait Publisher {
 
     async fn upload_data(&self, info: Self::Info, data: Self::Data) -> Result<()>;
 }
+
+/// A report rendered as a chat message, used by the Matrix and webhook
+/// publishers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportMessage {
+    pub title: String,
+    /// Human readable summary of the report.
+    pub text: String,
+    /// Machine readable summary of the report.
+    pub content: serde_json::Value,
+}
+
+/// Sends the request, retrying failed attempts with an increasing delay.
+async fn send_with_retry(req: RequestBuilder) -> Result<()> {
+    let mut delay = PUBLISH_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let req_attempt = req
+            .try_clone()
+            .ok_or_else(|| anyhow!("publishing request can not be retried"))?;
+
+        let res = match req_attempt.send().await {
+            Ok(resp) => resp.error_for_status().map(|_| ()),
+            Err(err) => Err(err),
+        };
+
+        match res {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt < PUBLISH_ATTEMPTS => {
+                warn!(
+                    "Failed to publish report (attempt {} of {}), retrying in {}s: {:?}",
+                    attempt, PUBLISH_ATTEMPTS, delay, err
+                );
+
+                sleep(Duration::from_secs(delay)).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
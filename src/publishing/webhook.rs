@@ -0,0 +1,28 @@
+use super::{send_with_retry, Publisher, ReportMessage};
+use crate::Result;
+use reqwest::Client;
+
+/// Sends reports as JSON to an arbitrary HTTP endpoint.
+pub struct Webhook {
+    client: Client,
+    url: String,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Webhook {
+            client: Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for Webhook {
+    type Data = ReportMessage;
+    type Info = ();
+
+    async fn upload_data(&self, _info: Self::Info, data: Self::Data) -> Result<()> {
+        send_with_retry(self.client.post(&self.url).json(&data)).await
+    }
+}
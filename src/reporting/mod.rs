@@ -1,5 +1,9 @@
-use crate::publishing::Publisher;
-use crate::Result;
+use crate::publishing::{GoogleStoragePayload, Publisher, ReportMessage};
+use crate::{Result, TokenAmount};
+use anyhow::Error;
+use serde::Serializer;
+use std::borrow::Cow;
+use std::str::FromStr;
 use std::sync::Arc;
 
 mod nominations;
@@ -7,8 +11,93 @@ mod rewards_slashes;
 mod transfers;
 
 pub use nominations::{NominationReport, NominationReportGenerator};
-pub use rewards_slashes::RewardSlashReportGenerator;
-pub use transfers::{TransferReport, TransferReportGenerator};
+pub use rewards_slashes::{RewardSlashReport, RewardSlashReportGenerator};
+pub use transfers::{render as render_transfers, TransferReport, TransferReportGenerator};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+    Text,
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        match val {
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            "text" => Ok(ReportFormat::Text),
+            _ => Err(anyhow!("unknown report format '{}'", val)),
+        }
+    }
+}
+
+/// Any of the generated reports, for publishers which are not specific to a
+/// report module.
+#[derive(Clone)]
+pub enum AnyReport {
+    Transfers(TransferReport),
+    RewardsSlashes(RewardSlashReport),
+    Nominations(NominationReport),
+}
+
+impl From<TransferReport> for AnyReport {
+    fn from(val: TransferReport) -> Self {
+        AnyReport::Transfers(val)
+    }
+}
+
+impl From<RewardSlashReport> for AnyReport {
+    fn from(val: RewardSlashReport) -> Self {
+        AnyReport::RewardsSlashes(val)
+    }
+}
+
+impl From<NominationReport> for AnyReport {
+    fn from(val: NominationReport) -> Self {
+        AnyReport::Nominations(val)
+    }
+}
+
+impl From<AnyReport> for GoogleStoragePayload {
+    fn from(val: AnyReport) -> Self {
+        match val {
+            AnyReport::Transfers(report) => report.into(),
+            AnyReport::RewardsSlashes(report) => report.into(),
+            AnyReport::Nominations(report) => report.into(),
+        }
+    }
+}
+
+impl From<AnyReport> for ReportMessage {
+    fn from(val: AnyReport) -> Self {
+        match val {
+            AnyReport::Transfers(report) => report.into(),
+            AnyReport::RewardsSlashes(report) => report.into(),
+            AnyReport::Nominations(report) => report.into(),
+        }
+    }
+}
+
+/// Serializes an amount as its value in tokens, without the symbol.
+fn serialize_token_value<S: Serializer>(
+    amount: &TokenAmount,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&amount.value())
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break, so
+/// user-supplied values can't shift or add columns.
+pub fn csv_escape(val: &str) -> Cow<'_, str> {
+    if val.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        Cow::Owned(format!("\"{}\"", val.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(val)
+    }
+}
 
 // TODO: Is this type constraint required here?
 #[async_trait]
@@ -26,3 +115,15 @@ pub trait GenerateReport<T: Publisher> {
         report: Self::Report,
     ) -> Result<()>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_escape_fields() {
+        assert_eq!(csv_escape("Alice"), "Alice");
+        assert_eq!(csv_escape("Alice, Bob"), "\"Alice, Bob\"");
+        assert_eq!(csv_escape("the \"main\" one"), "\"the \"\"main\"\" one\"");
+    }
+}
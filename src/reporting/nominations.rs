@@ -1,13 +1,14 @@
-use super::GenerateReport;
+use super::{csv_escape, GenerateReport};
 use crate::chain_api::Nomination;
 use crate::database::{ContextData, DatabaseReader};
-use crate::publishing::{GoogleStoragePayload, Publisher};
+use crate::publishing::{GoogleStoragePayload, Publisher, ReportMessage};
 use crate::{Context, Result};
 use chrono::{SecondsFormat, TimeZone, Utc};
 use std::marker::PhantomData;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+#[derive(Clone)]
 pub struct NominationReport(String);
 
 pub struct NominationReportGenerator<'a> {
@@ -89,9 +90,9 @@ where
                     .to_rfc3339(),
                 context.network.as_str(),
                 context.stash,
-                context.description,
+                csv_escape(&context.description),
                 data.stash_account_display.address,
-                data.stash_account_display.display,
+                csv_escape(&data.stash_account_display.display),
             ))
         }
 
@@ -125,3 +126,13 @@ impl From<NominationReport> for GoogleStoragePayload {
         }
     }
 }
+
+impl From<NominationReport> for ReportMessage {
+    fn from(val: NominationReport) -> Self {
+        ReportMessage {
+            title: "Nominations report".to_string(),
+            text: val.0,
+            content: serde_json::Value::Null,
+        }
+    }
+}
@@ -1,19 +1,65 @@
-use super::GenerateReport;
+use super::{csv_escape, serialize_token_value, GenerateReport};
 use crate::chain_api::RewardSlash;
 use crate::database::{ContextData, DatabaseReader};
-use crate::publishing::GoogleStoragePayload;
-use crate::publishing::Publisher;
-use crate::{BlockNumber, Context, Network, Result};
+use crate::publishing::{GoogleStoragePayload, Publisher, ReportMessage};
+use crate::{Balance, BlockNumber, Context, Network, Result, Timestamp, TokenAmount};
 use chrono::SecondsFormat;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-pub struct RewardSlashReport(String);
+#[derive(Clone)]
+pub struct RewardSlashReport {
+    name: String,
+    window: Option<u64>,
+    content: String,
+    /// Only set for the list of rewards/slashes, not for the totals.
+    summary: Option<RewardsReportSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RewardsReportSummary {
+    pub rewards: usize,
+    pub slashes: usize,
+    /// The totals per context and era, in the order of the contexts.
+    pub eras: Vec<EraTotals>,
+    /// Every slash, listed separately so none goes unnoticed.
+    pub slash_events: Vec<SlashEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EraTotals {
+    pub stash: String,
+    pub network: String,
+    pub description: String,
+    /// Unknown if not returned by Subscan.
+    pub era: Option<u32>,
+    /// Sum of the rewards.
+    #[serde(serialize_with = "serialize_token_value")]
+    pub rewarded: TokenAmount,
+    /// Sum of the slashes.
+    #[serde(serialize_with = "serialize_token_value")]
+    pub slashed: TokenAmount,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlashEvent {
+    pub stash: String,
+    pub network: String,
+    pub description: String,
+    pub era: Option<u32>,
+    pub block_num: BlockNumber,
+    #[serde(serialize_with = "serialize_token_value")]
+    pub amount: TokenAmount,
+    pub token: String,
+}
 
 pub struct RewardSlashReportGenerator<'a> {
     reader: DatabaseReader,
     contexts: Arc<RwLock<Vec<Context>>>,
+    window: Option<u64>,
     _p: PhantomData<&'a ()>,
 }
 
@@ -22,9 +68,16 @@ impl<'a> RewardSlashReportGenerator<'a> {
         RewardSlashReportGenerator {
             reader: db,
             contexts: contexts,
+            window: None,
             _p: PhantomData,
         }
     }
+    /// Only reports the rewards/slashes of the last `window` seconds, based
+    /// on their block timestamp.
+    pub fn with_window(mut self, window: Option<u64>) -> Self {
+        self.window = window;
+        self
+    }
 }
 
 #[async_trait]
@@ -42,15 +95,27 @@ where
     }
     async fn fetch_data(&self) -> Result<Option<Self::Data>> {
         let contexts = self.contexts.read().await;
-        let data = self
-            .reader
-            // Simply fetch everything as of now.
-            .fetch_rewards_slashes(
-                contexts.as_slice(),
-                BlockNumber::from(0),
-                BlockNumber::from(i64::MAX as u64),
-            )
-            .await?;
+        let data = match self.window {
+            Some(window) => {
+                self.reader
+                    .fetch_rewards_slashes_by_timestamp(
+                        contexts.as_slice(),
+                        Timestamp::from(Timestamp::now().as_secs().saturating_sub(window)),
+                        Timestamp::from(i64::MAX as u64),
+                    )
+                    .await?
+            }
+            None => {
+                self.reader
+                    // Simply fetch everything as of now.
+                    .fetch_rewards_slashes(
+                        contexts.as_slice(),
+                        BlockNumber::from(0),
+                        BlockNumber::from(i64::MAX as u64),
+                    )
+                    .await?
+            }
+        };
 
         if data.is_empty() {
             return Ok(None);
@@ -76,39 +141,7 @@ where
         );
 
         let contexts = self.contexts.read().await;
-        let mut report = String::from("Network,Block Number,Address,Description,Event,Value\n");
-
-        for entry in data {
-            // TODO: Improve performance here.
-            let context = contexts
-                .iter()
-                .find(|c| c.stash == entry.context_id.stash.clone().into_owned())
-                .ok_or(anyhow!("No context found while generating reports"))?;
-
-            let data = entry.data.as_ref();
-            let amount = data.amount.parse::<f64>()?;
-            let amount = match context.network {
-                Network::Kusama => amount / 1_000_000_000_000.0,
-                Network::Polkadot => amount / 10_000_000_000.0,
-            };
-
-            if amount == 0.0 {
-                debug!("Skipping reward of 0 for {:?}", context);
-                continue;
-            }
-
-            report.push_str(&format!(
-                "{},{},{},{},{},{}\n",
-                context.network.as_str(),
-                data.block_num,
-                context.stash,
-                context.description,
-                data.event_id,
-                amount,
-            ));
-        }
-
-        Ok(vec![RewardSlashReport(report)])
+        generate(contexts.as_slice(), data, self.window)
     }
     async fn publish(
         &self,
@@ -126,15 +159,390 @@ where
     }
 }
 
+/// Whether the event is a slash, e.g. `Slash` or `Slashed`, rather than a
+/// reward.
+fn is_slash(entry: &RewardSlash) -> bool {
+    entry.event_id.starts_with("Slash")
+}
+
+/// Creates the list of rewards/slashes (including the summary) and the totals
+/// per network.
+fn generate(
+    contexts: &[Context],
+    data: &[ContextData<RewardSlash>],
+    window: Option<u64>,
+) -> Result<Vec<RewardSlashReport>> {
+    let (report, totals) = render(contexts, data)?;
+    let summary = summarize(contexts, data)?;
+
+    // Reports over different windows must not overwrite each other.
+    let name = |name: &str| match window {
+        Some(window) => format!("{}_last_{}s.csv", name, window),
+        None => format!("{}.csv", name),
+    };
+
+    Ok(vec![
+        RewardSlashReport {
+            name: name("rewards_slashes"),
+            window: window,
+            content: report,
+            summary: Some(summary),
+        },
+        RewardSlashReport {
+            name: name("rewards_slashes_totals"),
+            window: window,
+            content: totals,
+            summary: None,
+        },
+    ])
+}
+
+/// Sums up the rewards and slashes of each context per era, and lists the
+/// slashes.
+fn summarize(
+    contexts: &[Context],
+    data: &[ContextData<RewardSlash>],
+) -> Result<RewardsReportSummary> {
+    let mut rewards = 0;
+    let mut slash_events = vec![];
+    // The rewarded and slashed amounts, by context index and era.
+    let mut eras: BTreeMap<(usize, Option<u32>), (Balance, Balance)> = BTreeMap::new();
+
+    for entry in data {
+        let idx = contexts
+            .iter()
+            .position(|c| c.id() == entry.context_id)
+            .ok_or(anyhow!("No context found while generating reports"))?;
+        let context = &contexts[idx];
+
+        let data = entry.data.as_ref();
+        let amount = data.amount.parse::<Balance>()?;
+        if amount == Balance::default() {
+            continue;
+        }
+
+        let totals = eras.entry((idx, data.era)).or_default();
+        if is_slash(data) {
+            totals.1 = totals.1.checked_add(amount)?;
+            slash_events.push(SlashEvent {
+                stash: context.stash.clone(),
+                network: context.network.as_str().to_string(),
+                description: context.description.clone(),
+                era: data.era,
+                block_num: data.block_num,
+                amount: amount.in_tokens(context.network),
+                token: context.network.token_symbol().to_string(),
+            });
+        } else {
+            totals.0 = totals.0.checked_add(amount)?;
+            rewards += 1;
+        }
+    }
+
+    Ok(RewardsReportSummary {
+        rewards: rewards,
+        slashes: slash_events.len(),
+        eras: eras
+            .into_iter()
+            .map(|((idx, era), (rewarded, slashed))| {
+                let context = &contexts[idx];
+
+                EraTotals {
+                    stash: context.stash.clone(),
+                    network: context.network.as_str().to_string(),
+                    description: context.description.clone(),
+                    era: era,
+                    rewarded: rewarded.in_tokens(context.network),
+                    slashed: slashed.in_tokens(context.network),
+                    token: context.network.token_symbol().to_string(),
+                }
+            })
+            .collect(),
+        slash_events: slash_events,
+    })
+}
+
+/// Renders the list of rewards/slashes and the rewarded and slashed totals
+/// per network. Amounts of different networks are never summed together.
+fn render(contexts: &[Context], data: &[ContextData<RewardSlash>]) -> Result<(String, String)> {
+    let mut report = String::from("Network,Block Number,Address,Description,Event,Value,Token\n");
+    // The rewarded and slashed amounts per network.
+    let mut totals: Vec<(Network, Balance, Balance)> = vec![];
+
+    for entry in data {
+        // TODO: Improve performance here.
+        let context = contexts
+            .iter()
+            .find(|c| c.id() == entry.context_id)
+            .ok_or(anyhow!("No context found while generating reports"))?;
+
+        let data = entry.data.as_ref();
+        let amount = data.amount.parse::<Balance>()?;
+
+        if amount == Balance::default() {
+            debug!("Skipping reward of 0 for {:?}", context);
+            continue;
+        }
+
+        let tokens = amount.in_tokens(context.network);
+        report.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            context.network.as_str(),
+            data.block_num,
+            context.stash,
+            csv_escape(&context.description),
+            data.event_id,
+            tokens.value(),
+            tokens.symbol(),
+        ));
+
+        let idx = match totals
+            .iter()
+            .position(|(network, _, _)| *network == context.network)
+        {
+            Some(idx) => idx,
+            None => {
+                totals.push((context.network, Balance::default(), Balance::default()));
+                totals.len() - 1
+            }
+        };
+
+        let (_, rewarded, slashed) = &mut totals[idx];
+        if is_slash(data) {
+            *slashed = slashed.checked_add(amount)?;
+        } else {
+            *rewarded = rewarded.checked_add(amount)?;
+        }
+    }
+
+    let mut summary = String::from("Network,Rewarded,Slashed,Token\n");
+    for (network, rewarded, slashed) in totals {
+        summary.push_str(&format!(
+            "{},{},{},{}\n",
+            network.as_str(),
+            rewarded.in_tokens(network).value(),
+            slashed.in_tokens(network).value(),
+            network.token_symbol()
+        ));
+    }
+
+    Ok((report, summary))
+}
+
 impl From<RewardSlashReport> for GoogleStoragePayload {
     fn from(val: RewardSlashReport) -> Self {
         let _date = chrono::offset::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
 
         GoogleStoragePayload {
-            name: format!("rewards_slashes.csv"),
+            name: val.name,
             mime_type: "application/vnd.google-apps.document".to_string(),
-            body: val.0.into_bytes(),
+            body: val.content.into_bytes(),
             is_public: false,
         }
     }
 }
+
+impl RewardsReportSummary {
+    /// Renders the summary as human readable text, starting with the slashes.
+    pub fn to_text(&self) -> String {
+        let era = |era: Option<u32>| {
+            era.map(|e| e.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+
+        let mut text = format!("{} rewards, {} slashes\n", self.rewards, self.slashes);
+
+        if !self.slash_events.is_empty() {
+            text.push_str("\nSLASHES:\n");
+            for slash in &self.slash_events {
+                text.push_str(&format!(
+                    "{} ({}): {} slashed in era {} (block {})\n",
+                    slash.description,
+                    slash.network,
+                    slash.amount,
+                    era(slash.era),
+                    slash.block_num,
+                ));
+            }
+            text.push('\n');
+        }
+
+        for totals in &self.eras {
+            text.push_str(&format!(
+                "{} ({}), era {}: {} rewarded, {} slashed\n",
+                totals.description,
+                totals.network,
+                era(totals.era),
+                totals.rewarded,
+                totals.slashed,
+            ));
+        }
+
+        text
+    }
+}
+
+impl From<RewardSlashReport> for ReportMessage {
+    fn from(val: RewardSlashReport) -> Self {
+        let mut title = match val.window {
+            Some(window) => format!("Rewards/slashes report (last {}s, {})", window, val.name),
+            None => format!("Rewards/slashes report ({})", val.name),
+        };
+
+        match val.summary {
+            Some(summary) => {
+                if summary.slashes > 0 {
+                    title = format!("[{} SLASH(ES)] {}", summary.slashes, title);
+                }
+
+                ReportMessage {
+                    title: title,
+                    text: summary.to_text(),
+                    content: serde_json::to_value(&summary).unwrap_or_default(),
+                }
+            }
+            None => ReportMessage {
+                title: title,
+                text: val.content,
+                content: serde_json::Value::Null,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn entry(context: &Context, amount: &str) -> ContextData<'static, RewardSlash> {
+        ContextData {
+            context_id: crate::ContextId {
+                stash: Cow::Owned(context.stash.clone()),
+                network: context.network,
+            },
+            tags: Default::default(),
+            description: Default::default(),
+            timestamp: Default::default(),
+            data: Cow::Owned(RewardSlash {
+                amount: amount.to_string(),
+                event_id: "Reward".to_string(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn render_mixed_networks() {
+        let alice = Context::alice();
+        let mut bob = Context::bob();
+        bob.network = Network::Kusama;
+
+        let data = vec![
+            entry(&alice, "15000000000"),
+            entry(&alice, "5000000000"),
+            entry(&bob, "2500000000000"),
+        ];
+
+        let reports = generate(&[alice.clone(), bob.clone()], &data, None).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].name, "rewards_slashes.csv");
+
+        let lines: Vec<&str> = reports[0].content.lines().collect();
+        assert_eq!(
+            lines[1],
+            format!("polkadot,0,{},,Reward,1.5,DOT", alice.stash)
+        );
+        assert_eq!(
+            lines[2],
+            format!("polkadot,0,{},,Reward,0.5,DOT", alice.stash)
+        );
+        assert_eq!(lines[3], format!("kusama,0,{},,Reward,2.5,KSM", bob.stash));
+
+        // Totals are per network.
+        assert_eq!(
+            reports[1].content,
+            "Network,Rewarded,Slashed,Token\npolkadot,2,0,DOT\nkusama,2.5,0,KSM\n"
+        );
+    }
+
+    #[test]
+    fn render_rewards_and_slashes() {
+        let alice = Context::alice();
+
+        let mut slash = entry(&alice, "5000000000");
+        slash.data.to_mut().event_id = "Slashed".to_string();
+        let data = vec![entry(&alice, "15000000000"), slash];
+
+        // Slashes are not added to the rewards.
+        let (_, totals) = render(&[alice], &data).unwrap();
+        assert_eq!(
+            totals,
+            "Network,Rewarded,Slashed,Token\npolkadot,1.5,0.5,DOT\n"
+        );
+    }
+
+    #[test]
+    fn summarize_eras() {
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let era_entry = |event: &str, era: Option<u32>, amount: &str| {
+            let mut entry = entry(&alice, amount);
+            let data = entry.data.to_mut();
+            data.event_id = event.to_string();
+            data.era = era;
+            data.block_num = BlockNumber::from(100);
+            entry
+        };
+
+        let data = vec![
+            era_entry("Rewarded", Some(1), "10000000000"),
+            era_entry("Rewarded", Some(1), "5000000000"),
+            era_entry("Slashed", Some(1), "20000000000"),
+            era_entry("Rewarded", Some(2), "10000000000"),
+            era_entry("Rewarded", None, "0"),
+        ];
+
+        let summary = summarize(&[alice.clone()], &data).unwrap();
+        assert_eq!(summary.rewards, 3);
+        assert_eq!(summary.slashes, 1);
+        assert_eq!(
+            summary
+                .eras
+                .iter()
+                .map(|t| (t.era, t.rewarded.to_string(), t.slashed.value()))
+                .collect::<Vec<(Option<u32>, String, String)>>(),
+            vec![
+                (Some(1), "1.5 DOT".to_string(), "2".to_string()),
+                (Some(2), "1 DOT".to_string(), "0".to_string())
+            ]
+        );
+        assert_eq!(
+            summary.slash_events,
+            vec![SlashEvent {
+                stash: alice.stash.clone(),
+                network: "polkadot".to_string(),
+                description: "Alice".to_string(),
+                era: Some(1),
+                block_num: BlockNumber::from(100),
+                amount: Balance::from_plancks(20_000_000_000, Network::Polkadot),
+                token: "DOT".to_string(),
+            }]
+        );
+
+        // Slashes are flagged in the title and listed first.
+        let reports = generate(&[alice], &data, Some(3_600)).unwrap();
+        assert_eq!(reports[0].name, "rewards_slashes_last_3600s.csv");
+        assert_eq!(reports[1].name, "rewards_slashes_totals_last_3600s.csv");
+
+        let message = ReportMessage::from(reports.into_iter().next().unwrap());
+        assert_eq!(
+            message.title,
+            "[1 SLASH(ES)] Rewards/slashes report (last 3600s, rewards_slashes_last_3600s.csv)"
+        );
+        assert!(message
+            .text
+            .starts_with("3 rewards, 1 slashes\n\nSLASHES:\nAlice (polkadot): 2 DOT slashed"));
+    }
+}
@@ -1,20 +1,73 @@
-use super::GenerateReport;
+use super::{csv_escape, serialize_token_value, GenerateReport, ReportFormat};
 use crate::chain_api::Transfer;
 use crate::database::{ContextData, DatabaseReader};
-use crate::publishing::GoogleStoragePayload;
-use crate::publishing::Publisher;
-use crate::{Context, Result, Timestamp};
-use chrono::SecondsFormat;
+use crate::publishing::{GoogleStoragePayload, Publisher, ReportMessage};
+use crate::{Balance, BlockNumber, Context, Result, Timestamp, TokenAmount};
+use chrono::{SecondsFormat, TimeZone, Utc};
 use std::marker::PhantomData;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Number of largest transfers listed in the summary, per network.
+const TOP_TRANSFERS: usize = 10;
+
 #[derive(Debug, Clone)]
-pub struct TransferReport(String);
+pub struct TransferReport {
+    window: Option<u64>,
+    content: String,
+    summary: TransferReportSummary,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransferReportSummary {
+    /// Timestamp of the earliest reported transfer.
+    pub first: Option<Timestamp>,
+    /// Timestamp of the latest reported transfer.
+    pub last: Option<Timestamp>,
+    pub transfers: usize,
+    pub contexts: Vec<ContextTransferTotals>,
+    /// The largest transfers per network, in descending order.
+    pub largest: Vec<LargestTransfer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextTransferTotals {
+    pub stash: String,
+    pub network: String,
+    pub description: String,
+    pub transfers: usize,
+    /// Sum of the received amounts.
+    #[serde(serialize_with = "serialize_token_value")]
+    pub incoming: TokenAmount,
+    /// Sum of the sent amounts.
+    #[serde(serialize_with = "serialize_token_value")]
+    pub outgoing: TokenAmount,
+    /// Sum of the fees paid on the sent transfers.
+    #[serde(serialize_with = "serialize_token_value")]
+    pub fees: TokenAmount,
+    /// The received minus the sent amounts and the fees, in tokens. Prefixed
+    /// with `-` if more was spent than received.
+    pub net: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LargestTransfer {
+    pub network: String,
+    pub description: String,
+    pub from: String,
+    pub to: String,
+    #[serde(serialize_with = "serialize_token_value")]
+    pub amount: TokenAmount,
+    pub token: String,
+    pub block_num: BlockNumber,
+    pub extrinsic_index: String,
+}
 
 pub struct TransferReportGenerator<'a> {
     reader: DatabaseReader,
     contexts: Arc<RwLock<Vec<Context>>>,
+    window: Option<u64>,
     _p: PhantomData<&'a ()>,
 }
 
@@ -23,9 +76,15 @@ impl<'a> TransferReportGenerator<'a> {
         TransferReportGenerator {
             reader: db,
             contexts: contexts,
+            window: None,
             _p: PhantomData,
         }
     }
+    /// Only reports the transfers of the last `window` seconds.
+    pub fn with_window(mut self, window: Option<u64>) -> Self {
+        self.window = window;
+        self
+    }
 }
 
 #[async_trait]
@@ -42,15 +101,16 @@ where
         "TransferReportGenerator"
     }
     async fn fetch_data(&self) -> Result<Option<Self::Data>> {
+        let from = match self.window {
+            Some(window) => Timestamp::from(Timestamp::now().as_secs().saturating_sub(window)),
+            // Simply fetch everything as of now.
+            None => Timestamp::from(0),
+        };
+
         let contexts = self.contexts.read().await;
         let data = self
             .reader
-            // Simply fetch everything as of now.
-            .fetch_transfers(
-                contexts.as_slice(),
-                Timestamp::from(0),
-                Timestamp::from(i64::MAX as u64),
-            )
+            .fetch_transfers(contexts.as_slice(), from, Timestamp::from(i64::MAX as u64))
             .await?;
 
         if data.is_empty() {
@@ -77,34 +137,14 @@ where
         );
 
         let contexts = self.contexts.read().await;
+        let report = render(ReportFormat::Csv, contexts.as_slice(), data)?;
+        let summary = summarize(contexts.as_slice(), data, TOP_TRANSFERS)?;
 
-        // List all transfers.
-        let mut report =
-            String::from("Network,Block Number,Block Timestamp,From,Description,To,Amount,Extrinsic Index,Success\n");
-
-        for entry in data {
-            // TODO: Improve performance here.
-            let context = contexts
-                .iter()
-                .find(|c| c.stash == entry.context_id.stash.clone().into_owned())
-                .ok_or(anyhow!("No context found while generating reports"))?;
-
-            let data = entry.data.as_ref();
-            report.push_str(&format!(
-                "{},{},{},{},{},{},{},{},{}\n",
-                context.network.as_str(),
-                data.block_num,
-                data.block_timestamp,
-                data.from,
-                context.description,
-                data.to,
-                data.amount,
-                data.extrinsic_index,
-                data.success,
-            ));
-        }
-
-        Ok(vec![TransferReport(report)])
+        Ok(vec![TransferReport {
+            window: self.window,
+            content: report,
+            summary: summary,
+        }])
     }
     async fn publish(
         &self,
@@ -112,25 +152,522 @@ where
         info: <T as Publisher>::Info,
         report: Self::Report,
     ) -> Result<()> {
+        let transfers = report.summary.transfers;
+        let accounts = report.summary.contexts.len();
         publisher
             .upload_data(info, <T as Publisher>::Data::from(report))
             .await?;
 
-        info!("Uploaded new report");
+        info!(
+            "Uploaded new report ({} transfers of {} accounts)",
+            transfers, accounts
+        );
 
         Ok(())
     }
 }
 
+#[derive(Serialize)]
+struct TransferRow<'a> {
+    network: &'a str,
+    block_num: BlockNumber,
+    block_timestamp: Timestamp,
+    from: &'a str,
+    description: &'a str,
+    to: &'a str,
+    amount: &'a str,
+    token: &'a str,
+    fee: Option<String>,
+    extrinsic_index: String,
+    success: bool,
+}
+
+/// Summarizes the transfers: the totals per context, the time span covered and
+/// the largest transfers per network.
+pub fn summarize(
+    contexts: &[Context],
+    data: &[ContextData<Transfer>],
+    top: usize,
+) -> Result<TransferReportSummary> {
+    // Totals in plancks, in the order of the contexts.
+    let mut totals: Vec<(&Context, usize, Balance, Balance, Balance)> = vec![];
+    let mut largest: Vec<(&Context, &Transfer, Balance)> = vec![];
+    let mut first: Option<Timestamp> = None;
+    let mut last: Option<Timestamp> = None;
+
+    for entry in data {
+        let context = contexts
+            .iter()
+            .find(|c| c.id() == entry.context_id)
+            .ok_or(anyhow!("No context found while generating reports"))?;
+
+        let transfer = entry.data.as_ref();
+        let amount = Balance::parse_tokens(&transfer.amount, context.network.decimals())?;
+
+        let idx = match totals.iter().position(|(c, ..)| *c == context) {
+            Some(idx) => idx,
+            None => {
+                totals.push((
+                    context,
+                    0,
+                    Balance::default(),
+                    Balance::default(),
+                    Balance::default(),
+                ));
+                totals.len() - 1
+            }
+        };
+
+        let (_, count, incoming, outgoing, fees) = &mut totals[idx];
+        *count += 1;
+        // A transfer to itself is both.
+        if transfer.to == context.stash {
+            *incoming = incoming.checked_add(amount)?;
+        }
+        if transfer.from == context.stash {
+            *outgoing = outgoing.checked_add(amount)?;
+            if let Some(fee) = transfer.fee {
+                *fees = fees.checked_add(fee)?;
+            }
+        }
+
+        let timestamp = transfer.block_timestamp;
+        first = Some(first.map_or(timestamp, |t| if timestamp < t { timestamp } else { t }));
+        last = Some(last.map_or(timestamp, |t| if timestamp > t { timestamp } else { t }));
+
+        largest.push((context, transfer, amount));
+    }
+
+    // Amounts of different networks are not comparable.
+    largest.sort_by(|(a, _, a_amount), (b, _, b_amount)| {
+        a.network
+            .as_str()
+            .cmp(b.network.as_str())
+            .then(b_amount.cmp(a_amount))
+    });
+
+    let mut per_network: Vec<(&str, usize)> = vec![];
+    let largest = largest
+        .into_iter()
+        .filter(|(context, ..)| {
+            let network = context.network.as_str();
+            match per_network.iter_mut().find(|(n, _)| *n == network) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count <= top
+                }
+                None => {
+                    per_network.push((network, 1));
+                    top > 0
+                }
+            }
+        })
+        .map(|(context, transfer, amount)| LargestTransfer {
+            network: context.network.as_str().to_string(),
+            description: context.description.clone(),
+            from: transfer.from.clone(),
+            to: transfer.to.clone(),
+            amount: amount.in_tokens(context.network),
+            token: context.network.token_symbol().to_string(),
+            block_num: transfer.block_num,
+            extrinsic_index: transfer.extrinsic_index.to_string(),
+        })
+        .collect();
+
+    let contexts = totals
+        .into_iter()
+        .map(|(context, count, incoming, outgoing, fees)| {
+            let network = context.network;
+            let spent = outgoing.checked_add(fees)?;
+            let net = if incoming >= spent {
+                Balance::from_plancks(incoming.as_plancks() - spent.as_plancks(), network).value()
+            } else {
+                format!(
+                    "-{}",
+                    Balance::from_plancks(spent.as_plancks() - incoming.as_plancks(), network)
+                        .value()
+                )
+            };
+
+            Ok(ContextTransferTotals {
+                stash: context.stash.clone(),
+                network: context.network.as_str().to_string(),
+                description: context.description.clone(),
+                transfers: count,
+                incoming: incoming.in_tokens(network),
+                outgoing: outgoing.in_tokens(network),
+                fees: fees.in_tokens(network),
+                net: net,
+                token: context.network.token_symbol().to_string(),
+            })
+        })
+        .collect::<Result<Vec<ContextTransferTotals>>>()?;
+
+    Ok(TransferReportSummary {
+        first: first,
+        last: last,
+        transfers: data.len(),
+        contexts: contexts,
+        largest: largest,
+    })
+}
+
+/// Renders the list of transfers in the given format.
+pub fn render(
+    format: ReportFormat,
+    contexts: &[Context],
+    data: &[ContextData<Transfer>],
+) -> Result<String> {
+    let mut rows = vec![];
+    for entry in data {
+        // TODO: Improve performance here.
+        let context = contexts
+            .iter()
+            .find(|c| c.id() == entry.context_id)
+            .ok_or(anyhow!("No context found while generating reports"))?;
+
+        let data = entry.data.as_ref();
+        rows.push(TransferRow {
+            network: context.network.as_str(),
+            block_num: data.block_num,
+            block_timestamp: data.block_timestamp,
+            from: &data.from,
+            description: &context.description,
+            to: &data.to,
+            amount: &data.amount,
+            token: context.network.token_symbol(),
+            fee: data.fee.map(|fee| fee.in_tokens(context.network).value()),
+            extrinsic_index: data.extrinsic_index.to_string(),
+            success: data.success,
+        });
+    }
+
+    let report = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)?,
+        ReportFormat::Csv => {
+            let mut report = String::from("Network,Block Number,Block Timestamp,From,Description,To,Amount,Token,Fee,Extrinsic Index,Success\n");
+
+            for row in &rows {
+                report.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{},{}\n",
+                    row.network,
+                    row.block_num,
+                    row.block_timestamp,
+                    row.from,
+                    csv_escape(row.description),
+                    row.to,
+                    row.amount,
+                    row.token,
+                    row.fee.as_deref().unwrap_or(""),
+                    row.extrinsic_index,
+                    row.success,
+                ));
+            }
+
+            report
+        }
+        ReportFormat::Text => {
+            let mut report = String::new();
+
+            for row in &rows {
+                report.push_str(&format!(
+                    "[{}] {} ({}): {} -> {}, {} {} (block {}{})\n",
+                    Utc.timestamp(row.block_timestamp.as_secs() as i64, 0)
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                    row.description,
+                    row.network,
+                    row.from,
+                    row.to,
+                    row.amount,
+                    row.token,
+                    row.block_num,
+                    if row.success { "" } else { ", failed" },
+                ));
+            }
+
+            report
+        }
+    };
+
+    Ok(report)
+}
+
 impl From<TransferReport> for GoogleStoragePayload {
     fn from(val: TransferReport) -> Self {
         let _date = chrono::offset::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
 
+        // Reports over different windows must not overwrite each other.
+        let name = match val.window {
+            Some(window) => format!("report_transfer_last_{}s.csv", window),
+            None => format!("report_transfer.csv"),
+        };
+
         GoogleStoragePayload {
-            name: format!("report_transfer.csv"),
+            name: name,
             mime_type: "application/vnd.google-apps.document".to_string(),
-            body: val.0.into_bytes(),
+            body: val.content.into_bytes(),
             is_public: false,
         }
     }
 }
+
+impl TransferReportSummary {
+    /// Renders the summary as human readable text.
+    pub fn to_text(&self) -> String {
+        let date = |timestamp: Option<Timestamp>| {
+            timestamp
+                .map(|t| {
+                    Utc.timestamp(t.as_secs() as i64, 0)
+                        .to_rfc3339_opts(SecondsFormat::Secs, true)
+                })
+                .unwrap_or_else(|| "-".to_string())
+        };
+
+        let mut text = format!(
+            "{} transfers from {} to {}\n",
+            self.transfers,
+            date(self.first),
+            date(self.last)
+        );
+
+        for totals in &self.contexts {
+            text.push_str(&format!(
+                "{} ({}): {} transfers, {} in, {} out, {} fees, {} {} net\n",
+                totals.description,
+                totals.network,
+                totals.transfers,
+                totals.incoming,
+                totals.outgoing,
+                totals.fees,
+                totals.net,
+                totals.token,
+            ));
+        }
+
+        if !self.largest.is_empty() {
+            text.push_str("\nLargest transfers:\n");
+            for transfer in &self.largest {
+                text.push_str(&format!(
+                    "{} ({}): {} -> {} (block {})\n",
+                    transfer.amount,
+                    transfer.description,
+                    transfer.from,
+                    transfer.to,
+                    transfer.block_num,
+                ));
+            }
+        }
+
+        text
+    }
+}
+
+impl From<TransferReport> for ReportMessage {
+    fn from(val: TransferReport) -> Self {
+        let title = match val.window {
+            Some(window) => format!("Transfer report (last {}s)", window),
+            None => "Transfer report".to_string(),
+        };
+
+        ReportMessage {
+            title: title,
+            text: val.summary.to_text(),
+            content: serde_json::to_value(&val.summary).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn seed() -> (Vec<Context>, Vec<ContextData<'static, Transfer>>) {
+        let mut alice = Context::alice();
+        alice.description = "Alice".to_string();
+
+        let data = (0..2)
+            .map(|idx| ContextData {
+                context_id: crate::ContextId {
+                    stash: Cow::Owned(alice.stash.clone()),
+                    network: alice.network,
+                },
+                tags: Default::default(),
+                description: Default::default(),
+                timestamp: Default::default(),
+                data: Cow::Owned(Transfer {
+                    amount: format!("{}.5", idx),
+                    block_num: BlockNumber::from(100 + idx),
+                    block_timestamp: Timestamp::from(1_704_067_200 + idx * 60),
+                    extrinsic_index: format!("{}-1", 100 + idx).into(),
+                    fee: if idx == 0 {
+                        Some(Balance::from(156_000_000))
+                    } else {
+                        None
+                    },
+                    from: alice.stash.clone(),
+                    to: "bob".to_string(),
+                    success: true,
+                    ..Default::default()
+                }),
+            })
+            .collect();
+
+        (vec![alice], data)
+    }
+
+    #[test]
+    fn render_csv() {
+        let (contexts, data) = seed();
+        let report = render(ReportFormat::Csv, &contexts, &data).unwrap();
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            format!(
+                "polkadot,100,1704067200,{},Alice,bob,0.5,DOT,0.0156,100-1,true",
+                contexts[0].stash
+            )
+        );
+        assert_eq!(
+            lines[2],
+            format!(
+                "polkadot,101,1704067260,{},Alice,bob,1.5,DOT,,101-1,true",
+                contexts[0].stash
+            )
+        );
+    }
+
+    #[test]
+    fn render_csv_escapes_description() {
+        let (mut contexts, data) = seed();
+        contexts[0].description = "Alice, \"cold\" wallet".to_string();
+
+        let report = render(ReportFormat::Csv, &contexts, &data).unwrap();
+        let line = report.lines().nth(1).unwrap();
+        assert!(line.contains(",\"Alice, \"\"cold\"\" wallet\",bob,"));
+    }
+
+    #[test]
+    fn render_json() {
+        let (contexts, data) = seed();
+        let report = render(ReportFormat::Json, &contexts, &data).unwrap();
+
+        let rows: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+        assert_eq!(rows[0]["amount"], "0.5");
+        assert_eq!(rows[0]["fee"], "0.0156");
+        assert_eq!(rows[0]["block_num"], 100);
+        assert_eq!(rows[1]["fee"], serde_json::Value::Null);
+        assert_eq!(rows[1]["token"], "DOT");
+    }
+
+    #[test]
+    fn summarize_transfers() {
+        let (mut contexts, mut data) = seed();
+        let mut bob = Context::bob();
+        bob.description = "Bob".to_string();
+        contexts.push(bob.clone());
+
+        // An incoming transfer of Alice, also tracked for Bob as outgoing.
+        for context in &[&contexts[0], &bob] {
+            data.push(ContextData {
+                context_id: crate::ContextId {
+                    stash: Cow::Owned(context.stash.clone()),
+                    network: context.network,
+                },
+                tags: Default::default(),
+                description: Default::default(),
+                timestamp: Default::default(),
+                data: Cow::Owned(Transfer {
+                    amount: "3".to_string(),
+                    block_num: BlockNumber::from(99),
+                    block_timestamp: Timestamp::from(1_704_067_000),
+                    extrinsic_index: "99-1".to_string().into(),
+                    from: bob.stash.clone(),
+                    to: contexts[0].stash.clone(),
+                    success: true,
+                    ..Default::default()
+                }),
+            });
+        }
+
+        let summary = summarize(&contexts, &data, 2).unwrap();
+
+        assert_eq!(summary.transfers, 4);
+        assert_eq!(summary.first, Some(Timestamp::from(1_704_067_000)));
+        assert_eq!(summary.last, Some(Timestamp::from(1_704_067_260)));
+
+        assert_eq!(summary.contexts.len(), 2);
+        let alice = &summary.contexts[0];
+        assert_eq!(alice.description, "Alice");
+        assert_eq!(alice.transfers, 3);
+        assert_eq!(alice.incoming.value(), "3");
+        assert_eq!(alice.outgoing.value(), "2");
+        assert_eq!(alice.fees.to_string(), "0.0156 DOT");
+        assert_eq!(alice.net, "0.9844");
+        assert_eq!(alice.token, "DOT");
+        let bob = &summary.contexts[1];
+        assert_eq!(bob.transfers, 1);
+        assert_eq!(bob.incoming.value(), "0");
+        assert_eq!(bob.outgoing.value(), "3");
+        assert_eq!(bob.fees.value(), "0");
+        assert_eq!(bob.net, "-3");
+
+        // Limited to the top two.
+        let amounts: Vec<String> = summary.largest.iter().map(|t| t.amount.value()).collect();
+        assert_eq!(amounts, vec!["3", "3"]);
+
+        let summary = summarize(&contexts, &data, 3).unwrap();
+        assert_eq!(summary.largest[2].amount.to_string(), "1.5 DOT");
+        assert_eq!(summary.largest[2].block_num, BlockNumber::from(101));
+
+        // Invalid amounts are not silently skipped.
+        data[0].data.to_mut().amount = "1,5".to_string();
+        assert!(summarize(&contexts, &data, 3).is_err());
+    }
+
+    #[test]
+    fn report_message() {
+        let (contexts, data) = seed();
+        let report = TransferReport {
+            window: Some(3_600),
+            content: String::new(),
+            summary: summarize(&contexts, &data, 1).unwrap(),
+        };
+
+        let message = ReportMessage::from(report);
+        assert_eq!(message.title, "Transfer report (last 3600s)");
+        assert_eq!(
+            message.text,
+            format!(
+                "2 transfers from 2024-01-01T00:00:00Z to 2024-01-01T00:01:00Z\n\
+                 Alice (polkadot): 2 transfers, 0 DOT in, 2 DOT out, 0.0156 DOT fees, \
+                 -2.0156 DOT net\n\
+                 \n\
+                 Largest transfers:\n\
+                 1.5 DOT (Alice): {} -> bob (block 101)\n",
+                contexts[0].stash
+            )
+        );
+        assert_eq!(message.content["transfers"], 2);
+        assert_eq!(message.content["contexts"][0]["fees"], "0.0156");
+        assert_eq!(message.content["largest"][0]["amount"], "1.5");
+    }
+
+    #[test]
+    fn render_text() {
+        let (contexts, data) = seed();
+        let report = render(ReportFormat::Text, &contexts, &data).unwrap();
+
+        assert_eq!(
+            report,
+            format!(
+                "[2024-01-01T00:00:00Z] Alice (polkadot): {stash} -> bob, 0.5 DOT (block 100)\n\
+                 [2024-01-01T00:01:00Z] Alice (polkadot): {stash} -> bob, 1.5 DOT (block 101)\n",
+                stash = contexts[0].stash
+            )
+        );
+    }
+}